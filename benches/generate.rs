@@ -0,0 +1,84 @@
+#![feature(test)]
+
+extern crate test;
+extern crate thruster;
+
+use std::fmt::Write;
+use test::Bencher;
+use thruster::OpenApi;
+use thruster::process::extract_entrypoints;
+
+// Builds a spec with `count` paths, each with a GET and a POST operation
+// whose responses are inline (anonymous) object schemas
+fn large_spec(count: usize) -> String {
+    let mut yaml = String::from(
+        "openapi: \"3.0.0\"\ninfo:\n  title: large\n  version: 1\npaths:\n",
+    );
+    for ix in 0..count {
+        write!(
+            yaml,
+            r#"
+  /things{ix}/{{thingId}}:
+    get:
+      operationId: getThing{ix}
+      parameters:
+        - name: thingId
+          in: path
+          required: true
+          schema:
+            type: integer
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        200:
+          description: A thing
+          content:
+            application/json:
+              schema:
+                properties:
+                  id: {{type: integer}}
+                  name: {{type: string}}
+                  tags: {{type: array, items: {{type: string}}}}
+    post:
+      operationId: postThing{ix}
+      parameters:
+        - name: thingId
+          in: path
+          required: true
+          schema:
+            type: integer
+      responses:
+        201:
+          description: Created
+          content:
+            application/json:
+              schema:
+                properties:
+                  id: {{type: integer}}
+                  created: {{type: string}}
+"#,
+            ix = ix
+        ).unwrap();
+    }
+    yaml
+}
+
+#[bench]
+fn bench_extract_entrypoints(b: &mut Bencher) {
+    let spec = OpenApi::from_string(&large_spec(300)).unwrap();
+    b.iter(|| extract_entrypoints(&spec));
+}
+
+#[bench]
+fn bench_build_template_args(b: &mut Bencher) {
+    let spec = OpenApi::from_string(&large_spec(300)).unwrap();
+    let entrypoints = extract_entrypoints(&spec);
+    b.iter(|| {
+        entrypoints
+            .iter()
+            .map(|entry| entry.build_template_args())
+            .collect::<Vec<_>>()
+    });
+}
@@ -1,16 +1,124 @@
 extern crate thruster;
+#[macro_use]
+extern crate clap;
+
+use std::path::Path;
 
+use clap::{App, Arg};
 use thruster::*;
 
 #[macro_use]
 extern crate error_chain;
 
-
 fn run() -> Result<()> {
-    let src_path = "/home/alex/scratch/anywhere/src";
-    let spec = OpenApi::from_file("example_apis/petstore.yaml")?;
-    // bootstrap(spec, dir_path)?;
-    generate_sources(&spec, src_path)?;
+    let matches = App::new("thruster")
+        .version(crate_version!())
+        .about("Generate a Rocket or actix-web webserver from an OpenAPI specification")
+        .arg(
+            Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .value_name("SPEC")
+                .help("Path to the OpenAPI (or Swagger 2.0) specification, as YAML or JSON")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("DIR")
+                .help("Directory to write to: the crate's `src` directory, or the new crate when bootstrapping")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("templates")
+                .long("templates")
+                .value_name("DIR")
+                .help("Directory of Handlebars templates overriding the bundled ones"),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .value_name("PORT")
+                .help("Port the generated server listens on, unless ROCKET_PORT is set"),
+        )
+        .arg(
+            Arg::with_name("address")
+                .long("address")
+                .value_name("ADDRESS")
+                .help("Address the generated server binds to, unless ROCKET_ADDRESS is set"),
+        )
+        .arg(
+            Arg::with_name("framework")
+                .long("framework")
+                .value_name("FRAMEWORK")
+                .possible_values(&["rocket", "actix"])
+                .default_value("rocket")
+                .help("Web framework to generate the server for"),
+        )
+        .arg(
+            Arg::with_name("bootstrap")
+                .long("bootstrap")
+                .help("Create a new crate at the output path rather than generating sources into it"),
+        )
+        .arg(
+            Arg::with_name("skip-tool-check")
+                .long("skip-tool-check")
+                .requires("bootstrap")
+                .help("Don't check that cargo, cargo fmt and cargo add are installed before bootstrapping"),
+        )
+        .arg(
+            Arg::with_name("latest-dependencies")
+                .long("latest-dependencies")
+                .requires("bootstrap")
+                .help("Add the latest versions of dependencies with cargo add, rather than known-good ones"),
+        )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .requires("bootstrap")
+                .help("Replace the output directory when bootstrapping, even if it is not empty"),
+        )
+        .get_matches();
+
+    // Both are required, so clap has already rejected their absence
+    let input = Path::new(matches.value_of("input").unwrap());
+    let output = Path::new(matches.value_of("output").unwrap());
+    if !input.is_file() {
+        bail!("Input spec '{}' does not exist or is not a file", input.display());
+    }
+
+    let port = match matches.value_of("port") {
+        Some(port) => Some(port.parse::<u16>().chain_err(|| format!("Invalid port '{}'", port))?),
+        None => None,
+    };
+    // clap has already rejected any other value
+    let framework = match matches.value_of("framework") {
+        Some("actix") => Framework::Actix,
+        _ => Framework::Rocket,
+    };
+    let config = Config {
+        framework,
+        force: matches.is_present("force"),
+        skip_tool_check: matches.is_present("skip-tool-check"),
+        latest_dependencies: matches.is_present("latest-dependencies"),
+        template_dir: matches.value_of("templates").map(|dir| dir.into()),
+        port,
+        address: matches.value_of("address").map(|address| address.into()),
+        ..Config::default()
+    };
+    if matches.is_present("bootstrap") {
+        bootstrap(input, output, &config)
+            .chain_err(|| format!("Failed to bootstrap crate at '{}'", output.display()))?;
+    } else {
+        if output.exists() && !output.is_dir() {
+            bail!("Output path '{}' is not a directory", output.display());
+        }
+        let spec = load_spec(input)
+            .chain_err(|| format!("Failed to load spec '{}'", input.display()))?;
+        generate_sources(&spec, output, &config)
+            .chain_err(|| format!("Failed to generate sources in '{}'", output.display()))?;
+    }
     Ok(())
 }
 
@@ -0,0 +1,144 @@
+// Resolution of references into other files. Each component referred to is
+// copied into the spec's own `components` under the same name, and the
+// reference rewritten to point at the copy, so that the rest of the pipeline
+// only ever sees local references.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value as JsonValue;
+use serde_yaml;
+
+use {Result, ResultExt};
+
+/// Whether the document refers to components defined in other files
+pub fn has_external_refs(spec: &JsonValue) -> bool {
+    match *spec {
+        JsonValue::Object(ref map) => map.iter().any(|(key, value)| match value.as_str() {
+            Some(ref_) if key == "$ref" => !ref_.starts_with('#'),
+            _ => has_external_refs(value),
+        }),
+        JsonValue::Array(ref values) => values.iter().any(has_external_refs),
+        _ => false,
+    }
+}
+
+/// Copy the components referred to in other files into the spec, with
+/// relative file references resolved against `dir`
+pub fn inline_external_refs(spec: &mut JsonValue, dir: &Path) -> Result<()> {
+    let mut docs: BTreeMap<PathBuf, JsonValue> = BTreeMap::new();
+    let mut imported: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut pending = Vec::new();
+    rewrite_refs(spec, None, dir, &mut pending)?;
+    while let Some((path, pointer)) = pending.pop() {
+        component_name(&pointer)?;
+        if let Some(from) = imported.get(&pointer) {
+            if *from != path {
+                bail!(
+                    "Component {} is defined in both {} and {}",
+                    pointer,
+                    from.display(),
+                    path.display()
+                );
+            }
+            continue;
+        }
+        if spec.pointer(&pointer).is_some() {
+            bail!(
+                "Component {} of {} clashes with one of the same name in the spec",
+                pointer,
+                path.display()
+            );
+        }
+        if !docs.contains_key(&path) {
+            let doc = load(&path)?;
+            docs.insert(path.clone(), doc);
+        }
+        let mut component = match docs[&path].pointer(&pointer) {
+            Some(component) => component.clone(),
+            None => bail!("{} has no component {}", path.display(), pointer),
+        };
+        // References within the other file are resolved against its directory
+        let file_dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        rewrite_refs(&mut component, Some(&path), &file_dir, &mut pending)?;
+        {
+            let (section, name) = component_name(&pointer)?;
+            spec["components"][section][name] = component;
+        }
+        imported.insert(pointer, path);
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<JsonValue> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .chain_err(|| format!("Failed to read referenced file {}", path.display()))?;
+    Ok(serde_yaml::from_str(&text)?)
+}
+
+// The section and name of a component pointer, e.g.
+// "/components/schemas/Error" -> ("schemas", "Error")
+fn component_name(pointer: &str) -> Result<(&str, &str)> {
+    let parts: Vec<&str> = pointer.split('/').collect();
+    if parts.len() != 4 || parts[0] != "" || parts[1] != "components" {
+        bail!(
+            "External reference to {} is not supported, only components can be referenced",
+            pointer
+        );
+    }
+    Ok((parts[2], parts[3]))
+}
+
+// Point references in other files at the spec's own components, recording
+// each component which must be copied across. Local references within another
+// file (`file`) refer to that file's components, so must be copied too.
+fn rewrite_refs(
+    value: &mut JsonValue,
+    file: Option<&Path>,
+    dir: &Path,
+    pending: &mut Vec<(PathBuf, String)>,
+) -> Result<()> {
+    match *value {
+        JsonValue::Object(ref mut map) => {
+            for (key, value) in map.iter_mut() {
+                if key != "$ref" {
+                    rewrite_refs(value, file, dir, pending)?;
+                    continue;
+                }
+                let new_ref = match value.as_str() {
+                    Some(ref_) => match ref_.find('#') {
+                        Some(0) => {
+                            if let Some(file) = file {
+                                pending.push((file.to_path_buf(), ref_[1..].into()));
+                            }
+                            continue;
+                        }
+                        Some(loc) => {
+                            let path = dir.join(&ref_[..loc]);
+                            let path = path.canonicalize().chain_err(|| {
+                                format!("Failed to find referenced file {}", path.display())
+                            })?;
+                            let pointer = &ref_[loc + 1..];
+                            pending.push((path, pointer.into()));
+                            format!("#{}", pointer)
+                        }
+                        None => bail!("Reference {} to a whole file is not supported", ref_),
+                    },
+                    None => continue,
+                };
+                *value = JsonValue::String(new_ref);
+            }
+        }
+        JsonValue::Array(ref mut values) => {
+            for value in values {
+                rewrite_refs(value, file, dir, pending)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
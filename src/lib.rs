@@ -5,6 +5,7 @@ extern crate serde;
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
+extern crate serde_yaml;
 extern crate handlebars;
 extern crate rocket;
 extern crate openapi3;
@@ -14,12 +15,20 @@ extern crate inflector;
 #[macro_use]
 extern crate derive_new;
 
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem;
 use std::path::Path;
-use std::fs::File;
+use std::fs::{self, File};
 use std::process::Command;
-use std::io::Write;
+use std::io::{Read, Write};
 use handlebars::Handlebars;
+use rocket::http::Status;
+use serde_json::Value as JsonValue;
 pub use openapi3::OpenApi;
+use openapi3::objects::Schema;
+use inflector::Inflector;
 use tempdir::TempDir;
 
 pub use errors::*;
@@ -33,20 +42,198 @@ mod errors {
             Template(::handlebars::TemplateError);
             TemplateFile(::handlebars::TemplateFileError);
             OpenApi(::openapi3::Error); // TODO goes in links?
+            Json(::serde_json::Error);
+            Yaml(::serde_yaml::Error);
+            Utf8(::std::string::FromUtf8Error);
+        }
+        errors {
+            InvalidRoute(route: String, reason: String) {
+                description("a route is not a valid path")
+                display("Invalid route '{}': {}", route, reason)
+            }
+            MissingTool(tool: String, install: String) {
+                description("a tool required for bootstrapping is not installed")
+                display("'{}' is required for bootstrapping but is not installed, to install it {}", tool, install)
+            }
         }
     }
 }
 
+pub mod external;
+pub mod merge;
 pub mod process;
+pub mod swagger2;
+pub mod templates;
+pub mod typegen;
+
+pub use typegen::AllOfStrategy;
+
+thread_local!(static WARNINGS: RefCell<Vec<String>> = RefCell::new(Vec::new()));
+
+// Report a problem which does not prevent generation from continuing
+fn warn<S: Into<String>>(msg: S) {
+    let msg = msg.into();
+    eprintln!("Warning: {}", msg);
+    WARNINGS.with(|w| w.borrow_mut().push(msg));
+}
+
+/// Returns (and clears) the warnings emitted on this thread so far
+pub fn take_warnings() -> Vec<String> {
+    WARNINGS.with(|w| mem::replace(&mut *w.borrow_mut(), Vec::new()))
+}
 
 const HEADER: &str = r#"
 // *** This file was generated by thruster ***
 "#;
 
-struct Config {
-    dir_path: String,
-    gen: String,
-    stub: String,
+/// The web framework the generated server is written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framework {
+    Rocket,
+    /// actix-web 4, which runs on stable Rust. Handlers are always async, and
+    /// content negotiation, response enums and catchers are not supported, so
+    /// configs enabling them are rejected
+    Actix,
+}
+
+impl Default for Framework {
+    fn default() -> Self {
+        Framework::Rocket
+    }
+}
+
+/// What the generated stubs do until they are implemented
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StubBody {
+    /// Panic with `unimplemented!()`
+    Unimplemented,
+    /// Panic with `todo!()`. Not supported by Rocket 0.3, whose nightly
+    /// compiler predates the macro
+    Todo,
+    /// Respond with a `501 Not Implemented`. Only stubs which document no
+    /// error response can return the status, the rest still panic
+    NotImplemented,
+}
+
+impl Default for StubBody {
+    fn default() -> Self {
+        StubBody::Unimplemented
+    }
+}
+
+pub struct Config {
+    pub dir_path: String,
+    pub gen: String,
+    pub stub: String,
+    pub types: String,
+    /// The binary's root module, without the `.rs`
+    pub main: String,
+    pub all_of: AllOfStrategy,
+    pub generate_catchers: bool,
+    pub split_types: bool,
+    pub swagger_path: String,
+    /// The `Content-Type` the spec is served with
+    pub swagger_content_type: String,
+    /// The `Cache-Control` header the spec is served with (e.g. "max-age=3600"),
+    /// if any
+    pub swagger_cache_control: Option<String>,
+    /// Where to add a `GET` route for liveness probes (e.g. `/health`), unless
+    /// the spec already has an operation there. `None`, the default, leaves it out
+    pub health_path: Option<String>,
+    /// Also derive `PartialEq`, `Eq` and `Hash` for types which contain no floats
+    pub derive_hash_eq: bool,
+    /// Further derives for every generated struct and union, e.g. `Default`.
+    /// `Debug`, `Clone` and the serde derives are always present
+    pub type_derives: Vec<String>,
+    /// Generate `async fn` stubs and routes which await them. Async mode targets
+    /// Rocket 0.5, whereas the default synchronous mode targets Rocket 0.3.
+    /// Content negotiation is not yet supported in async mode, so configs
+    /// enabling both are rejected
+    pub async_handlers: bool,
+    pub framework: Framework,
+    /// Write a `Dockerfile` and `.dockerignore` when bootstrapping
+    pub dockerfile: bool,
+    /// Run `cargo clippy` on the bootstrapped crate and report the lint count
+    pub run_clippy: bool,
+    /// Let `bootstrap` replace an existing non-empty directory
+    pub force: bool,
+    /// Skip checking that cargo and the subcommands `bootstrap` runs are installed
+    pub skip_tool_check: bool,
+    /// Generate a separate `{Name}Request` struct for schemas with read-only
+    /// properties, which ignores them when deserializing
+    pub split_read_write: bool,
+    /// Wrap constrained scalar schemas in newtypes which validate their values.
+    /// This covers component schemas, properties and path, query and header
+    /// parameters
+    pub validated_newtypes: bool,
+    /// Generate scalar component schemas (e.g. a string named `Email`) as
+    /// newtypes, `struct Email(pub String)`, rather than as type aliases
+    pub scalar_newtypes: bool,
+    /// Generate a builder for each struct, e.g. `Pet::builder().with_name(..).build()`,
+    /// which leaves optional fields `None` unless they are set
+    pub builders: bool,
+    /// Choose between multiple response content types using the `Accept` header
+    pub negotiate_content: bool,
+    /// Mount each route under the path of its `servers` URL rather than at "/".
+    /// Templated server variables take their default values
+    pub mount_server_paths: bool,
+    /// Put the stubs and routes of each operation in a submodule named after
+    /// its first tag, e.g. `stub/pets.rs` and `gen/pets.rs`. Untagged
+    /// operations stay in `stub/mod.rs` and `gen/mod.rs`, whose `mount_api`
+    /// mounts the routes of every submodule
+    pub tag_modules: bool,
+    /// Nest the generated modules under a parent module of this name (e.g. `api`),
+    /// so they cannot clash with modules of a crate they are embedded in
+    pub api_module: Option<String>,
+    /// Fail if any operation cannot be generated, rather than skipping it
+    pub strict: bool,
+    /// Leave out operations the spec marks as deprecated
+    pub skip_deprecated: bool,
+    /// String formats to generate as types from other crates, e.g. chrono's
+    pub formats: process::Formats,
+    /// The map type of objects with `additionalProperties`
+    pub map_type: process::MapType,
+    /// Have stubs return the `example` of their success response, where the
+    /// spec gives one, rather than `unimplemented!()`
+    pub example_stubs: bool,
+    /// The body of stubs which have no example to return
+    pub stub_body: StubBody,
+    /// Generate a test module of this name (e.g. `tests`), with an ignored
+    /// test per operation which sends its route a request built from the
+    /// spec's examples and expects a success status
+    pub tests: Option<String>,
+    /// The port the generated server listens on by default. The `ROCKET_PORT`
+    /// (or for actix-web, `PORT`) environment variable still takes precedence
+    /// at runtime
+    pub port: Option<u16>,
+    /// The address the generated server binds to by default, e.g. "0.0.0.0".
+    /// The `ROCKET_ADDRESS` (or for actix-web, `ADDRESS`) environment variable
+    /// still takes precedence at runtime
+    pub address: Option<String>,
+    /// Return an enum with a variant per success response from operations which
+    /// document more than one, rather than only using the first, and likewise
+    /// an error enum responding with each variant's status code. Not applied
+    /// to content-negotiated operations
+    pub response_enums: bool,
+    /// Return a struct carrying the values of the headers a success response
+    /// declares, along with its body, which the route sets on the response.
+    /// Only applied to single JSON responses with Rocket
+    pub response_headers: bool,
+    /// Answer CORS preflight requests and add CORS headers to responses,
+    /// allowing any origin, via `rocket_cors` or `actix-cors`
+    pub enable_cors: bool,
+    /// Log a line with the method, path and status of each request, via
+    /// `log` and `env_logger` (so enabled with e.g. `RUST_LOG=info`)
+    pub request_logging: bool,
+    /// When bootstrapping, add dependencies at their latest versions with
+    /// `cargo add` (from `cargo-edit`), rather than at the versions the
+    /// generated code is written against
+    pub latest_dependencies: bool,
+    /// A directory of Handlebars templates to use in place of the bundled ones.
+    /// Each of `gen.hbs`, `stub.hbs`, `main.hbs`, `dockerfile.hbs` and `client.hbs`
+    /// found there overrides its bundled counterpart. Each entrypoint's
+    /// `extensions` holds the `x-` fields of its operation, for such templates to use
+    pub template_dir: Option<String>,
 }
 
 impl Default for Config {
@@ -55,6 +242,282 @@ impl Default for Config {
             dir_path: "/tmp/thruster_generated".into(),
             gen: "gen".into(),
             stub: "stub".into(),
+            types: "types".into(),
+            main: "main".into(),
+            all_of: AllOfStrategy::default(),
+            generate_catchers: false,
+            split_types: false,
+            swagger_path: "/swagger".into(),
+            swagger_content_type: "application/json".into(),
+            swagger_cache_control: None,
+            health_path: None,
+            derive_hash_eq: false,
+            type_derives: Vec::new(),
+            async_handlers: false,
+            framework: Framework::default(),
+            dockerfile: false,
+            run_clippy: false,
+            force: false,
+            skip_tool_check: false,
+            split_read_write: false,
+            validated_newtypes: false,
+            scalar_newtypes: false,
+            builders: false,
+            negotiate_content: false,
+            mount_server_paths: false,
+            tag_modules: false,
+            api_module: None,
+            strict: false,
+            skip_deprecated: false,
+            example_stubs: false,
+            stub_body: StubBody::default(),
+            formats: process::Formats::default(),
+            map_type: process::MapType::default(),
+            tests: None,
+            port: None,
+            address: None,
+            response_enums: false,
+            response_headers: false,
+            enable_cors: false,
+            request_logging: false,
+            latest_dependencies: false,
+            template_dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// Fail if any options are enabled which the framework, or async mode,
+    /// does not support, naming all of them at once
+    pub fn validate(&self) -> Result<()> {
+        let mut conflicts = Vec::new();
+        match self.framework {
+            Framework::Rocket if self.async_handlers => {
+                if self.negotiate_content {
+                    conflicts.push("negotiate_content is not supported with async_handlers");
+                }
+            }
+            Framework::Rocket => {
+                // The nightly Rocket 0.3 builds with predates the macro
+                if self.stub_body == StubBody::Todo {
+                    conflicts.push("stub_body Todo needs a newer Rust than Rocket 0.3 supports");
+                }
+            }
+            Framework::Actix => {
+                if self.negotiate_content {
+                    conflicts.push("negotiate_content is not supported by actix-web");
+                }
+                if self.response_enums {
+                    conflicts.push("response_enums is not supported by actix-web");
+                }
+                if self.generate_catchers {
+                    conflicts.push("generate_catchers is not supported by actix-web");
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            bail!("Conflicting options: {}", conflicts.join("; "));
+        }
+        Ok(())
+    }
+
+    // Whether the generated server targets Rocket 0.3
+    fn sync_rocket(&self) -> bool {
+        self.framework == Framework::Rocket && !self.async_handlers
+    }
+
+    // The edition of the generated crate. Rocket 0.3's code generation needs
+    // the 2015 edition, while async functions need 2018 or later
+    fn edition(&self) -> &'static str {
+        if self.sync_rocket() {
+            "2015"
+        } else {
+            "2021"
+        }
+    }
+
+    // The options which change the Rust type a schema maps to
+    fn type_options(&self) -> process::TypeOptions {
+        process::TypeOptions {
+            formats: self.formats,
+            map_type: self.map_type,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BootstrapReport {
+    /// The number of lints reported by clippy, if it was run
+    pub clippy_lints: Option<usize>,
+}
+
+// The path from the crate root to the generated modules, e.g. "api::". Async
+// code (Rocket 0.5 and actix-web) needs edition 2018, whose paths start from `crate`
+fn module_prefix(config: &Config) -> String {
+    let root = if config.sync_rocket() { "" } else { "crate::" };
+    match config.api_module {
+        Some(ref module) => format!("{}{}::", root, module),
+        None => root.into(),
+    }
+}
+
+// The entrypoints of the spec. Operations which fail to build are skipped
+// with a warning, unless in strict mode
+fn entrypoints<'a>(spec: &'a OpenApi, config: &Config) -> Result<Vec<Entrypoint<'a>>> {
+    config.validate()?;
+    process::check_type_names(spec)?;
+    let (entrypoints, errors) = process::collect_entrypoints(spec, config.type_options());
+    if config.strict && !errors.is_empty() {
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        bail!("Failed to generate {} operation(s): {}", errors.len(), messages.join("; "));
+    }
+    for e in errors {
+        warn(format!("Skipping operation: {}", e));
+    }
+    if config.skip_deprecated {
+        return Ok(entrypoints.into_iter().filter(|entry| !entry.deprecated).collect());
+    }
+    Ok(entrypoints)
+}
+
+fn entrypoint_args(entrypoints: &[Entrypoint], config: &Config) -> Vec<JsonValue> {
+    entrypoints
+        .iter()
+        .map(|entry| {
+            let mut args = entry.build_template_args();
+            if entry.serves_spec {
+                args["spec_response"] = json!({
+                    "content_type": format!("{:?}", config.swagger_content_type),
+                    "cache_control": config.swagger_cache_control.as_ref().map(|v| format!("{:?}", v)),
+                    // Rocket serves the spec as JSON unless told otherwise
+                    "custom": config.swagger_content_type != "application/json"
+                        || config.swagger_cache_control.is_some()
+                });
+            }
+            if config.validated_newtypes {
+                entry.add_param_validation(&mut args);
+            }
+            match config.framework {
+                Framework::Rocket => {
+                    args["async"] = json!(config.async_handlers);
+                    entry.add_body_negotiation(&mut args, false, config.validated_newtypes);
+                    if config.async_handlers {
+                        rocket_05_query(&mut args["query"]);
+                        // The stub reads the body as it arrives, up to the `bytes` limit
+                        // configured for Rocket
+                        let binary = entry.set_binary_body(
+                            &mut args,
+                            "rocket::data::DataStream<'_>",
+                            "rocket::Data<'_>",
+                            "body.open(limits.get(\"bytes\").unwrap_or(rocket::data::Limits::BYTES))",
+                        );
+                        if binary {
+                            args["route_args"]
+                                .as_array_mut()
+                                .unwrap()
+                                .push(json!({"name": "limits", "type": "&rocket::data::Limits"}));
+                        }
+                    } else if config.negotiate_content {
+                        entry.add_negotiation(&mut args);
+                    }
+                    if config.sync_rocket() {
+                        entry.add_uuid_guards(&mut args);
+                    }
+                    if config.response_enums && args["negotiate"].is_null() {
+                        entry.add_response_enum(&mut args);
+                        entry.add_error_enum(&mut args);
+                    }
+                    if config.response_headers {
+                        entry.add_response_headers(&mut args);
+                    }
+                    // Each enum is defined along with its `Responder`
+                    let enums: Vec<JsonValue> = [&args["responses"], &args["errors"]]
+                        .iter()
+                        .filter(|enum_| !enum_.is_null())
+                        .map(|&enum_| enum_.clone())
+                        .collect();
+                    args["enums"] = json!(enums);
+                }
+                Framework::Actix => {
+                    args["async"] = json!(true);
+                    entry.add_actix_args(&mut args);
+                    entry.add_body_negotiation(&mut args, true, config.validated_newtypes);
+                }
+            }
+            stub_body(&mut args, config);
+            if args["responses"].is_null() && entry.discarded_success_responses() > 0 {
+                warn(format!(
+                    "Operation '{}' documents success responses with differing bodies, \
+                     only the first is used (see `Config::response_enums`)",
+                    args["function"].as_str().unwrap()
+                ));
+            }
+            args
+        })
+        .collect()
+}
+
+// Stubs without an error type of their own return the framework's status
+// code type when they should respond with a 501
+fn stub_body(args: &mut JsonValue, config: &Config) {
+    if args["serves_spec"] == true {
+        return;
+    }
+    let body = match config.stub_body {
+        StubBody::Unimplemented => "unimplemented!()",
+        StubBody::Todo => "todo!()",
+        StubBody::NotImplemented if args["error_type"] == "()" => {
+            // Rocket 0.3 responds to a `Status` error with a 500, so it is
+            // wrapped in a `Failure`, which responds with the status itself
+            let (status_type, status) = match config.framework {
+                Framework::Rocket if config.async_handlers => (
+                    "rocket::http::Status",
+                    "rocket::http::Status::NotImplemented",
+                ),
+                Framework::Rocket => (
+                    "rocket::response::Failure",
+                    "rocket::response::Failure(rocket::http::Status::NotImplemented)",
+                ),
+                Framework::Actix => (
+                    "actix_web::http::StatusCode",
+                    "actix_web::http::StatusCode::NOT_IMPLEMENTED",
+                ),
+            };
+            args["error_type"] = json!(status_type);
+            args["status_error"] = json!(true);
+            args["stub_body"] = json!(format!("Err({})", status));
+            return;
+        }
+        StubBody::NotImplemented => {
+            warn(format!(
+                "Operation '{}' has an error type, so cannot respond with a 501 \
+                 and its stub panics instead",
+                args["function"].as_str().unwrap()
+            ));
+            "unimplemented!()"
+        }
+    };
+    args["stub_body"] = json!(body);
+}
+
+// Rocket 0.5 forms collect repeated keys into a `Vec` themselves, and rename
+// fields with `#[field(name = ...)]`
+fn rocket_05_query(query: &mut JsonValue) {
+    if query.is_null() {
+        return;
+    }
+    query["repeated"] = json!(false);
+    for field in query["fields"].as_array_mut().unwrap() {
+        if !field["delimiter"].is_null() {
+            warn(format!(
+                "Delimited query parameter '{}' is not supported with async handlers, \
+                 and is expected as repeated keys",
+                field["key"].as_str().unwrap()
+            ));
+        }
+        field["rename"] = JsonValue::Null;
+        if field["key"] != field["name"] {
+            field["attribute"] = json!(format!("#[field(name = {})]", field["key"]));
         }
     }
 }
@@ -62,119 +525,836 @@ impl Default for Config {
 pub fn generate_server_endpoints<W: Write>(
     mut writer: W,
     handlebars: &Handlebars,
+    spec: &OpenApi,
     entrypoints: &Vec<Entrypoint>,
+    config: &Config,
 ) -> Result<()> {
+    let mut modules = BTreeMap::new();
+    modules.insert(String::new(), entrypoints.clone());
+    let rendered = render_server_endpoints(handlebars, spec, &modules, "", config)?;
+    writeln!(writer, "{}", rendered)?;
+    Ok(())
+}
+
+// The routes of the entrypoints of one of `modules`, as split by
+// `tag_modules`. The parent module, "", mounts the routes of them all
+fn render_server_endpoints(
+    handlebars: &Handlebars,
+    spec: &OpenApi,
+    modules: &BTreeMap<String, Vec<Entrypoint>>,
+    module: &str,
+    config: &Config,
+) -> Result<String> {
+    let entrypoints: Vec<Entrypoint> = modules
+        .values()
+        .flat_map(|entries| entries.iter().cloned())
+        .collect();
+    // `Config::validate` rejects catchers for actix-web
+    let catchers = if config.generate_catchers && config.framework == Framework::Rocket {
+        process::error_statuses(&entrypoints)
+    } else {
+        Vec::new()
+    };
+    let mut mounts: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (name, entries) in modules {
+        for entry in entries {
+            let base_path = if config.mount_server_paths {
+                entry.base_path.as_str()
+            } else {
+                "/"
+            };
+            let function = if name.is_empty() {
+                format!("_{}", entry.operation_id.as_str())
+            } else {
+                format!("{}::_{}", name, entry.operation_id.as_str())
+            };
+            mounts.entry(base_path).or_insert_with(Vec::new).push(function);
+        }
+    }
+    let rocket_type = if config.async_handlers {
+        "rocket::Rocket<rocket::Build>"
+    } else {
+        "rocket::Rocket"
+    };
+    // The guards are defined once, in the parent module
+    let guard_entrypoints: &[Entrypoint] = if module.is_empty() { &entrypoints } else { &[] };
+    let guards: Vec<JsonValue> = process::security_guards(guard_entrypoints)
+        .iter()
+        .map(|security| json!({
+            "name": security.guard_name(),
+            "scheme": security.scheme,
+            "credential": security.credential(config.framework == Framework::Actix),
+            "async": config.async_handlers
+        }))
+        .collect();
+    // Embedded as a string literal, which the swagger route returns as-is
+    let spec = format!("{:?}", serde_json::to_string(spec)?);
+    let mut args = entrypoint_args(&modules[module], config);
+    // The parent module mounts the routes of its submodules
+    for arg in &mut args {
+        arg["public"] = json!(!module.is_empty());
+    }
     let tmpl_args = json!({
-        "entrypoints": entrypoints
+        "async": config.async_handlers,
+        "rocket_type": rocket_type,
+        "spec": spec,
+        "guards": guards,
+        "prefix": module_prefix(config),
+        "stub": config.stub,
+        "types": config.types,
+        "module": !module.is_empty(),
+        "modules": submodules(modules, module),
+        "entrypoints": args,
+        "mounts": mounts
+            .iter()
+            .map(|(base_path, functions)| json!({
+                "base_path": base_path,
+                "scope": base_path.trim_right_matches('/'),
+                "functions": functions
+            }))
+            .collect::<Vec<_>>(),
+        "catchers": catchers
             .iter()
-            .map(|entry| entry.build_template_args())
+            .map(|&code| json!({
+                "code": code,
+                "function": format!("catch_{}", code),
+                "attribute": if config.async_handlers { "catch" } else { "error" },
+                "message": Status::from_code(code).map(|s| s.reason).unwrap_or("Error")
+            }))
             .collect::<Vec<_>>()
     });
-    let rendered = handlebars.render("gen", &tmpl_args)?;
+    Ok(handlebars.render("gen", &tmpl_args)?)
+}
+
+// The names of the tag modules declared by the parent module
+fn submodules<T>(modules: &BTreeMap<String, T>, module: &str) -> Vec<String> {
+    if !module.is_empty() {
+        return Vec::new();
+    }
+    modules.keys().filter(|name| !name.is_empty()).cloned().collect()
+}
+
+// The entrypoints of each tag module, by the module's name, which is that of
+// the operation's first tag. Untagged operations, and the built-in routes,
+// are under "", for the parent module
+fn tag_modules<'a>(
+    entrypoints: &[Entrypoint<'a>],
+    config: &Config,
+) -> BTreeMap<String, Vec<Entrypoint<'a>>> {
+    let mut modules = BTreeMap::new();
+    modules.insert(String::new(), Vec::new());
+    for entry in entrypoints {
+        let name = match entry.tags.first() {
+            Some(tag) => module_name(tag, config),
+            None => String::new(),
+        };
+        modules.entry(name).or_insert_with(Vec::new).push(entry.clone());
+    }
+    modules
+}
+
+// A tag as a module name, e.g. "Pet Store" -> "pet_store"
+fn module_name(tag: &str, config: &Config) -> String {
+    let name = tag.to_snake_case();
+    // The modules would shadow the crates and modules they import
+    let imported = ["std", "io", "rocket", "rocket_contrib", "actix_web", "web", "serde",
+                    "serde_json", "serde_derive", "serde_urlencoded", "regex", "chrono", "uuid"];
+    let generated = [&config.gen, &config.stub, &config.types];
+    if name.is_empty() || name.starts_with(|c: char| c.is_numeric())
+        || typegen::KEYWORDS.contains(&name.as_str())
+        || imported.contains(&name.as_str())
+        || generated.iter().any(|module| **module == name)
+    {
+        format!("tag_{}", name)
+    } else {
+        name
+    }
+}
+
+pub fn generate_function_stubs<W: Write>(
+    mut writer: W,
+    handlebars: &Handlebars,
+    entrypoints: &Vec<Entrypoint>,
+    config: &Config,
+) -> Result<()> {
+    let mut modules = BTreeMap::new();
+    modules.insert(String::new(), entrypoints.clone());
+    let rendered = render_function_stubs(handlebars, &modules, "", config)?;
     writeln!(writer, "{}", rendered)?;
     Ok(())
 }
 
-pub fn generate_function_stubs<W: Write>(
+// The stubs of the entrypoints of one of `modules`, as for `render_server_endpoints`
+fn render_function_stubs(
+    handlebars: &Handlebars,
+    modules: &BTreeMap<String, Vec<Entrypoint>>,
+    module: &str,
+    config: &Config,
+) -> Result<String> {
+    let entrypoints = &modules[module];
+    let mut args = entrypoint_args(entrypoints, config);
+    if config.example_stubs {
+        for (entry, args) in entrypoints.iter().zip(&mut args) {
+            entry.add_example_return(args);
+        }
+    }
+    // Routes which serve the spec never call a stub
+    args.retain(|args| args["serves_spec"] != true);
+    let entrypoints = args;
+    // The response enums and structs are defined alongside the routes
+    let gen_module = if module.is_empty() {
+        config.gen.clone()
+    } else {
+        format!("{}::{}", config.gen, module)
+    };
+    let response_enums: Vec<String> = entrypoints
+        .iter()
+        .flat_map(|e| {
+            vec![
+                &e["responses"]["name"],
+                &e["errors"]["name"],
+                &e["response_headers"]["name"],
+                &e["negotiate"]["name"],
+            ]
+        })
+        .filter_map(|name| name.as_str())
+        .map(|name| format!("{}{}::{}", module_prefix(config), gen_module, name))
+        .collect();
+    let tmpl_args = json!({
+        "prefix": module_prefix(config),
+        "types": config.types,
+        "modules": submodules(modules, module),
+        "response_enums": response_enums,
+        "entrypoints": entrypoints
+    });
+    Ok(handlebars.render("stub", &tmpl_args)?)
+}
+
+/// Generate a test per entrypoint, which calls its route with example values
+/// and asserts a success status
+pub fn generate_tests<W: Write>(
+    mut writer: W,
+    handlebars: &Handlebars,
+    entrypoints: &Vec<Entrypoint>,
+    config: &Config,
+) -> Result<()> {
+    let mut args = entrypoint_args(entrypoints, config);
+    for (entry, args) in entrypoints.iter().zip(&mut args) {
+        entry.add_test_args(args);
+    }
+    let tmpl_args = json!({
+        "async": config.async_handlers,
+        "prefix": module_prefix(config),
+        "gen": config.gen,
+        "entrypoints": args
+    });
+    let rendered = handlebars.render("tests", &tmpl_args)?;
+    writeln!(writer, "{}", rendered)?;
+    Ok(())
+}
+
+/// Generate a `reqwest` client for the API, with a method per entrypoint
+pub fn generate_client<W: Write>(
     mut writer: W,
     handlebars: &Handlebars,
     entrypoints: &Vec<Entrypoint>,
+    config: &Config,
 ) -> Result<()> {
+    let entrypoints: Vec<JsonValue> = entrypoints
+        .iter()
+        .map(|entry| {
+            let mut args = entry.build_template_args();
+            entry.add_client_args(&mut args);
+            args
+        })
+        .collect();
     let tmpl_args = json!({
+        "prefix": module_prefix(config),
+        "types": config.types,
         "entrypoints": entrypoints
-            .iter()
-            .map(|entry| entry.build_template_args())
-            .collect::<Vec<_>>()
     });
-    let rendered = handlebars.render("stub", &tmpl_args)?;
+    let rendered = handlebars.render("client", &tmpl_args)?;
     writeln!(writer, "{}", rendered)?;
     Ok(())
 }
 
 pub fn generate_types<W: Write>(
-    mut writer: W,
+    writer: W,
     handlebars: &Handlebars,
-    spec: &OpenApi) -> Result<()> {
-    use openapi3::objects::CodeGen;
+    spec: &OpenApi,
+    config: &Config) -> Result<()> {
+    let entrypoints = entrypoints(spec, config)?;
+    write_types(writer, spec, &entrypoints, config)
+}
+
+fn write_types<W: Write>(
+    mut writer: W,
+    spec: &OpenApi,
+    entrypoints: &[Entrypoint],
+    config: &Config,
+) -> Result<()> {
     writeln!(writer, "{}", HEADER)?;
+    let no_schemas = BTreeMap::new();
+    let schemas = component_schemas(spec).unwrap_or(&no_schemas);
+    let (type_schemas, aliases) = type_schemas(spec, entrypoints, config)?;
+    let base64 = uses_base64(&type_schemas, entrypoints);
+    let mut codes = Vec::new();
+    for (name, schema) in type_schemas {
+        println!("Generating type: {}", name);
+        codes.push(typegen::generate_type(&name, &schema, schemas, config)?);
+    }
+    if codes.iter().any(|code| typegen::needs_deserialize_some(code)) {
+        writeln!(writer, "{}", typegen::DESERIALIZE_SOME)?;
+    }
+    if base64 {
+        writeln!(writer, "{}", typegen::BASE64)?;
+    }
+    for code in codes {
+        writeln!(writer, "{}", code)?;
+    }
+    for (alias, target) in aliases {
+        writeln!(writer, "pub type {} = {};", alias, target)?;
+    }
+    Ok(())
+}
+
+fn component_schemas(spec: &OpenApi) -> Option<&BTreeMap<String, Schema>> {
     spec.components
         .as_ref()
         .and_then(|components| components.schemas.as_ref())
+}
+
+// The component schemas, followed by the inline object schemas of the
+// entrypoints, which would otherwise have no definition, and the schemas of
+// parameters needing validated newtypes. An inline schema
+// identical to one already defined becomes an alias of it, returned separately
+// as (alias, target) pairs
+fn type_schemas<'a>(
+    spec: &'a OpenApi,
+    entrypoints: &[Entrypoint<'a>],
+    config: &Config,
+) -> Result<(Vec<(String, Cow<'a, Schema>)>, Vec<(String, String)>)> {
+    let mut out: Vec<(String, Cow<Schema>)> = component_schemas(spec)
         .map(|schemas| {
             schemas
                 .iter()
-                .map(|(name, schema)| {
-                    println!("Generating type: {}", name);
-                    let code = schema.generate_code(name)?;
-                    writeln!(writer, "{}", code)?;
-                    Ok(())
-                })
-                .collect::<Result<Vec<()>>>()
-                .map(|_| ())
+                .map(|(name, schema)| (name.clone(), Cow::Borrowed(schema)))
+                .collect()
         })
-        .unwrap_or(Ok(()))
+        .unwrap_or_default();
+    let mut aliases = Vec::new();
+    for entrypoint in entrypoints {
+        if config.validated_newtypes {
+            for (name, schema) in entrypoint.validated_params() {
+                out.push((name, Cow::Borrowed(schema)));
+            }
+        }
+        for (name, schema) in entrypoint.anonymous_schemas() {
+            let existing = out.iter()
+                .find(|&&(_, ref other)| **other == schema)
+                .map(|&(ref other_name, _)| other_name.clone());
+            match existing {
+                Some(target) => aliases.push((name, target)),
+                None => out.push((name, Cow::Owned(schema))),
+            }
+        }
+    }
+    Ok((out, aliases))
+}
+
+// Whether the type schemas or entrypoints have any `format: byte` strings,
+// whose `Base64` type is then defined alongside the generated types
+fn uses_base64(schemas: &[(String, Cow<Schema>)], entrypoints: &[Entrypoint]) -> bool {
+    schemas.iter().any(|&(_, ref schema)| typegen::uses_base64(schema))
+        || entrypoints.iter().any(|entry| entry.uses_base64())
+}
+
+// Render each schema into its own module, keyed by file name, along with
+// a `mod.rs` which re-exports them all
+fn split_types(
+    spec: &OpenApi,
+    entrypoints: &[Entrypoint],
+    config: &Config,
+) -> Result<Vec<(String, String)>> {
+    let mut mod_rs = format!("{}\n", HEADER);
+    let mut needs_deserialize_some = false;
+    let mut files = Vec::new();
+    let no_schemas = BTreeMap::new();
+    let schemas = component_schemas(spec).unwrap_or(&no_schemas);
+    let (type_schemas, aliases) = type_schemas(spec, entrypoints, config)?;
+    let base64 = uses_base64(&type_schemas, entrypoints);
+    for (name, schema) in type_schemas {
+        println!("Generating type: {}", name);
+        let module = name.to_snake_case();
+        let code = typegen::generate_type(&name, &schema, schemas, config)?;
+        needs_deserialize_some |= typegen::needs_deserialize_some(&code);
+        // Other generated types are re-exported by mod.rs
+        files.push((
+            format!("{}.rs", module),
+            format!("{}\nuse super::*;\n\n{}\n", HEADER, code),
+        ));
+        mod_rs.push_str(&format!("mod {};\npub use self::{}::*;\n", module, module));
+    }
+    for (alias, target) in aliases {
+        mod_rs.push_str(&format!("pub type {} = {};\n", alias, target));
+    }
+    if needs_deserialize_some {
+        mod_rs.push_str(typegen::DESERIALIZE_SOME);
+    }
+    if base64 {
+        mod_rs.push_str(typegen::BASE64);
+    }
+    files.push(("mod.rs".into(), mod_rs));
+    Ok(files)
+}
+
+/// Like `generate_types`, but writes each schema to its own file within `types_dir`,
+/// along with a `mod.rs` which re-exports them all
+pub fn generate_types_dir<P: AsRef<Path>>(
+    types_dir: P,
+    spec: &OpenApi,
+    config: &Config,
+) -> Result<()> {
+    let types_dir = types_dir.as_ref();
+    fs::create_dir_all(types_dir)?;
+    let entrypoints = entrypoints(spec, config)?;
+    for (file_name, contents) in split_types(spec, &entrypoints, config)? {
+        let mut file = File::create(types_dir.join(file_name))?;
+        file.write_all(contents.as_bytes())?;
+    }
+    Ok(())
 }
 
-pub fn generate_main<W: Write>(mut writer: W, handlebars: &Handlebars) -> Result<()> {
+
+pub fn generate_main<W: Write>(
+    mut writer: W,
+    handlebars: &Handlebars,
+    spec: &OpenApi,
+    entrypoints: &[Entrypoint],
+    config: &Config,
+) -> Result<()> {
+    // Rocket's routes decode form bodies themselves
+    let form_bodies = config.framework == Framework::Rocket
+        && entrypoints.iter().any(|entry| entry.uses_form_bodies());
+    let (schemas, _) = type_schemas(spec, entrypoints, config)?;
     let main = handlebars.render(
         "main",
-        &json!({"gen": "gen", "stub": "stub"}))?;
+        &json!({
+            "async": config.async_handlers,
+            "api_module": config.api_module,
+            "prefix": module_prefix(config),
+            "gen": config.gen,
+            "stub": config.stub,
+            "types": config.types,
+            "validation": config.validated_newtypes,
+            "form_bodies": form_bodies,
+            "chrono": config.formats.chrono,
+            "uuid": config.formats.uuid,
+            "base64": uses_base64(&schemas, entrypoints),
+            "indexmap": config.map_type == process::MapType::IndexMap,
+            "tests": config.tests,
+            "cors": config.enable_cors,
+            "logging": config.request_logging,
+            "listen": config.port.is_some() || config.address.is_some(),
+            "port": config.port,
+            "address": config.address
+        }))?;
     writeln!(writer, "{}", main)?;
     Ok(())
 }
 
-pub fn generate_sources<P: AsRef<Path>>(spec: &OpenApi, src_path: P) -> Result<()> {
-    let src_path: &Path = src_path.as_ref();
-
-    let gen_name = "gen";
-    let stub_name = "stub";
-    let types_name = "types";
+pub fn generate_dockerfile<W: Write>(
+    mut writer: W,
+    handlebars: &Handlebars,
+    crate_name: &str,
+    port: u16,
+    config: &Config,
+) -> Result<()> {
+    // Rocket 0.3 builds only on nightly. The server reads its address and
+    // port from variables which depend on the framework
+    let (builder, runtime) = if config.sync_rocket() {
+        ("rustlang/rust:nightly", "debian:stretch-slim")
+    } else {
+        ("rust:1-bookworm", "debian:bookworm-slim")
+    };
+    let env_prefix = match config.framework {
+        Framework::Rocket => "ROCKET_",
+        Framework::Actix => "",
+    };
+    let dockerfile = handlebars.render(
+        "dockerfile",
+        &json!({
+            "crate_name": crate_name,
+            "port": port,
+            "builder": builder,
+            "runtime": runtime,
+            "env_prefix": env_prefix
+        }))?;
+    writeln!(writer, "{}", dockerfile)?;
+    Ok(())
+}
 
-    let gen_path = src_path.join(format!("{}.rs", gen_name));
-    let stub_path = src_path.join(format!("{}.rs", stub_name));
-    let types_path = src_path.join(format!("{}.rs", types_name));
-    let main_path = src_path.join("main.rs");
+// Rocket's default port, used when the spec's servers don't specify one
+const DEFAULT_PORT: u16 = 8000;
 
-    let mut entrypoints = process::extract_entrypoints(spec);
-    let swagger = process::Entrypoint::swagger_entrypoint();
-    entrypoints.push(swagger);
+// Extract the port of the first server URL, e.g. "http://localhost:8080/v1" -> 8080
+fn server_port(spec: &OpenApi) -> Option<u16> {
+    let url = match spec.servers.as_ref().and_then(|servers| servers.first()) {
+        Some(server) => server.url.as_str(),
+        None => return None,
+    };
+    let authority = match url.find("://") {
+        Some(ix) => &url[ix + 3..],
+        None => url,
+    };
+    let authority = authority.split('/').next().unwrap_or("");
+    authority
+        .rfind(':')
+        .and_then(|ix| authority[ix + 1..].parse().ok())
+}
 
+fn load_templates(config: &Config) -> Result<Handlebars> {
     let mut handlebars = Handlebars::new();
     handlebars.register_escape_fn(handlebars::no_escape);
-    // TODO grab templates from user input
-    handlebars.register_template_file("gen", "templates/gen.hbs")?;
-    handlebars.register_template_file("stub", "templates/stub.hbs")?;
-    handlebars.register_template_file("main", "templates/main.hbs")?;
+    let (gen_template, main_template, tests_template) = match config.framework {
+        Framework::Rocket => (
+            templates::GEN_TEMPLATE,
+            templates::MAIN_TEMPLATE,
+            templates::TESTS_TEMPLATE,
+        ),
+        Framework::Actix => (
+            templates::ACTIX_GEN_TEMPLATE,
+            templates::ACTIX_MAIN_TEMPLATE,
+            templates::ACTIX_TESTS_TEMPLATE,
+        ),
+    };
+    let bundled = [
+        ("gen", gen_template),
+        ("stub", templates::STUB_TEMPLATE),
+        ("main", main_template),
+        ("dockerfile", templates::DOCKERFILE_TEMPLATE),
+        ("client", templates::CLIENT_TEMPLATE),
+        ("tests", tests_template),
+    ];
+    for &(name, template) in &bundled {
+        let user_path = config
+            .template_dir
+            .as_ref()
+            .map(|dir| Path::new(dir).join(format!("{}.hbs", name)))
+            .and_then(|path| if path.is_file() { Some(path) } else { None });
+        match user_path {
+            Some(path) => {
+                println!("Using template: {}", path.display());
+                handlebars
+                    .register_template_file(name, &path)
+                    .chain_err(|| format!("Failed to load template {}", path.display()))?
+            }
+            None => handlebars.register_template_string(name, template)?,
+        }
+    }
+    Ok(handlebars)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Json,
+}
+
+/// Run the whole pipeline in memory, from the text of a spec to the generated
+/// sources, keyed by their path relative to the crate's `src` directory
+pub fn generate_from_str(spec: &str, format: Format) -> Result<BTreeMap<String, String>> {
+    let spec = parse_spec(spec, format)?;
+    generate_sources_to_map(&spec, &Config::default())
+}
+
+/// Parse a spec, converting it first if it is in the Swagger 2.0 format
+pub fn parse_spec(spec: &str, format: Format) -> Result<OpenApi> {
+    let value: JsonValue = match format {
+        Format::Yaml => serde_yaml::from_str(spec)?,
+        Format::Json => serde_json::from_str(spec)?,
+    };
+    if swagger2::is_swagger2(&value) {
+        return swagger2::from_swagger2(&value);
+    }
+    Ok(match format {
+        Format::Yaml => OpenApi::from_string(spec)?,
+        Format::Json => serde_json::from_value(value)?,
+    })
+}
+
+/// Load a spec from a YAML or JSON file, which may be in the Swagger 2.0 format.
+/// Components referred to in other files are loaded relative to the spec.
+pub fn load_spec<P: AsRef<Path>>(spec_path: P) -> Result<OpenApi> {
+    let spec_path = spec_path.as_ref();
+    let mut text = String::new();
+    File::open(spec_path)?.read_to_string(&mut text)?;
+    // JSON is a subset of YAML, so the YAML parser accepts both
+    let mut value: JsonValue = serde_yaml::from_str(&text)?;
+    if !external::has_external_refs(&value) {
+        return parse_spec(&text, Format::Yaml);
+    }
+    let dir = spec_path.parent().unwrap_or_else(|| Path::new("."));
+    external::inline_external_refs(&mut value, dir)?;
+    parse_spec(&serde_json::to_string(&value)?, Format::Json)
+}
+
+/// Generates the sources of a spec, loading the templates and collecting the
+/// entrypoints once for all of them
+pub struct Generator<'a> {
+    spec: &'a OpenApi,
+    config: &'a Config,
+    handlebars: Handlebars,
+    entrypoints: Vec<Entrypoint<'a>>,
+    // The entrypoints of each tag module, or all of them under "" when the
+    // config has no tag modules
+    modules: BTreeMap<String, Vec<Entrypoint<'a>>>,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(spec: &'a OpenApi, config: &'a Config) -> Result<Generator<'a>> {
+        let mut entrypoints = entrypoints(spec, config)?;
+        let swagger = process::Entrypoint::swagger_entrypoint(&config.swagger_path)?;
+        entrypoints.push(swagger);
+        if let Some(ref path) = config.health_path {
+            let health = process::Entrypoint::health_entrypoint(path)?;
+            if entrypoints.iter().any(|entry| entry.conflicts_with(&health)) {
+                warn(format!(
+                    "The spec already has a GET {} route or a getHealth operation, \
+                     so no health check has been added",
+                    path
+                ));
+            } else {
+                entrypoints.push(health);
+            }
+        }
+        let modules = if config.tag_modules {
+            tag_modules(&entrypoints, config)
+        } else {
+            let mut modules = BTreeMap::new();
+            modules.insert(String::new(), entrypoints.clone());
+            modules
+        };
+        Ok(Generator {
+            spec,
+            config,
+            handlebars: load_templates(config)?,
+            entrypoints,
+            modules,
+        })
+    }
+
+    /// The routes. With tag modules, those of the parent module
+    pub fn endpoints(&self) -> Result<String> {
+        let gen =
+            render_server_endpoints(&self.handlebars, self.spec, &self.modules, "", self.config)?;
+        Ok(format!("{}\n", gen))
+    }
+
+    /// The stubs. With tag modules, those of the parent module
+    pub fn stubs(&self) -> Result<String> {
+        let stub = render_function_stubs(&self.handlebars, &self.modules, "", self.config)?;
+        Ok(format!("{}\n", stub))
+    }
+
+    /// The types of the component schemas, in a single module
+    pub fn types(&self) -> Result<String> {
+        let mut types = Vec::new();
+        write_types(&mut types, self.spec, &self.entrypoints, self.config)?;
+        Ok(String::from_utf8(types)?)
+    }
+
+    /// The crate's `main`, which launches the server
+    pub fn main(&self) -> Result<String> {
+        let mut main = Vec::new();
+        generate_main(&mut main, &self.handlebars, self.spec, &self.entrypoints, self.config)?;
+        Ok(String::from_utf8(main)?)
+    }
+
+    /// Every source file, keyed by its path relative to the crate's `src` directory
+    pub fn all_to_map(&self) -> Result<BTreeMap<String, String>> {
+        let config = self.config;
+        // Generated modules live in a subdirectory when nested in a parent module
+        let dir = config
+            .api_module
+            .as_ref()
+            .map(|module| format!("{}/", module))
+            .unwrap_or_default();
+        let gen_name = format!("{}{}", dir, config.gen);
+        let stub_name = format!("{}{}", dir, config.stub);
+        let types_name = format!("{}{}", dir, config.types);
+
+        let mut files = BTreeMap::new();
+        // Without tag modules there is only the parent, as a single file
+        let file_name = |parent: &str, module: &str| match (config.tag_modules, module) {
+            (false, _) => format!("{}.rs", parent),
+            (true, "") => format!("{}/mod.rs", parent),
+            (true, module) => format!("{}/{}.rs", parent, module),
+        };
+
+        println!("Generating server endpoints");
+        for module in self.modules.keys() {
+            let gen = render_server_endpoints(
+                &self.handlebars,
+                self.spec,
+                &self.modules,
+                module,
+                config,
+            )?;
+            files.insert(file_name(&gen_name, module), format!("{}\n", gen));
+        }
+
+        println!("Generating stub functions");
+        for module in self.modules.keys() {
+            let stub = render_function_stubs(&self.handlebars, &self.modules, module, config)?;
+            files.insert(file_name(&stub_name, module), format!("{}\n", stub));
+        }
+
+        println!("Generating types");
+        if config.split_types {
+            for (file_name, contents) in split_types(self.spec, &self.entrypoints, config)? {
+                files.insert(format!("{}/{}", types_name, file_name), contents);
+            }
+        } else {
+            files.insert(format!("{}.rs", types_name), self.types()?);
+        }
+
+        if config.api_module.is_some() {
+            files.insert(
+                format!("{}mod.rs", dir),
+                format!(
+                    "{}\npub mod {};\npub mod {};\npub mod {};\n",
+                    HEADER, config.gen, config.stub, config.types
+                ),
+            );
+        }
+
+        if let Some(ref tests) = config.tests {
+            println!("Generating tests");
+            let mut out = Vec::new();
+            generate_tests(&mut out, &self.handlebars, &self.entrypoints, config)?;
+            files.insert(format!("{}.rs", tests), String::from_utf8(out)?);
+        }
+
+        println!("Generating main");
+        files.insert(format!("{}.rs", config.main), self.main()?);
+
+        Ok(files)
+    }
+
+    /// Write every source file within `src_path`, leaving unchanged files alone
+    pub fn all_to_dir<P: AsRef<Path>>(&self, src_path: P) -> Result<()> {
+        let src_path: &Path = src_path.as_ref();
+        for (file_name, contents) in self.all_to_map()? {
+            let path = src_path.join(file_name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_if_changed(&path, &contents)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generate all sources without touching the filesystem, keyed by their path
+/// relative to the crate's `src` directory
+pub fn generate_sources_to_map(
+    spec: &OpenApi,
+    config: &Config,
+) -> Result<BTreeMap<String, String>> {
+    Generator::new(spec, config)?.all_to_map()
+}
 
-    println!("Generating server endpoints");
-    let gen_file = File::create(gen_path)?;
-    generate_server_endpoints(gen_file, &handlebars, &entrypoints)?;
+/// The main generated modules, held in memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedSources {
+    pub gen: String,
+    pub stub: String,
+    pub types: String,
+    pub main: String,
+}
 
-    println!("Generating stub functions");
-    let stub_file = File::create(stub_path)?;
-    generate_function_stubs(stub_file, &handlebars, &entrypoints)?;
+/// Generate the main modules without touching the filesystem, e.g. from a
+/// build script. Any other files (such as tests) are only available from
+/// `generate_sources_to_map`.
+pub fn generate_sources_to_string(spec: &OpenApi, config: &Config) -> Result<GeneratedSources> {
+    if config.split_types || config.tag_modules {
+        bail!(
+            "Split types and tag modules span several files, \
+             use generate_sources_to_map instead"
+        );
+    }
+    let dir = config
+        .api_module
+        .as_ref()
+        .map(|module| format!("{}/", module))
+        .unwrap_or_default();
+    let mut files = generate_sources_to_map(spec, config)?;
+    let mut take = |name: &str| {
+        files
+            .remove(&format!("{}.rs", name))
+            .ok_or_else(|| Error::from(format!("{}.rs was not generated", name)))
+    };
+    Ok(GeneratedSources {
+        gen: take(&format!("{}{}", dir, config.gen))?,
+        stub: take(&format!("{}{}", dir, config.stub))?,
+        types: take(&format!("{}{}", dir, config.types))?,
+        main: take(config.main.as_str())?,
+    })
+}
 
-    println!("Generating types");
-    let types_file = File::create(types_path)?;
-    generate_types(types_file, &handlebars, &spec)?;
+pub fn generate_sources<P: AsRef<Path>>(spec: &OpenApi, src_path: P, config: &Config) -> Result<()> {
+    Generator::new(spec, config)?.all_to_dir(src_path)
+}
 
-    println!("Generating main");
-    let main_file = File::create(main_path)?;
-    generate_main(main_file, &handlebars)?;
+/// Generate a single server from several specs, as if their paths and
+/// components had been written in one. The servers of the first spec are used.
+pub fn generate_sources_multi<P: AsRef<Path>>(
+    specs: &[OpenApi],
+    src_path: P,
+    config: &Config,
+) -> Result<()> {
+    let spec = merge::merge_specs(specs)?;
+    generate_sources(&spec, src_path, config)
+}
 
+// Write the file unless it already has these contents, so that regenerating
+// an unchanged file leaves its modified time, and so build caches, alone
+fn write_if_changed(path: &Path, contents: &str) -> Result<()> {
+    let mut existing = String::new();
+    if let Ok(mut file) = File::open(path) {
+        if file.read_to_string(&mut existing).is_ok() && existing == contents {
+            return Ok(());
+        }
+    }
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
     Ok(())
 }
 
-pub fn bootstrap<P: AsRef<Path>>(spec_path: P, dir_path: P) -> Result<()> {
-    // TODO assumes cargo, cargo fmt and cargo add are installed
+pub fn bootstrap<P: AsRef<Path>>(
+    spec_path: P,
+    dir_path: P,
+    config: &Config,
+) -> Result<BootstrapReport> {
+    let mut report = BootstrapReport::default();
+    config.validate()?;
+    if !config.skip_tool_check {
+        check_tools("cargo", config)?;
+    }
+
+    let dir_path = dir_path.as_ref();
+    if !config.force && is_occupied(dir_path)? {
+        bail!("'{}' already exists and is not empty", dir_path.display());
+    }
 
-    let spec = OpenApi::from_file(spec_path)?;
+    let spec = load_spec(spec_path)?;
 
+    // The temporary dir is deleted when dropped, including on failure
     let tmp_dir = TempDir::new("thruster-bootstrap")?;
     println!("Created temporary dir: {}", tmp_dir.path().to_string_lossy());
 
     let crate_name: &str = dir_path
-        .as_ref()
         .file_name()
         .ok_or("Could not extract crate name from path".into())
         .and_then(|s| {
@@ -184,24 +1364,129 @@ pub fn bootstrap<P: AsRef<Path>>(spec_path: P, dir_path: P) -> Result<()> {
     cargo_new(tmp_dir.path(), crate_name)?;
 
     let crate_path = tmp_dir.path().join(crate_name);
+    write_edition(&crate_path, config)?;
     let srcpath = crate_path.join("src");
 
-    generate_sources(&spec, &srcpath)?;
+    let generator = Generator::new(&spec, config)?;
+    generator.all_to_dir(&srcpath)?;
+
+    if config.dockerfile {
+        println!("Generating Dockerfile");
+        let port = config.port.or(server_port(&spec)).unwrap_or(DEFAULT_PORT);
+        let dockerfile = File::create(crate_path.join("Dockerfile"))?;
+        generate_dockerfile(dockerfile, &generator.handlebars, crate_name, port, config)?;
+        let mut dockerignore = File::create(crate_path.join(".dockerignore"))?;
+        writeln!(dockerignore, "target")?;
+    }
 
     cargo_fmt(&crate_path)?;
-    cargo_add(&crate_path)?;
+    if config.latest_dependencies {
+        cargo_add(&crate_path, &spec, &generator.entrypoints, config)?;
+    } else {
+        write_dependencies(&crate_path, &spec, &generator.entrypoints, config)?;
+    }
     cargo_check(&crate_path)?;
+    report.clippy_lints = clippy_lints(&crate_path, config)?;
 
-    // TODO don't move if already exists
-    let mut child = Command::new("mv")
-        .current_dir(tmp_dir.path())
-        .args(&[crate_name, dir_path.as_ref().to_str().unwrap()])
-        .spawn()?;
-    let ecode = child.wait()?;
-    if !ecode.success() {
-        bail!("Failed to execute 'mv' command")
+    move_dir(&crate_path, dir_path)?;
+    Ok(report)
+}
+
+// Whether there is anything at the path which bootstrapping would replace
+fn is_occupied(path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    if !path.is_dir() {
+        return Ok(true);
+    }
+    Ok(fs::read_dir(path)?.next().is_some())
+}
+
+// Move the crate to `to`, replacing whatever is there. The existing contents
+// are set aside until the move succeeds, and restored if it fails
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if !to.exists() {
+        return place_dir(from, to);
+    }
+    let mut backup = to.with_extension("thruster-backup");
+    let mut ix = 1;
+    while backup.exists() {
+        backup = to.with_extension(format!("thruster-backup{}", ix));
+        ix += 1;
+    }
+    fs::rename(to, &backup)
+        .chain_err(|| format!("Failed to set aside the existing '{}'", to.display()))?;
+    if let Err(e) = place_dir(from, to) {
+        let _ = fs::rename(&backup, to);
+        return Err(e);
+    }
+    if backup.is_dir() {
+        fs::remove_dir_all(&backup)?;
+    } else {
+        fs::remove_file(&backup)?;
     }
+    Ok(())
+}
+
+// Falls back to copying when the two are on different filesystems
+fn place_dir(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = copy_dir(from, to) {
+        // Don't leave a partial crate behind
+        let _ = fs::remove_dir_all(to);
+        return Err(e).chain_err(|| format!("Failed to copy crate to '{}'", to.display()));
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
 
+// The tools bootstrapping runs, with the cargo arguments which probe for each
+// and how to install it
+const REQUIRED_TOOLS: &[(&str, &[&str], &str)] = &[
+    ("cargo", &["--version"], "see https://rustup.rs"),
+    ("cargo fmt", &["fmt", "--version"], "run `rustup component add rustfmt`"),
+    ("cargo add", &["add", "--help"], "run `cargo install cargo-edit`"),
+    ("cargo clippy", &["clippy", "--version"], "run `rustup component add clippy`"),
+];
+
+// Fail with `ErrorKind::MissingTool` unless each required tool can be run
+// through the given cargo executable. `cargo add` is only needed to add the
+// latest dependencies, and `cargo clippy` to run clippy
+fn check_tools(cargo: &str, config: &Config) -> Result<()> {
+    for &(tool, args, install) in REQUIRED_TOOLS {
+        let needed = match tool {
+            "cargo add" => config.latest_dependencies,
+            "cargo clippy" => config.run_clippy,
+            _ => true,
+        };
+        if !needed {
+            continue;
+        }
+        let installed = Command::new(cargo)
+            .args(args)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if !installed {
+            bail!(ErrorKind::MissingTool(tool.into(), install.into()));
+        }
+    }
     Ok(())
 }
 
@@ -217,6 +1502,15 @@ fn cargo_command<P: AsRef<Path>>(dir_path: P, args: &[&str]) -> Result<()> {
     Ok(())
 }
 
+// Like `cargo_command`, but captures stdout and tolerates a non-zero exit
+fn cargo_output<P: AsRef<Path>>(dir_path: P, args: &[&str]) -> Result<String> {
+    let output = Command::new("cargo")
+        .current_dir(dir_path)
+        .args(args)
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 fn cargo_new<P: AsRef<Path>>(dir_path: P, crate_name: &str) -> Result<()> {
     cargo_command(dir_path, &["new", "--bin", crate_name])
 }
@@ -229,9 +1523,2552 @@ fn cargo_check<P: AsRef<Path>>(dir_path: P) -> Result<()> {
     cargo_command(dir_path, &["check"])
 }
 
-fn cargo_add<P: AsRef<Path>>(dir_path: P) -> Result<()> {
-    cargo_command(
-        dir_path,
-        &["add", "rocket", "rocket_codegen", "serde", "serde_derive"],
-    )
+// The number of clippy's lints, if the config asks for it to be run
+fn clippy_lints(crate_path: &Path, config: &Config) -> Result<Option<usize>> {
+    if !config.run_clippy {
+        return Ok(None);
+    }
+    cargo_clippy(crate_path).map(Some)
+}
+
+// Run clippy, which `check_tools` has found, and count its lints
+fn cargo_clippy<P: AsRef<Path>>(dir_path: P) -> Result<usize> {
+    let output = cargo_output(dir_path, &["clippy", "--message-format=json"])?;
+    let lints = count_lints(&output);
+    println!("Clippy reported {} lints", lints);
+    Ok(lints)
+}
+
+// Count clippy's lints in cargo's JSON message output, leaving out the
+// compiler's own warnings
+fn count_lints(messages: &str) -> usize {
+    messages
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-message")
+        .filter(|msg| {
+            msg["message"]["code"]["code"]
+                .as_str()
+                .map_or(false, |code| code.starts_with("clippy::"))
+        })
+        .count()
+}
+
+// A dependency of the generated crate, at the release its templates are written against
+struct Dependency {
+    name: &'static str,
+    version: &'static str,
+    features: Vec<&'static str>,
+    // Whether only this release will do, even when adding the latest dependencies
+    pinned: bool,
+}
+
+impl Dependency {
+    fn new(name: &'static str, version: &'static str) -> Dependency {
+        Dependency {
+            name,
+            version,
+            features: Vec::new(),
+            pinned: false,
+        }
+    }
+
+    fn features(mut self, features: &[&'static str]) -> Dependency {
+        self.features.extend(features);
+        self
+    }
+
+    fn pinned(mut self) -> Dependency {
+        self.pinned = true;
+        self
+    }
+
+    // The dependency's line of a `[dependencies]` section
+    fn toml(&self) -> String {
+        if self.features.is_empty() {
+            format!("{} = \"{}\"", self.name, self.version)
+        } else {
+            let features: Vec<String> = self.features.iter().map(|f| format!("\"{}\"", f)).collect();
+            format!(
+                "{} = {{ version = \"{}\", features = [{}] }}",
+                self.name,
+                self.version,
+                features.join(", ")
+            )
+        }
+    }
+}
+
+// The crates the generated code uses
+fn dependencies(
+    spec: &OpenApi,
+    entrypoints: &[Entrypoint],
+    config: &Config,
+) -> Result<Vec<Dependency>> {
+    let mut deps = vec![
+        Dependency::new("serde", "1.0"),
+        Dependency::new("serde_derive", "1.0"),
+        Dependency::new("serde_json", "1.0"),
+    ];
+    match config.framework {
+        Framework::Actix => deps.push(Dependency::new("actix-web", "4")),
+        Framework::Rocket if config.async_handlers => {
+            // Rocket 0.5 implements its param guards for `uuid::Uuid` itself
+            let features: &[&str] = if config.formats.uuid { &["json", "uuid"] } else { &["json"] };
+            deps.push(Dependency::new("rocket", "0.5.0").features(features).pinned());
+        }
+        Framework::Rocket => {
+            let features: &[&str] = if config.formats.uuid { &["uuid"] } else { &[] };
+            // The latest releases are for Rocket 0.5, which the sync code is not
+            deps.push(Dependency::new("rocket", "0.3").pinned());
+            deps.push(Dependency::new("rocket_codegen", "0.3").pinned());
+            deps.push(Dependency::new("rocket_contrib", "0.3").features(features).pinned());
+        }
+    }
+    if config.validated_newtypes {
+        // Pattern checks compile their regexes once, lazily
+        deps.push(Dependency::new("regex", "1"));
+        deps.push(Dependency::new("lazy_static", "1"));
+    }
+    let (schemas, _) = type_schemas(spec, entrypoints, config)?;
+    if uses_base64(&schemas, entrypoints) {
+        deps.push(Dependency::new("base64", "0.21"));
+    }
+    // Rocket has no form decoder for bodies which may also be JSON
+    if config.framework == Framework::Rocket
+        && entrypoints.iter().any(|entry| entry.uses_form_bodies())
+    {
+        deps.push(Dependency::new("serde_urlencoded", "0.7"));
+    }
+    if config.request_logging {
+        // actix-web's logging middleware depends on `log` itself
+        if config.framework == Framework::Rocket {
+            deps.push(Dependency::new("log", "0.4"));
+        }
+        deps.push(Dependency::new("env_logger", "0.10"));
+    }
+    if config.enable_cors {
+        // Each release of rocket_cors supports only one version of Rocket
+        deps.push(match config.framework {
+            Framework::Actix => Dependency::new("actix-cors", "0.6"),
+            Framework::Rocket if config.async_handlers => Dependency::new("rocket_cors", "0.6").pinned(),
+            Framework::Rocket => Dependency::new("rocket_cors", "0.2").pinned(),
+        });
+    }
+    if config.formats.chrono {
+        deps.push(Dependency::new("chrono", "0.4").features(&["serde"]));
+    }
+    if config.formats.uuid {
+        // rocket_contrib 0.3's `UUID` guard wraps the `Uuid` of uuid 0.5, which
+        // the stubs are passed
+        let uuid = if config.sync_rocket() {
+            Dependency::new("uuid", "0.5").pinned()
+        } else {
+            Dependency::new("uuid", "1")
+        };
+        deps.push(uuid.features(&["serde"]));
+    }
+    if config.map_type == process::MapType::IndexMap {
+        deps.push(Dependency::new("indexmap", "2").features(&["serde"]));
+    }
+    Ok(deps)
+}
+
+// Add the dependencies to the `[dependencies]` section of a manifest
+fn add_dependencies(manifest: &str, deps: &[Dependency]) -> String {
+    let lines: Vec<String> = deps.iter().map(|dep| dep.toml()).collect();
+    let lines = lines.join("\n");
+    match manifest.find("[dependencies]\n") {
+        Some(ix) => {
+            let (before, after) = manifest.split_at(ix + "[dependencies]\n".len());
+            format!("{}{}\n{}", before, lines, after)
+        }
+        None => format!("{}\n[dependencies]\n{}\n", manifest, lines),
+    }
+}
+
+// Set the manifest's edition, which `cargo new` picks for itself
+fn set_edition(manifest: &str, edition: &str) -> String {
+    let line = format!("edition = \"{}\"", edition);
+    if manifest.lines().any(|l| l.starts_with("edition")) {
+        let lines: Vec<&str> = manifest
+            .lines()
+            .map(|l| if l.starts_with("edition") { line.as_str() } else { l })
+            .collect();
+        return format!("{}\n", lines.join("\n"));
+    }
+    match manifest.find("[package]\n") {
+        Some(ix) => {
+            let (before, after) = manifest.split_at(ix + "[package]\n".len());
+            format!("{}{}\n{}", before, line, after)
+        }
+        None => format!("[package]\n{}\n{}", line, manifest),
+    }
+}
+
+fn write_edition<P: AsRef<Path>>(dir_path: P, config: &Config) -> Result<()> {
+    let path = dir_path.as_ref().join("Cargo.toml");
+    let mut manifest = String::new();
+    File::open(&path)?.read_to_string(&mut manifest)?;
+    let mut file = File::create(&path)?;
+    file.write_all(set_edition(&manifest, config.edition()).as_bytes())?;
+    Ok(())
+}
+
+fn write_dependencies<P: AsRef<Path>>(
+    dir_path: P,
+    spec: &OpenApi,
+    entrypoints: &[Entrypoint],
+    config: &Config,
+) -> Result<()> {
+    let deps = dependencies(spec, entrypoints, config)?;
+    let path = dir_path.as_ref().join("Cargo.toml");
+    let mut manifest = String::new();
+    File::open(&path)?.read_to_string(&mut manifest)?;
+    let mut file = File::create(&path)?;
+    file.write_all(add_dependencies(&manifest, &deps).as_bytes())?;
+    Ok(())
+}
+
+// Add the dependencies at their latest versions, where any will do
+fn cargo_add<P: AsRef<Path>>(
+    dir_path: P,
+    spec: &OpenApi,
+    entrypoints: &[Entrypoint],
+    config: &Config,
+) -> Result<()> {
+    let mut args = vec!["add"];
+    for dep in dependencies(spec, entrypoints, config)? {
+        if dep.features.is_empty() && !dep.pinned {
+            args.push(dep.name);
+            continue;
+        }
+        let name = if dep.pinned {
+            format!("{}@{}", dep.name, dep.version)
+        } else {
+            dep.name.to_string()
+        };
+        let features = dep.features.join(",");
+        let mut dep_args = vec!["add", name.as_str()];
+        if !features.is_empty() {
+            dep_args.extend(&["--features", features.as_str()]);
+        }
+        cargo_command(&dir_path, &dep_args)?;
+    }
+    cargo_command(&dir_path, &args)
+}
+
+// Helpers shared by the tests of each module
+#[cfg(test)]
+mod test_util {
+    use super::*;
+
+    // The routes of a spec written in YAML
+    pub fn render_endpoints(yaml: &str, config: &Config) -> String {
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = process::extract_entrypoints(&spec);
+        let handlebars = load_templates(&Config::default()).unwrap();
+        let mut out = Vec::new();
+        generate_server_endpoints(&mut out, &handlebars, &spec, &entrypoints, config).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::render_endpoints;
+
+    #[test]
+    fn test_generate_catchers() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: catchers
+  version: 1
+paths:
+  /pets/{petId}:
+    get:
+      operationId: showPetById
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+        404:
+          description: No such pet
+"#;
+        let gen = render_endpoints(yaml, &Config::default());
+        assert!(!gen.contains("#[error(404)]"));
+
+        let config = Config {
+            generate_catchers: true,
+            ..Config::default()
+        };
+        let gen = render_endpoints(yaml, &config);
+        assert!(gen.contains("#[error(404)]"));
+        assert!(gen.contains("fn catch_404("));
+        assert!(gen.contains("errors![catch_404,"));
+    }
+
+    #[test]
+    fn test_async_handlers() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = process::extract_entrypoints(&spec);
+        let handlebars = load_templates(&Config::default()).unwrap();
+        let config = Config {
+            async_handlers: true,
+            ..Config::default()
+        };
+
+        let mut out = Vec::new();
+        generate_function_stubs(&mut out, &handlebars, &entrypoints, &config).unwrap();
+        let stub = String::from_utf8(out).unwrap();
+        assert!(stub.contains("pub async fn list_pets("));
+
+        let mut out = Vec::new();
+        generate_server_endpoints(&mut out, &handlebars, &spec, &entrypoints, &config).unwrap();
+        let gen = String::from_utf8(out).unwrap();
+        assert!(gen.contains("async fn _list_pets("));
+        assert!(gen.contains(").await.map(Json)"));
+        assert!(gen.contains("use rocket::serde::json::Json;"));
+        assert!(gen.contains("#[get(\"/pets?<query..>\")]"));
+        assert!(gen.contains("pub fn mount_api(rocket: rocket::Rocket<rocket::Build>)"));
+
+        let mut out = Vec::new();
+        generate_main(&mut out, &handlebars, &spec, &[], &config).unwrap();
+        let main = String::from_utf8(out).unwrap();
+        assert!(main.contains("#[launch]\nfn rocket() -> _ {"));
+        assert!(!main.contains("rocket_codegen"));
+
+        let mut out = Vec::new();
+        generate_function_stubs(&mut out, &handlebars, &entrypoints, &Config::default()).unwrap();
+        let stub = String::from_utf8(out).unwrap();
+        assert!(stub.contains("pub fn list_pets("));
+    }
+
+    #[test]
+    fn test_conflicting_options() {
+        assert!(Config::default().validate().is_ok());
+        let config = Config {
+            async_handlers: true,
+            negotiate_content: true,
+            ..Config::default()
+        };
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(err, "Conflicting options: negotiate_content is not supported with async_handlers");
+
+        // Every conflict is named at once
+        let config = Config {
+            framework: Framework::Actix,
+            response_enums: true,
+            generate_catchers: true,
+            ..Config::default()
+        };
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("response_enums is not supported by actix-web"));
+        assert!(err.contains("generate_catchers is not supported by actix-web"));
+        let spec = OpenApi::from_string(include_str!("../example_apis/petstore.yaml")).unwrap();
+        assert!(generate_sources_to_map(&spec, &config).is_err());
+    }
+
+    #[test]
+    fn test_actix_framework() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("use actix_web::{web, HttpResponse};"));
+        assert!(gen.contains("#[derive(Deserialize)]\npub struct ListPetsQuery {"));
+        assert!(gen.contains("#[actix_web::get(\"/pets/{pet_id}\")]"));
+        assert!(gen.contains("async fn _show_pet_by_id(path: web::Path<(String,)>,) -> HttpResponse {"));
+        assert!(gen.contains("let (pet_id,) = path.into_inner();"));
+        assert!(gen.contains("async fn _list_pets(query: web::Query<ListPetsQuery>,) -> HttpResponse {"));
+        assert!(gen.contains("match list_pets(query.limit,).await {"));
+        assert!(gen.contains("Ok(body) => HttpResponse::Ok().json(body),"));
+        assert!(gen.contains("pub fn configure(cfg: &mut web::ServiceConfig) {"));
+        assert!(gen.contains("web::scope(\"\")\n            .service(_list_pets)"));
+        assert!(!gen.contains("rocket"));
+        assert!(files["stub.rs"].contains("pub async fn list_pets("));
+        let main = &files["main.rs"];
+        assert!(main.contains("#[actix_web::main]"));
+        assert!(main.contains("App::new().configure(crate::gen::configure)"));
+        assert!(files["gen.rs"].contains("use crate::stub::*;\nuse crate::types::*;"));
+        assert!(main.contains(".unwrap_or(8000);"));
+    }
+
+    #[test]
+    fn test_generate_tests() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            tests: Some("tests".into()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let tests = &files["tests.rs"];
+        assert!(tests.contains("Client::new(gen::mount_api(rocket::ignite()))"));
+        assert!(tests.contains("#[test]\n#[ignore]\nfn test_show_pet_by_id() {"));
+        assert!(tests.contains("client.get(\"/pets/example\")"));
+        // Optional parameters are left out
+        assert!(tests.contains("client.get(\"/pets\")"));
+        assert!(tests.contains("assert!(response.status().class().is_success()"));
+        assert!(files["main.rs"].contains("#[cfg(test)]\nmod tests;\n"));
+
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(!files.contains_key("tests.rs"));
+        assert!(!files["main.rs"].contains("mod tests;"));
+    }
+
+    #[test]
+    fn test_example_stubs() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: Examples
+paths:
+  /pets/{petId}:
+    get:
+      operationId: getPet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+  /greeting:
+    get:
+      operationId: getGreeting
+      responses:
+        200:
+          description: A greeting
+          content:
+            text/plain:
+              schema:
+                type: string
+              example: Hello
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Some pets
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+  /pets/count:
+    get:
+      operationId: countPets
+      responses:
+        200:
+          description: The number of pets
+          content:
+            application/json:
+              schema:
+                type: integer
+              example: 3
+  /pets/first:
+    get:
+      operationId: firstPet
+      responses:
+        200:
+          description: The first pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+              example:
+                name: 5
+components:
+  schemas:
+    Pet:
+      properties:
+        name:
+          type: string
+      example:
+        name: Rex
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            example_stubs: true,
+            ..Config::default()
+        };
+        take_warnings();
+        let stub = &generate_sources_to_map(&spec, &config).unwrap()["stub.rs"];
+        assert!(stub.contains(
+            "Ok(::serde_json::from_str(\"{\\\"name\\\":\\\"Rex\\\"}\").unwrap())"
+        ));
+        assert!(stub.contains("pub fn count_pets() -> Result<i64, ()> {\n    Ok(3)\n}"));
+        // An example which doesn't match its schema is left out
+        assert!(stub.contains("pub fn first_pet() -> Result<Pet, ()> {\n    unimplemented!()\n}"));
+        assert!(take_warnings().iter().any(|w| w.contains("'first_pet' does not match")));
+        assert!(stub.contains("Ok(\"Hello\".into())"));
+        // Without an example the stub is left unimplemented
+        assert!(stub.contains("pub fn list_pets() -> Result<Vec<Pet>, ()> {\n    unimplemented!()\n}"));
+
+        let stub = &generate_sources_to_map(&spec, &Config::default()).unwrap()["stub.rs"];
+        assert!(!stub.contains("Ok("));
+    }
+
+    #[test]
+    fn test_binary_formats() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: Files
+paths:
+  /files:
+    post:
+      operationId: uploadFile
+      requestBody:
+        required: true
+        content:
+          application/octet-stream:
+            schema:
+              type: string
+              format: binary
+      responses:
+        200:
+          description: The file's checksum
+          content:
+            application/json:
+              schema:
+                type: string
+                format: byte
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("data = \"<body>\")]"));
+        assert!(gen.contains("fn _upload_file(body: rocket::Data,) -> Result<Json<Base64>, ()>"));
+        assert!(gen.contains("upload_file(body,)"));
+        assert!(files["stub.rs"].contains("pub fn upload_file(body: rocket::Data,) -> Result<Base64, ()>"));
+        assert!(files["types.rs"].contains("pub struct Base64(pub Vec<u8>);"));
+        assert!(files["main.rs"].contains("extern crate base64;\n"));
+
+        let config = Config {
+            async_handlers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains(
+            "async fn _upload_file(body: rocket::Data<'_>,limits: &rocket::data::Limits,)"
+        ));
+        assert!(gen.contains(
+            "upload_file(body.open(limits.get(\"bytes\").unwrap_or(rocket::data::Limits::BYTES)),)"
+        ));
+        assert!(files["stub.rs"].contains(
+            "pub async fn upload_file(body: rocket::data::DataStream<'_>,)"
+        ));
+
+        // Octet streams are streamed even without a binary schema
+        let yaml = yaml.replace(
+            "schema:\n              type: string\n              format: binary",
+            "example: abc",
+        );
+        let spec = OpenApi::from_string(&yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["gen.rs"].contains("fn _upload_file(body: rocket::Data,)"));
+        assert!(!files["gen.rs"].contains("Json<Vec<u8>>"));
+
+        let files = generate_sources_to_map(&OpenApi::from_string(
+            include_str!("../example_apis/petstore.yaml")).unwrap(), &Config::default()).unwrap();
+        assert!(!files["types.rs"].contains("Base64"));
+        assert!(!files["main.rs"].contains("base64"));
+    }
+
+    #[test]
+    fn test_chrono_formats() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: Events
+paths: {}
+components:
+  schemas:
+    Event:
+      required: [at]
+      properties:
+        at:
+          type: string
+          format: date-time
+        on:
+          type: string
+          format: date
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            formats: process::Formats {
+                chrono: true,
+                ..process::Formats::default()
+            },
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let types = &files["types.rs"];
+        assert!(types.contains("pub at: ::chrono::DateTime<::chrono::Utc>,"));
+        assert!(types.contains("pub on: Option<::chrono::NaiveDate>,"));
+        // Rocket 0.3's crates are of the 2015 edition, so declare their dependencies
+        assert!(files["main.rs"].contains("extern crate serde_json;\nextern crate chrono;\n"));
+
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["types.rs"].contains("pub at: String,"));
+        assert!(!files["main.rs"].contains("chrono"));
+    }
+
+    #[test]
+    fn test_index_map() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: Labels
+paths: {}
+components:
+  schemas:
+    Labels:
+      type: object
+      additionalProperties:
+        type: string
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            map_type: process::MapType::IndexMap,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["types.rs"].contains("pub type Labels = ::indexmap::IndexMap<String, String>;"));
+        assert!(files["main.rs"].contains("extern crate indexmap;\n"));
+
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(!files["main.rs"].contains("indexmap"));
+    }
+
+    #[test]
+    fn test_uuid_format() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: Pets
+paths:
+  /pets/{petId}:
+    get:
+      operationId: showPet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+            format: uuid
+        - name: owner
+          in: query
+          schema:
+            type: string
+            format: uuid
+      responses:
+        204:
+          description: A pet
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            formats: process::Formats {
+                uuid: true,
+                ..process::Formats::default()
+            },
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("fn _show_pet(pet_id: rocket_contrib::UUID,query: ShowPetQuery,)"));
+        assert!(gen.contains("pub owner: Option<rocket_contrib::UUID>,"));
+        assert!(gen.contains("show_pet(pet_id.into_inner(),query.owner.map(|id| id.into_inner()),)"));
+        assert!(files["stub.rs"].contains("pub fn show_pet(pet_id: ::uuid::Uuid,owner: Option<::uuid::Uuid>,)"));
+        assert!(files["main.rs"].contains("extern crate uuid;\n"));
+
+        let config = Config {
+            async_handlers: true,
+            ..config
+        };
+        let gen = &generate_sources_to_map(&spec, &config).unwrap()["gen.rs"];
+        assert!(gen.contains("async fn _show_pet(pet_id: ::uuid::Uuid,query: ShowPetQuery,)"));
+
+        let stub = &generate_sources_to_map(&spec, &Config::default()).unwrap()["stub.rs"];
+        assert!(stub.contains("pub fn show_pet(pet_id: String,owner: Option<String>,)"));
+    }
+
+    #[test]
+    fn test_stub_body() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: stubs
+  version: 1
+paths:
+  /things:
+    get:
+      operationId: listThings
+      responses:
+        200:
+          description: The things
+          content:
+            application/json:
+              schema:
+                type: string
+  /things/{id}:
+    delete:
+      operationId: deleteThing
+      parameters:
+        - name: id
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        204:
+          description: Deleted
+        404:
+          description: No such thing
+          content:
+            application/json:
+              schema:
+                type: string
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert_eq!(files["stub.rs"].matches("unimplemented!()").count(), 2);
+
+        let config = Config {
+            stub_body: StubBody::Todo,
+            ..Config::default()
+        };
+        let err = generate_sources_to_map(&spec, &config).unwrap_err().to_string();
+        assert!(err.contains("stub_body Todo"));
+        let config = Config {
+            async_handlers: true,
+            ..config
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["stub.rs"].contains("pub async fn list_things() -> Result<String, ()> {\n    todo!()"));
+
+        let config = Config {
+            stub_body: StubBody::NotImplemented,
+            ..Config::default()
+        };
+        take_warnings();
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let stub = &files["stub.rs"];
+        assert!(stub.contains(
+            "pub fn list_things() -> Result<String, rocket::response::Failure> {\n    \
+             Err(rocket::response::Failure(rocket::http::Status::NotImplemented))"
+        ));
+        assert!(files["gen.rs"].contains("-> Result<Json<String>, rocket::response::Failure> {"));
+        // Rocket 0.3 responds to the stub's error with a 501
+        {
+            use rocket::response::{Failure, Responder};
+            let client = rocket::local::Client::new(rocket::ignite()).unwrap();
+            let request = client.get("/things");
+            let result: ::std::result::Result<String, Failure> =
+                Err(Failure(Status::NotImplemented));
+            assert_eq!(result.respond_to(request.inner()).unwrap_err(), Status::NotImplemented);
+        }
+        // The documented error can't carry the status
+        assert!(stub.contains("-> Result<(), String> {\n    unimplemented!()"));
+        assert!(take_warnings().iter().any(|w| w.contains("'delete_thing'")));
+
+        let config = Config {
+            stub_body: StubBody::NotImplemented,
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["stub.rs"].contains("Err(actix_web::http::StatusCode::NOT_IMPLEMENTED)"));
+        assert!(files["gen.rs"].contains("Err(status) => HttpResponse::new(status),"));
+
+        // Rocket 0.5 responds to a `Status` error with the status
+        let config = Config {
+            stub_body: StubBody::NotImplemented,
+            async_handlers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["stub.rs"].contains("Err(rocket::http::Status::NotImplemented)"));
+    }
+
+    #[test]
+    fn test_health_route() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(!files["gen.rs"].contains("_get_health"));
+
+        let config = Config {
+            health_path: Some("/health".into()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains("#[get(\"/health\")]"));
+        assert!(files["gen.rs"].contains("_get_health,"));
+        assert!(files["stub.rs"].contains(
+            "pub fn get_health() -> Result<::serde_json::Value, ()> {\n    \
+             Ok(vec![(\"status\", \"ok\")].into_iter().collect())\n}"
+        ));
+
+        // A route the spec defines itself is left alone
+        let config = Config {
+            health_path: Some("/pets".into()),
+            ..Config::default()
+        };
+        take_warnings();
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(!files["gen.rs"].contains("_get_health"));
+        assert!(take_warnings().iter().any(|w| w.contains("no health check")));
+    }
+
+    #[test]
+    fn test_generate_sources_to_string() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let sources = generate_sources_to_string(&spec, &Config::default()).unwrap();
+        assert!(sources.gen.contains("#[get(\"/pets?<query>\")]"));
+        assert!(sources.stub.contains("pub fn list_pets("));
+        assert!(sources.types.contains("pub struct Pet"));
+        assert!(sources.main.contains("fn main()"));
+
+        let config = Config {
+            api_module: Some("api".into()),
+            ..Config::default()
+        };
+        let sources = generate_sources_to_string(&spec, &config).unwrap();
+        assert!(sources.gen.contains("#[get(\"/pets?<query>\")]"));
+
+        let config = Config {
+            split_types: true,
+            ..Config::default()
+        };
+        assert!(generate_sources_to_string(&spec, &config).is_err());
+    }
+
+    #[test]
+    fn test_embedded_spec() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        let literal = format!("{:?}", serde_json::to_string(&spec).unwrap());
+        assert!(files["gen.rs"].contains(&format!("pub const SPEC: &str = {};", literal)));
+        assert!(files["gen.rs"].contains("\\\"title\\\":\\\"Swagger Petstore\\\""));
+        assert!(files["gen.rs"].contains(
+            "fn _get_swagger() -> Result<Json<::serde_json::Value>, ()> {\n    \
+             Ok(Json(::serde_json::from_str(SPEC).expect(\"the embedded spec is valid JSON\")))\n}"
+        ));
+        // The route needs no implementing, so has no stub
+        assert!(!files["stub.rs"].contains("get_swagger"));
+
+        let config = Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains(
+            "async fn _get_swagger() -> HttpResponse {\n    \
+             HttpResponse::Ok().content_type(\"application/json\").body(SPEC)\n}"
+        ));
+
+        let config = Config {
+            framework: Framework::Actix,
+            swagger_cache_control: Some("max-age=3600".into()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains(
+            "HttpResponse::Ok().content_type(\"application/json\")\
+             .insert_header((\"Cache-Control\", \"max-age=3600\")).body(SPEC)"
+        ));
+
+        let config = Config {
+            swagger_content_type: "application/vnd.oai.openapi+json".into(),
+            swagger_cache_control: Some("max-age=3600".into()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains(
+            "fn _get_swagger() -> rocket::response::Response<'static> {\n    \
+             let mut response = rocket::response::Response::build();\n    \
+             response.raw_header(\"Content-Type\", \"application/vnd.oai.openapi+json\");\n    \
+             response.raw_header(\"Cache-Control\", \"max-age=3600\");\n    \
+             response.sized_body(::std::io::Cursor::new(SPEC)).finalize()\n}"
+        ));
+
+        let config = Config {
+            async_handlers: true,
+            swagger_cache_control: Some("no-cache".into()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains(
+            "response.sized_body(SPEC.len(), ::std::io::Cursor::new(SPEC)).finalize()"
+        ));
+    }
+
+    #[test]
+    fn test_listen_config() {
+        let spec = OpenApi::from_string(include_str!("../example_apis/petstore.yaml")).unwrap();
+        let handlebars = load_templates(&Config::default()).unwrap();
+        let render_main = |config: &Config| {
+            let mut out = Vec::new();
+            generate_main(&mut out, &handlebars, &spec, &[], config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let main = render_main(&Config::default());
+        assert!(main.contains("let rocket = rocket::Rocket::ignite();"));
+        assert!(!main.contains("ROCKET_PORT"));
+
+        let config = Config {
+            port: Some(8080),
+            address: Some("0.0.0.0".into()),
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains(
+            "    let config = rocket::config::Config::build(environment)\n        \
+             .address(std::env::var(\"ROCKET_ADDRESS\").unwrap_or(\"0.0.0.0\".into()))\n        \
+             .port(std::env::var(\"ROCKET_PORT\").ok().and_then(|port| port.parse().ok()).unwrap_or(8080))\n        \
+             .finalize()"
+        ));
+        assert!(main.contains("let rocket = rocket::custom(config, true);"));
+
+        let config = Config {
+            port: Some(8080),
+            async_handlers: true,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains("    let figment = rocket::Config::figment();\n    let figment = figment.merge((\"port\""));
+        assert!(main.contains(".unwrap_or(8080)));\n    crate::gen::mount_api"));
+        assert!(main.contains("gen::mount_api(rocket::custom(figment))\n}"));
+        assert!(!main.contains("ROCKET_ADDRESS"));
+    }
+
+    #[test]
+    fn test_cors() {
+        let spec = OpenApi::from_string(include_str!("../example_apis/petstore.yaml")).unwrap();
+        let render_main = |config: &Config| {
+            let handlebars = load_templates(config).unwrap();
+            let mut out = Vec::new();
+            generate_main(&mut out, &handlebars, &spec, &[], config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert!(!render_main(&Config::default()).contains("cors"));
+
+        let config = Config {
+            enable_cors: true,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains("extern crate rocket_cors;\n"));
+        assert!(main.contains(
+            "    let rocket = gen::mount_api(rocket);\n    \
+             // Answers preflight requests and adds CORS headers, allowing any origin\n    \
+             let rocket = rocket.attach(rocket_cors::Cors::default());\n    \
+             println!"
+        ));
+
+        let config = Config {
+            enable_cors: true,
+            async_handlers: true,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains(
+            "gen::mount_api(rocket::build())\n        \
+             .attach(rocket_cors::CorsOptions::default().to_cors()"
+        ));
+
+        let config = Config {
+            enable_cors: true,
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains("extern crate actix_cors;\n"));
+        assert!(main.contains(
+            "actix_web::App::new()\n            \
+             // Answers preflight requests and adds CORS headers, allowing any origin\n            \
+             .wrap(actix_cors::Cors::permissive())\n            \
+             .configure(crate::gen::configure)"
+        ));
+    }
+
+    #[test]
+    fn test_request_logging() {
+        let spec = OpenApi::from_string(include_str!("../example_apis/petstore.yaml")).unwrap();
+        let render_main = |config: &Config| {
+            let handlebars = load_templates(config).unwrap();
+            let mut out = Vec::new();
+            generate_main(&mut out, &handlebars, &spec, &[], config).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert!(!render_main(&Config::default()).contains("log"));
+
+        let config = Config {
+            request_logging: true,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains("#[macro_use]\nextern crate log;\nextern crate env_logger;\n"));
+        assert!(main.contains("fn main() {\n    env_logger::init();\n"));
+        assert!(main.contains(
+            "    let rocket = gen::mount_api(rocket);\n    \
+             // Logs the method, path and status of each request\n    \
+             let rocket = rocket.attach(rocket::fairing::AdHoc::on_response(|request, response| {\n        \
+             info!(target: \"access\", \"method={} path={} status={}\""
+        ));
+
+        let config = Config {
+            request_logging: true,
+            async_handlers: true,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains("fn rocket() -> _ {\n    env_logger::init();\n"));
+        assert!(main.contains(
+            "gen::mount_api(rocket::build())\n        \
+             // Logs the method, path and status of each request\n        \
+             .attach(rocket::fairing::AdHoc::on_response(\"Request logging\""
+        ));
+
+        let config = Config {
+            request_logging: true,
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let main = render_main(&config);
+        assert!(main.contains("extern crate env_logger;\n"));
+        assert!(main.contains(
+            ".wrap(\n                \
+             actix_web::middleware::Logger::new(\"method=%{method}xi path=%U status=%s\")"
+        ));
+    }
+
+    #[test]
+    fn test_content_negotiation() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: negotiation
+  version: 1
+paths:
+  /report:
+    get:
+      operationId: getReport
+      responses:
+        200:
+          description: The report
+          content:
+            application/json:
+              schema:
+                type: string
+            text/csv:
+              schema:
+                type: string
+"#;
+        let config = Config {
+            negotiate_content: true,
+            ..Config::default()
+        };
+        let gen = render_endpoints(yaml, &config);
+        assert!(gen.contains("pub enum GetReportMediaType {"));
+        assert!(gen.contains("GetReportMediaType::ApplicationJson => \"application/json\","));
+        assert!(gen.contains("GetReportMediaType::TextCsv => \"text/csv\","));
+        assert!(gen.contains("fn _get_report(accept: GetReportMediaType,) -> Result<rocket::response::Content<String>, ()>"));
+        assert!(gen.contains("get_report(accept,)"));
+        // The stub takes the media type, which is defined alongside the route
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["stub.rs"].contains("use gen::GetReportMediaType;\n"));
+        assert!(files["stub.rs"].contains("accept: GetReportMediaType"));
+
+        let gen = render_endpoints(yaml, &Config::default());
+        assert!(!gen.contains("GetReportMediaType"));
+        assert!(gen.contains("-> Result<Json<String>, ()>"));
+    }
+
+    #[test]
+    fn test_response_content_types() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: content
+  version: 1
+paths:
+  /pet:
+    get:
+      operationId: getPet
+      responses:
+        200:
+          description: A pet
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Pet'
+  /greeting:
+    get:
+      operationId: getGreeting
+      responses:
+        200:
+          description: A greeting
+          content:
+            text/plain:
+              schema:
+                type: string
+  /page:
+    get:
+      operationId: getPage
+      responses:
+        200:
+          description: A page
+          content:
+            text/html:
+              schema:
+                type: string
+  /photo:
+    get:
+      operationId: getPhoto
+      responses:
+        200:
+          description: A photo
+          content:
+            image/png:
+              schema:
+                type: string
+                format: binary
+components:
+  schemas:
+    Pet:
+      properties:
+        name:
+          type: string
+"#;
+        let gen = render_endpoints(yaml, &Config::default());
+        assert!(gen.contains("fn _get_pet() -> Result<Json<Pet>, ()> {\n    get_pet().map(Json)\n}"));
+        assert!(gen.contains("fn _get_greeting() -> Result<String, ()> {\n    get_greeting()\n}"));
+        assert!(gen.contains("fn _get_page() -> Result<rocket::response::Content<String>, ()>"));
+        assert!(gen.contains("ContentType::parse_flexible(\"text/html\").unwrap(), body))"));
+        assert!(gen.contains("fn _get_photo() -> Result<rocket::response::Content<Vec<u8>>, ()>"));
+        // The spec itself is served as JSON, not as a JSON-encoded string
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        assert!(files["gen.rs"].contains("fn _get_swagger() -> Result<Json<::serde_json::Value>, ()>"));
+    }
+
+    #[test]
+    fn test_operation_server_override() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: servers
+  version: 1
+servers:
+  - url: http://example.com/v1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Pets
+  /uploads:
+    post:
+      operationId: uploadFile
+      servers:
+        - url: https://uploads.example.com/files/
+      responses:
+        200:
+          description: Uploaded
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = process::extract_entrypoints(&spec);
+        let base_paths: Vec<_> = entrypoints.iter().map(|e| e.base_path.as_str()).collect();
+        assert_eq!(base_paths, vec!["/v1", "/files"]);
+
+        let config = Config {
+            mount_server_paths: true,
+            ..Config::default()
+        };
+        fn mounted<'a>(gen: &'a str, base_path: &str) -> &'a str {
+            let start = gen.find(&format!(".mount(\"{}\"", base_path)).unwrap();
+            let end = start + gen[start..].find("])").unwrap();
+            &gen[start..end]
+        }
+        let gen = render_endpoints(yaml, &config);
+        assert!(mounted(&gen, "/files").contains("_upload_file,"));
+        assert!(!mounted(&gen, "/files").contains("_list_pets,"));
+        assert!(mounted(&gen, "/v1").contains("_list_pets,"));
+
+        let gen = render_endpoints(yaml, &Config::default());
+        assert!(mounted(&gen, "/").contains("_list_pets,"));
+        assert!(mounted(&gen, "/").contains("_upload_file,"));
+    }
+
+    #[test]
+    fn test_server_base_path() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: servers
+  version: 1
+servers:
+  - url: https://x/api/v1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Pets
+"#;
+        let config = Config {
+            mount_server_paths: true,
+            ..Config::default()
+        };
+        let gen = render_endpoints(yaml, &config);
+        assert!(gen.contains(".mount(\"/api/v1\", routes![\n        _list_pets,"));
+        let actix_config = Config {
+            framework: Framework::Actix,
+            mount_server_paths: true,
+            ..Config::default()
+        };
+        let spec = parse_spec(yaml, Format::Yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &actix_config).unwrap();
+        assert!(files["gen.rs"].contains("web::scope(\"/api/v1\")"));
+
+        // Server variables are substituted by their defaults
+        let yaml = yaml.replace(
+            "https://x/api/v1",
+            "\"{scheme}://x/{base}\"\n    variables:\n      \
+             scheme:\n        default: https\n      \
+             base:\n        default: api/v2",
+        );
+        let gen = render_endpoints(&yaml, &config);
+        assert!(gen.contains(".mount(\"/api/v2\", routes!["));
+    }
+
+    #[test]
+    fn test_generate_client() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = process::extract_entrypoints(&spec);
+        let handlebars = load_templates(&Config::default()).unwrap();
+        let mut out = Vec::new();
+        generate_client(&mut out, &handlebars, &entrypoints, &Config::default()).unwrap();
+        let client = String::from_utf8(out).unwrap();
+        assert!(client.contains("pub struct Client {"));
+        assert!(client.contains(
+            "pub fn show_pet_by_id(&self,pet_id: String,) -> reqwest::Result<Pets> {"
+        ));
+        assert!(client.contains("\"{}/pets/{pet_id}\",\n            self.base_url,pet_id = pet_id,"));
+        assert!(client.contains("reqwest::Method::Get, &url"));
+        assert!(client.contains("request.query(&[(\"limit\", &limit)]);"));
+        assert!(client.contains("pub fn create_pets(&self,) -> reqwest::Result<()> {"));
+    }
+
+    #[test]
+    fn test_generate_dockerfile() {
+        let handlebars = load_templates(&Config::default()).unwrap();
+        let mut out = Vec::new();
+        generate_dockerfile(&mut out, &handlebars, "petstore", 8080, &Config::default()).unwrap();
+        let dockerfile = String::from_utf8(out).unwrap();
+        assert!(dockerfile.contains("/target/release/petstore /usr/local/bin/petstore"));
+        assert!(dockerfile.contains("CMD [\"petstore\"]"));
+        assert!(dockerfile.contains("EXPOSE 8080"));
+        assert!(dockerfile.contains("ENV ROCKET_PORT=8080"));
+
+        let config = Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let handlebars = load_templates(&config).unwrap();
+        let mut out = Vec::new();
+        generate_dockerfile(&mut out, &handlebars, "petstore", 8080, &config).unwrap();
+        let dockerfile = String::from_utf8(out).unwrap();
+        assert!(dockerfile.contains("FROM rust:1-bookworm AS builder"));
+        assert!(dockerfile.contains("ENV ADDRESS=0.0.0.0\nENV PORT=8080\n"));
+        assert!(!dockerfile.contains("ROCKET_"));
+    }
+
+    #[test]
+    fn test_server_port() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        assert_eq!(server_port(&spec), None);
+        let yaml = yaml.replace("petstore.swagger.io/v1", "localhost:8080/v1");
+        let spec = OpenApi::from_string(&yaml).unwrap();
+        assert_eq!(server_port(&spec), Some(8080));
+    }
+
+    #[test]
+    fn test_regenerate_unchanged() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let tmp_dir = TempDir::new("thruster-test").unwrap();
+        let config = Config::default();
+        generate_sources(&spec, tmp_dir.path(), &config).unwrap();
+        let gen_path = tmp_dir.path().join("gen.rs");
+        let modified = || fs::metadata(&gen_path).unwrap().modified().unwrap();
+        let before = modified();
+
+        ::std::thread::sleep(::std::time::Duration::from_millis(20));
+        generate_sources(&spec, tmp_dir.path(), &config).unwrap();
+        assert_eq!(modified(), before);
+
+        // A changed file is rewritten
+        File::create(&gen_path).unwrap();
+        generate_sources(&spec, tmp_dir.path(), &config).unwrap();
+        let mut gen = String::new();
+        File::open(&gen_path).unwrap().read_to_string(&mut gen).unwrap();
+        assert!(gen.contains("pub fn mount_api("));
+    }
+
+    #[test]
+    fn test_move_dir() {
+        let tmp_dir = TempDir::new("thruster-test").unwrap();
+        let from = tmp_dir.path().join("from");
+        let to = tmp_dir.path().join("to");
+        fs::create_dir_all(from.join("src")).unwrap();
+        File::create(from.join("src/main.rs")).unwrap();
+        assert!(!is_occupied(&to).unwrap());
+
+        fs::create_dir(&to).unwrap();
+        assert!(!is_occupied(&to).unwrap());
+        File::create(to.join("old.rs")).unwrap();
+        assert!(is_occupied(&to).unwrap());
+
+        // A failed move leaves the existing directory as it was
+        assert!(move_dir(&tmp_dir.path().join("missing"), &to).is_err());
+        assert!(to.join("old.rs").is_file());
+
+        move_dir(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert!(to.join("src/main.rs").is_file());
+        assert!(!to.join("old.rs").exists());
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+
+        let copy = tmp_dir.path().join("copy");
+        copy_dir(&to, &copy).unwrap();
+        assert!(copy.join("src/main.rs").is_file());
+        assert!(to.join("src/main.rs").is_file());
+    }
+
+    #[test]
+    fn test_missing_tools() {
+        match check_tools("thruster-missing-cargo", &Config::default()) {
+            Err(Error(ErrorKind::MissingTool(ref tool, _), _)) => assert_eq!(tool, "cargo"),
+            other => panic!("Expected a missing tool, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_missing_clippy() {
+        use std::os::unix::fs::PermissionsExt;
+        // A cargo with every subcommand but clippy
+        let tmp_dir = TempDir::new("thruster-tools").unwrap();
+        let cargo = tmp_dir.path().join("cargo");
+        fs::write(&cargo, "#!/bin/sh\n[ \"$1\" != clippy ]\n").unwrap();
+        fs::set_permissions(&cargo, fs::Permissions::from_mode(0o755)).unwrap();
+        let cargo = cargo.to_str().unwrap();
+        check_tools(cargo, &Config::default()).unwrap();
+        let config = Config {
+            run_clippy: true,
+            ..Config::default()
+        };
+        match check_tools(cargo, &config) {
+            Err(Error(ErrorKind::MissingTool(ref tool, _), _)) => assert_eq!(tool, "cargo clippy"),
+            other => panic!("Expected a missing tool, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            async_handlers: true,
+            enable_cors: true,
+            ..Config::default()
+        };
+        let deps = dependencies(&spec, &entrypoints(&spec, &config).unwrap(), &config).unwrap();
+        let manifest = "[package]\nname = \"pets\"\n\n[dependencies]\n";
+        assert_eq!(
+            add_dependencies(manifest, &deps),
+            "[package]\nname = \"pets\"\n\n[dependencies]\n\
+             serde = \"1.0\"\n\
+             serde_derive = \"1.0\"\n\
+             serde_json = \"1.0\"\n\
+             rocket = { version = \"0.5.0\", features = [\"json\"] }\n\
+             rocket_cors = \"0.6\"\n"
+        );
+
+        let config = Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let deps = dependencies(&spec, &entrypoints(&spec, &config).unwrap(), &config).unwrap();
+        let names: Vec<&str> = deps.iter().map(|dep| dep.name).collect();
+        assert_eq!(names, vec!["serde", "serde_derive", "serde_json", "actix-web"]);
+        // A manifest without the section gains one
+        assert!(add_dependencies("[package]\n", &deps).ends_with("\n[dependencies]\nserde = \"1.0\"\n\
+             serde_derive = \"1.0\"\nserde_json = \"1.0\"\nactix-web = \"4\"\n"));
+    }
+
+    #[test]
+    fn test_count_lints() {
+        let messages = r#"{"reason":"compiler-artifact","target":{"name":"linted"}}
+{"reason":"compiler-message","message":{"level":"warning","message":"needless return","code":{"code":"clippy::needless_return"}}}
+{"reason":"compiler-message","message":{"level":"warning","message":"redundant clone","code":{"code":"clippy::redundant_clone"}}}
+{"reason":"compiler-message","message":{"level":"warning","message":"unused variable","code":{"code":"unused_variables"}}}
+{"reason":"compiler-message","message":{"level":"warning","message":"2 warnings emitted","code":null}}
+"#;
+        assert_eq!(count_lints(messages), 2);
+    }
+
+    #[test]
+    fn test_cargo_clippy() {
+        let tmp_dir = TempDir::new("thruster-clippy").unwrap();
+        cargo_new(tmp_dir.path(), "linted").unwrap();
+        let config = Config {
+            run_clippy: true,
+            ..Config::default()
+        };
+        let crate_path = tmp_dir.path().join("linted");
+        assert_eq!(clippy_lints(&crate_path, &Config::default()).unwrap(), None);
+        if check_tools("cargo", &config).is_err() {
+            // Without clippy there is nothing to run
+            return;
+        }
+        // A new crate has nothing to lint
+        assert_eq!(clippy_lints(&crate_path, &config).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_generate_from_str() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        let names: Vec<&str> = files.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names, vec!["gen.rs", "main.rs", "stub.rs", "types.rs"]);
+        assert!(files["gen.rs"].contains("fn _list_pets("));
+
+        let json = r#"{
+            "openapi": "3.0.0",
+            "info": {"title": "simple", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {"Pet": {"properties": {"id": {"type": "integer"}}}}}
+        }"#;
+        let files = generate_from_str(json, Format::Json).unwrap();
+        assert!(files["types.rs"].contains("pub struct Pet"));
+    }
+
+    #[test]
+    fn test_configured_module_names() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            gen: "routes".into(),
+            stub: "handlers".into(),
+            types: "models".into(),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let names: Vec<&str> = files.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names, vec!["handlers.rs", "main.rs", "models.rs", "routes.rs"]);
+        assert!(files["main.rs"].contains("mod routes;\nmod handlers;\nmod models;\n"));
+        assert!(files["main.rs"].contains("let rocket = routes::mount_api(rocket);"));
+        assert!(files["routes.rs"].contains("use handlers::*;\nuse models::*;"));
+        assert!(files["handlers.rs"].contains("use models::*;"));
+
+        // The defaults are unchanged
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["main.rs"].contains("mod gen;\nmod stub;\nmod types;\n"));
+        assert!(files["gen.rs"].contains("use stub::*;\nuse types::*;"));
+
+        // Async code is in the 2018 edition, where paths start from the crate
+        let config = Config {
+            async_handlers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains("use crate::stub::*;\nuse crate::types::*;"));
+        assert!(files["stub.rs"].contains("use crate::types::*;"));
+        assert!(files["main.rs"].contains("crate::gen::mount_api(rocket::build())"));
+    }
+
+    #[test]
+    fn test_set_edition() {
+        let manifest = "[package]\nname = \"pets\"\nedition = \"2021\"\n\n[dependencies]\n";
+        assert_eq!(
+            set_edition(manifest, "2015"),
+            "[package]\nname = \"pets\"\nedition = \"2015\"\n\n[dependencies]\n"
+        );
+        assert_eq!(
+            set_edition("[package]\nname = \"pets\"\n", "2021"),
+            "[package]\nedition = \"2021\"\nname = \"pets\"\n"
+        );
+    }
+
+    #[test]
+    fn test_user_templates() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let tmp_dir = TempDir::new("thruster-templates").unwrap();
+        File::create(tmp_dir.path().join("stub.hbs"))
+            .unwrap()
+            .write_all(b"// house style\n{{#each entrypoints as |e|}}// {{e.function}}\n{{/each}}")
+            .unwrap();
+        let config = Config {
+            template_dir: Some(tmp_dir.path().to_string_lossy().into_owned()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["stub.rs"].starts_with("// house style\n"));
+        assert!(files["stub.rs"].contains("// list_pets\n"));
+        // Templates missing from the directory fall back to the bundled ones
+        assert!(files["gen.rs"].contains("pub fn mount_api("));
+    }
+
+    #[test]
+    fn test_external_refs() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: split
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - $ref: './common.yaml#/components/parameters/Limit'
+      responses:
+        200:
+          description: Some pets
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: '#/components/schemas/Pet'
+        default:
+          $ref: 'common.yaml#/components/responses/Error'
+components:
+  schemas:
+    Pet:
+      properties:
+        name:
+          type: string
+"#;
+        let common = r#"
+components:
+  parameters:
+    Limit:
+      name: limit
+      in: query
+      schema:
+        type: integer
+  responses:
+    Error:
+      description: Something went wrong
+      content:
+        application/json:
+          schema:
+            $ref: '#/components/schemas/Error'
+  schemas:
+    Error:
+      properties:
+        message:
+          type: string
+"#;
+        let tmp_dir = TempDir::new("thruster-external").unwrap();
+        let path = tmp_dir.path().join("spec.yaml");
+        File::create(&path).unwrap().write_all(yaml.as_bytes()).unwrap();
+        File::create(tmp_dir.path().join("common.yaml"))
+            .unwrap()
+            .write_all(common.as_bytes())
+            .unwrap();
+        let spec = load_spec(&path).unwrap();
+        let components = spec.components.as_ref().unwrap();
+        let schemas = components.schemas.as_ref().unwrap();
+        assert!(schemas.contains_key("Pet"));
+        assert!(schemas.contains_key("Error"));
+        assert!(components.parameters.as_ref().unwrap().contains_key("Limit"));
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["stub.rs"]
+            .contains("pub fn list_pets(limit: Option<i64>,) -> Result<Vec<Pet>, Error>"));
+        assert!(files["types.rs"].contains("pub struct Error"));
+
+        // Without the file to hand, the reference is reported rather than
+        // left dangling
+        assert!(process::ref_name("common.yaml#/components/schemas/Error").is_err());
+        let missing = tmp_dir.path().join("missing.yaml");
+        File::create(&missing)
+            .unwrap()
+            .write_all(yaml.replace("common.yaml", "nothing.yaml").as_bytes())
+            .unwrap();
+        let err = load_spec(&missing).unwrap_err();
+        assert!(err.to_string().contains("nothing.yaml"));
+    }
+
+    #[test]
+    fn test_load_swagger2() {
+        let yaml = r#"
+swagger: "2.0"
+info:
+  title: simple
+  version: 1.0.0
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Some pets
+          schema:
+            type: array
+            items:
+              $ref: '#/definitions/Pet'
+definitions:
+  Pet:
+    properties:
+      name:
+        type: string
+"#;
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        assert!(files["gen.rs"].contains("-> Result<Json<Vec<Pet>>, ()>"));
+        assert!(files["types.rs"].contains("pub struct Pet"));
+
+        let tmp_dir = TempDir::new("thruster-swagger2").unwrap();
+        let path = tmp_dir.path().join("spec.yaml");
+        File::create(&path).unwrap().write_all(yaml.as_bytes()).unwrap();
+        let spec = load_spec(&path).unwrap();
+        assert!(spec.paths.contains_key("/pets"));
+    }
+
+    #[test]
+    fn test_anonymous_types() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: inline
+  version: 1
+paths:
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              required:
+                - name
+              properties:
+                name:
+                  type: string
+                tag:
+                  type: string
+      responses:
+        201:
+          description: Created
+"#;
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        assert!(files["gen.rs"].contains("body: Json<CreatePetAnonArg1>"));
+        let types = &files["types.rs"];
+        assert!(types.contains("pub struct CreatePetAnonArg1 {"));
+        assert!(types.contains("pub name: String,"));
+        assert!(types.contains("pub tag: Option<String>,"));
+    }
+
+    #[test]
+    fn test_anonymous_types_deduplicated() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: inline
+  version: 1
+paths:
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              properties:
+                name:
+                  type: string
+      responses:
+        200:
+          description: Created
+          content:
+            application/json:
+              schema:
+                properties:
+                  name:
+                    type: string
+  /pets/{petId}:
+    put:
+      operationId: updatePet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              properties:
+                name:
+                  type: string
+      responses:
+        204:
+          description: Updated
+"#;
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("body: Json<CreatePetAnonArg1>"));
+        assert!(gen.contains("Json<CreatePetAnonArg1>, "));
+        assert!(!gen.contains("CreatePetAnonArg2"));
+        assert!(gen.contains("body: Json<UpdatePetAnonArg1>"));
+        let types = &files["types.rs"];
+        assert_eq!(types.matches("pub struct ").count(), 1);
+        assert!(types.contains("pub type UpdatePetAnonArg1 = CreatePetAnonArg1;"));
+    }
+
+    #[test]
+    fn test_inline_named_schema() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: inline
+  version: 1
+paths:
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              required:
+                - name
+              properties:
+                name:
+                  type: string
+      responses:
+        200:
+          description: Created
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Pet'
+components:
+  schemas:
+    Pet:
+      required:
+        - name
+      properties:
+        name:
+          type: string
+"#;
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        assert!(files["gen.rs"].contains("body: Json<Pet>"));
+        assert!(files["stub.rs"].contains("pub fn create_pet(body: Pet,) -> Result<Pet, ()>"));
+        let types = &files["types.rs"];
+        assert_eq!(types.matches("pub struct ").count(), 1);
+        assert!(!types.contains("AnonArg"));
+    }
+
+    #[test]
+    fn test_security_guards() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: secured
+  version: 1
+security:
+  - api_key: []
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Some pets
+    post:
+      operationId: createPet
+      responses:
+        201:
+          description: Created
+  /search:
+    get:
+      operationId: search
+      parameters:
+        - name: api_key
+          in: query
+          schema:
+            type: string
+      responses:
+        200:
+          description: Some pets
+  /status:
+    get:
+      operationId: getStatus
+      security: []
+      responses:
+        200:
+          description: Up
+components:
+  securitySchemes:
+    api_key:
+      type: apiKey
+      in: header
+      name: X-API-Key
+"#;
+        let files = generate_from_str(yaml, Format::Yaml).unwrap();
+        let gen = &files["gen.rs"];
+        assert_eq!(gen.matches("pub struct ApiKeyGuard(pub String);").count(), 1);
+        assert!(gen.contains("match request.headers().get_one(\"X-API-Key\").map(|v| v.to_string()) {"));
+        assert!(gen.contains("rocket::Outcome::Failure((rocket::http::Status::Unauthorized, ()))"));
+        assert!(gen.contains("fn _list_pets(api_key: ApiKeyGuard,)"));
+        assert!(gen.contains("list_pets(api_key.0,)"));
+        assert!(gen.contains("fn _get_status()"));
+        assert!(files["stub.rs"].contains("pub fn create_pet(api_key: String,)"));
+        // A parameter of the same name keeps it
+        assert!(files["stub.rs"].contains("pub fn search(api_key: Option<String>,api_key_credential: String,)"));
+        assert!(gen.contains("api_key_credential: ApiKeyGuard,"));
+
+        let config = Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let spec = parse_spec(yaml, Format::Yaml).unwrap();
+        let gen = &generate_sources_to_map(&spec, &config).unwrap()["gen.rs"];
+        assert!(gen.contains("impl actix_web::FromRequest for ApiKeyGuard {"));
+        assert!(gen.contains("actix_web::error::ErrorUnauthorized"));
+    }
+
+    #[test]
+    fn test_response_headers() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: headers
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Some pets
+          headers:
+            X-Rate-Limit-Remaining:
+              schema:
+                type: integer
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: string
+    post:
+      operationId: createPet
+      responses:
+        201:
+          description: Created
+          headers:
+            Location:
+              schema:
+                type: string
+"#;
+        let spec = parse_spec(yaml, Format::Yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(!files["gen.rs"].contains("ListPetsResponse"));
+
+        let config = Config {
+            response_headers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains(
+            "pub struct ListPetsResponse {\n    \
+             pub body: Vec<String>,\n    \
+             pub x_rate_limit_remaining: Option<String>,\n}"
+        ));
+        assert!(gen.contains("response.set_raw_header(\"X-Rate-Limit-Remaining\", value);"));
+        assert!(gen.contains("fn _list_pets() -> Result<ListPetsResponse, ()> {\n    list_pets()\n}"));
+        assert!(gen.contains("pub struct CreatePetResponse {\n    pub location: Option<String>,\n}"));
+        assert!(gen.contains("Status::from_code(201).unwrap();"));
+        assert!(gen.contains("response.set_raw_header(\"Location\", value);"));
+        let stub = &files["stub.rs"];
+        assert!(stub.contains("use gen::ListPetsResponse;"));
+        assert!(stub.contains("pub fn create_pet() -> Result<CreatePetResponse, ()>"));
+    }
+
+    #[test]
+    fn test_deprecated() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: deprecated
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      deprecated: true
+      responses:
+        200:
+          description: Some pets
+    post:
+      operationId: createPet
+      responses:
+        201:
+          description: Created
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Pet:
+      properties:
+        tag:
+          type: string
+          deprecated: true
+"#;
+        let spec = parse_spec(yaml, Format::Yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["stub.rs"].contains(
+            "#[deprecated(note = \"deprecated in the API spec\")]\npub fn list_pets("
+        ));
+        assert!(!files["stub.rs"].contains(")]\npub fn create_pet("));
+        assert!(files["gen.rs"].contains("#[allow(deprecated)]\nfn _list_pets("));
+        assert!(files["types.rs"].contains(
+            "    #[deprecated(note = \"deprecated in the API spec\")]\n    pub tag: Option<String>,"
+        ));
+
+        let config = Config {
+            skip_deprecated: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(!files["stub.rs"].contains("list_pets"));
+        assert!(files["stub.rs"].contains("pub fn create_pet("));
+    }
+
+    #[test]
+    fn test_body_negotiation() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: forms
+  version: 1
+paths:
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+          application/x-www-form-urlencoded:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        201:
+          description: Created
+components:
+  schemas:
+    Pet:
+      required:
+        - name
+      properties:
+        name:
+          type: string
+"#;
+        let spec = parse_spec(yaml, Format::Yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("pub enum CreatePetContent {\n    Json(Pet),\n    Form(Pet),\n}"));
+        assert!(gen.contains("impl rocket::data::FromData for CreatePetContent {"));
+        assert!(gen.contains(
+            "if top == \"application\" && sub == \"x-www-form-urlencoded\" => {\n                \
+             ::serde_urlencoded::from_str(&body).map(CreatePetContent::Form).map_err(|_| ())"
+        ));
+        assert!(gen.contains("fn _create_pet(body: CreatePetContent,)"));
+        assert!(gen.contains("create_pet(body.into_inner(),)"));
+        assert!(files["stub.rs"].contains("pub fn create_pet(body: Pet,)"));
+        assert!(files["main.rs"].contains("extern crate serde_json;\nextern crate serde_urlencoded;\n"));
+        assert!(!generate_sources_to_map(&spec, &Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        }).unwrap()["main.rs"].contains("serde_urlencoded"));
+
+        let config = Config {
+            framework: Framework::Actix,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(!gen.contains("CreatePetContent"));
+        assert!(gen.contains("body: actix_web::Either<web::Json<Pet>, web::Form<Pet>>"));
+        assert!(gen.contains("actix_web::Either::Right(b) => b.into_inner()"));
+
+        // Rocket 0.5 reads the body up to the limit for its media type
+        let config = Config {
+            async_handlers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("impl<'r> rocket::data::FromData<'r> for CreatePetContent {"));
+        assert!(gen.contains("request.limits().get(\"form\").unwrap_or(rocket::data::Limits::FORM)"));
+        assert!(gen.contains("request.limits().get(\"json\").unwrap_or(rocket::data::Limits::JSON)"));
+        assert!(gen.contains("data.open(limit).into_string().await"));
+
+        // Either way a validated body is decoded into its newtype
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: forms
+  version: 1
+paths:
+  /tags:
+    put:
+      operationId: putTags
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: array
+              maxItems: 10
+              items:
+                type: string
+          application/x-www-form-urlencoded:
+            schema:
+              type: array
+              maxItems: 10
+              items:
+                type: string
+      responses:
+        204:
+          description: Replaced
+"#;
+        let spec = parse_spec(yaml, Format::Yaml).unwrap();
+        let config = Config {
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("pub enum PutTagsContent {\n    Json(PutTagsBody),\n    Form(PutTagsBody),\n}"));
+        assert!(gen.contains("fn _put_tags(body: Option<PutTagsContent>,)"));
+        assert!(gen.contains("put_tags(body.map(|body| body.into_inner()),)"));
+        let config = Config {
+            framework: Framework::Actix,
+            ..config
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains("actix_web::Either<web::Json<PutTagsBody>, web::Form<PutTagsBody>>"));
+    }
+
+    #[test]
+    fn test_response_enums() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: responses
+  version: 1
+paths:
+  /things:
+    post:
+      operationId: createThing
+      responses:
+        200:
+          description: Already exists
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Thing'
+        201:
+          description: Created
+          content:
+            application/json:
+              schema:
+                type: integer
+        204:
+          description: Nothing to report
+components:
+  schemas:
+    Thing:
+      properties:
+        id:
+          type: integer
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        take_warnings();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["gen.rs"].contains("Result<Json<Thing>, ()>"));
+        assert!(take_warnings().iter().any(|w| w.contains("'create_thing'")));
+
+        let config = Config {
+            response_enums: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("pub enum CreateThingResponse {\n    Ok(Thing),\n    Created(i64),\n    NoContent,\n}"));
+        assert!(gen.contains("CreateThingResponse::NoContent => {"));
+        assert!(gen.contains("from_code(201).unwrap(), Json(body))"));
+        assert!(gen.contains("-> Result<CreateThingResponse, ()> {"));
+        assert!(gen.contains("create_thing()\n}"));
+        let stub = &files["stub.rs"];
+        assert!(stub.contains("use gen::CreateThingResponse;\n"));
+        assert!(stub.contains("-> Result<CreateThingResponse, ()>"));
+    }
+
+    #[test]
+    fn test_error_enums() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: errors
+  version: 1
+paths:
+  /pets/{petId}:
+    get:
+      operationId: showPet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: The pet
+          content:
+            application/json:
+              schema:
+                type: string
+        400:
+          description: Invalid id
+        404:
+          description: No such pet
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Error'
+components:
+  schemas:
+    Error:
+      properties:
+        message:
+          type: string
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["gen.rs"].contains("rocket::response::status::Custom<Json<Error>>"));
+
+        let config = Config {
+            response_enums: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("pub enum ShowPetError {\n    BadRequest,\n    NotFound(Error),\n}"));
+        assert!(gen.contains("impl<'r> rocket::response::Responder<'r> for ShowPetError {"));
+        assert!(gen.contains(
+            "ShowPetError::NotFound(body) => {\n                \
+             rocket::response::status::Custom(rocket::http::Status::from_code(404).unwrap(), Json(body))"
+        ));
+        assert!(gen.contains("-> Result<Json<String>, ShowPetError> {"));
+        assert!(!gen.contains("map_err"));
+        let stub = &files["stub.rs"];
+        assert!(stub.contains("use gen::ShowPetError;\n"));
+        assert!(stub.contains("-> Result<String, ShowPetError>"));
+    }
+
+    #[test]
+    fn test_parameter_validation() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: validation
+  version: 1
+paths:
+  /pets/{petName}:
+    get:
+      operationId: showPet
+      parameters:
+        - name: petName
+          in: path
+          required: true
+          schema:
+            type: string
+            pattern: "^[a-z]+$"
+        - name: limit
+          in: query
+          schema:
+            type: integer
+            minimum: 1
+            maximum: 100
+      responses:
+        200:
+          description: A pet
+"#;
+        let config = Config {
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let types = &files["types.rs"];
+        assert!(types.contains("pub struct ShowPetPetName(String);"));
+        assert!(types.contains("::regex::Regex::new(r\"^[a-z]+$\").unwrap();"));
+        assert!(types.contains("impl<'a> ::rocket::request::FromParam<'a> for ShowPetPetName {"));
+        assert!(types.contains("pub struct ShowPetLimit(i64);"));
+        assert!(types.contains("if (value as f64) > 100.0 {"));
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("fn _show_pet(pet_name: ShowPetPetName,query: ShowPetQuery,)"));
+        assert!(gen.contains("pub limit: Option<ShowPetLimit>,"));
+        assert!(files["stub.rs"].contains("pub fn show_pet(pet_name: ShowPetPetName,limit: Option<ShowPetLimit>,)"));
+
+        // Without the option the parameters are plain
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["gen.rs"].contains("fn _show_pet(pet_name: String,query: ShowPetQuery,)"));
+
+        // Rocket 0.5 parses them with its own traits
+        let config = Config {
+            validated_newtypes: true,
+            async_handlers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let types = &files["types.rs"];
+        assert!(types.contains("impl<'a> ::rocket::request::FromParam<'a> for ShowPetPetName {"));
+        assert!(types.contains("impl<'v> ::rocket::form::FromFormField<'v> for ShowPetLimit {"));
+        assert!(files["gen.rs"].contains("pub limit: Option<ShowPetLimit>,"));
+    }
+
+    #[test]
+    fn test_array_validation() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: validation
+  version: 1
+paths:
+  /pets:
+    post:
+      operationId: createPets
+      parameters:
+        - name: tags
+          in: query
+          required: true
+          schema:
+            type: array
+            minItems: 1
+            uniqueItems: true
+            items:
+              type: string
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              type: array
+              maxItems: 10
+              items:
+                $ref: '#/components/schemas/Pet'
+      responses:
+        200:
+          description: Created
+components:
+  schemas:
+    Pet:
+      properties:
+        name:
+          type: string
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let types = &files["types.rs"];
+        assert!(types.contains("pub struct CreatePetsTags(Vec<String>);"));
+        assert!(types.contains("if value.len() < 1 {"));
+        assert!(types.contains("appears more than once"));
+        assert!(types.contains("#[derive(Debug, Clone, Serialize)]\npub struct CreatePetsBody(Vec<Pet>);"));
+        assert!(types.contains("if value.len() > 10 {"));
+        // Arrays are never parsed from a single string
+        assert!(!types.contains("FromStr for CreatePetsTags"));
+
+        let gen = &files["gen.rs"];
+        assert!(gen.contains("pub tags: CreatePetsTags,"));
+        assert!(gen.contains("tags: CreatePetsTags::new(tags).map_err(|_| ())?,"));
+        assert!(gen.contains("body: Json<CreatePetsBody>,"));
+        assert!(files["stub.rs"].contains("pub fn create_pets(tags: CreatePetsTags,body: CreatePetsBody,)"));
+
+        // An optional body is validated when present
+        let optional = yaml.replace("requestBody:\n        required: true", "requestBody:");
+        let spec = OpenApi::from_string(&optional).unwrap();
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        assert!(files["gen.rs"].contains("body: Option<Json<CreatePetsBody>>,"));
+        assert!(files["stub.rs"].contains("body: Option<CreatePetsBody>,)"));
+        let spec = OpenApi::from_string(yaml).unwrap();
+
+        // Rocket 0.5 collects the array as it would a `Vec`, then checks it
+        let config = Config {
+            validated_newtypes: true,
+            async_handlers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let types = &files["types.rs"];
+        assert!(types.contains("impl<'v> ::rocket::form::FromForm<'v> for CreatePetsTags {"));
+        assert!(types.contains("let value = <Vec<String> as ::rocket::form::FromForm<'v>>::finalize(ctxt)?;"));
+        // Items which are not form fields cannot be collected
+        assert!(!types.contains("FromForm<'v> for CreatePetsBody"));
+        assert!(files["gen.rs"].contains("pub tags: CreatePetsTags,"));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: broken
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Some pets
+  /pets/{petId}:
+    get:
+      operationId: showPetById
+      responses:
+        200:
+          description: Missing its path parameter
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        take_warnings();
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        assert!(files["gen.rs"].contains("fn _list_pets("));
+        assert!(!files["gen.rs"].contains("fn _show_pet_by_id("));
+        // The entrypoints are collected once, so the operation is skipped once
+        let skipped = take_warnings()
+            .into_iter()
+            .filter(|w| w.starts_with("Skipping operation"))
+            .count();
+        assert_eq!(skipped, 1);
+
+        let config = Config {
+            strict: true,
+            ..Config::default()
+        };
+        let err = generate_sources_to_map(&spec, &config).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to generate 1 operation(s)"));
+    }
+
+    #[test]
+    fn test_api_module() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config {
+            api_module: Some("api".into()),
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let names: Vec<&str> = files.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["api/gen.rs", "api/mod.rs", "api/stub.rs", "api/types.rs", "main.rs"]
+        );
+        assert!(files["api/mod.rs"].contains("pub mod gen;\npub mod stub;\npub mod types;"));
+        assert!(files["api/gen.rs"].contains("use api::stub::*;\nuse api::types::*;"));
+        assert!(files["api/stub.rs"].contains("use api::types::*;"));
+        assert!(files["main.rs"].contains("mod api;\n"));
+        assert!(!files["main.rs"].contains("mod gen;"));
+        assert!(files["main.rs"].contains("let rocket = api::gen::mount_api(rocket);"));
+    }
+
+    #[test]
+    fn test_generator() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let config = Config::default();
+        let generator = Generator::new(&spec, &config).unwrap();
+        let gen = generator.endpoints().unwrap();
+        assert!(gen.contains("fn _list_pets("));
+        assert!(gen.contains("pub fn mount_api"));
+        assert!(generator.stubs().unwrap().contains("pub fn list_pets("));
+        assert!(generator.types().unwrap().contains("pub struct Pet"));
+        assert!(generator.main().unwrap().contains("fn main()"));
+
+        // The free functions generate the same sources
+        let files = generator.all_to_map().unwrap();
+        assert_eq!(files["gen.rs"], gen);
+        assert_eq!(files, generate_sources_to_map(&spec, &config).unwrap());
+
+        // Generators with different configs on one thread keep to their own
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: counts
+  version: 1
+paths:
+  /counts:
+    get:
+      operationId: getCounts
+      responses:
+        200:
+          description: The counts
+          content:
+            application/json:
+              schema:
+                additionalProperties:
+                  type: integer
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let hash_config = Config {
+            map_type: process::MapType::HashMap,
+            ..Config::default()
+        };
+        let btree = Generator::new(&spec, &config).unwrap();
+        let hash = Generator::new(&spec, &hash_config).unwrap();
+        assert!(btree.stubs().unwrap().contains("Result<::std::collections::BTreeMap<String, i64>, ()>"));
+        assert!(hash.stubs().unwrap().contains("Result<::std::collections::HashMap<String, i64>, ()>"));
+        assert!(btree.stubs().unwrap().contains("BTreeMap"));
+    }
+
+    #[test]
+    fn test_tag_modules() {
+        let yaml = include_str!("../example_apis/petstore.yaml")
+            .replace("createPets\n      tags:\n        - pets", "createPets");
+        let spec = OpenApi::from_string(&yaml).unwrap();
+        let config = Config {
+            tag_modules: true,
+            response_headers: true,
+            ..Config::default()
+        };
+        let files = generate_sources_to_map(&spec, &config).unwrap();
+        let names: Vec<&str> = files.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["gen/mod.rs", "gen/pets.rs", "main.rs", "stub/mod.rs", "stub/pets.rs", "types.rs"]
+        );
+        // The untagged operation stays in the parent modules
+        assert!(files["stub/mod.rs"].contains("pub mod pets;\npub use self::pets::*;"));
+        assert!(files["stub/mod.rs"].contains("pub fn create_pets("));
+        assert!(files["stub/pets.rs"].contains("pub fn list_pets("));
+        assert!(files["stub/pets.rs"].contains("use gen::pets::ListPetsResponse;"));
+
+        let gen = &files["gen/mod.rs"];
+        assert!(gen.starts_with("// *** This file was generated by thruster ***\n\npub mod pets;\n"));
+        assert!(gen.contains("pub const SPEC"));
+        assert!(gen.contains("pets::_list_pets,"));
+        assert!(gen.contains("pets::_show_pet_by_id,"));
+        assert!(gen.contains("_create_pets,"));
+        let pets = &files["gen/pets.rs"];
+        assert!(pets.contains("use super::*;"));
+        assert!(pets.contains("pub fn _list_pets("));
+        assert!(!pets.contains("_create_pets"));
+        assert!(!pets.contains("pub const SPEC"));
+        assert!(!pets.contains("pub fn mount_api"));
+
+        assert!(generate_sources_to_string(&spec, &config).is_err());
+
+        // Tags are kept from shadowing keywords and the names the modules import
+        assert_eq!(module_name("Pet Store", &config), "pet_store");
+        for tag in &["type", "io", "rocket", "serde", "gen", "stub"] {
+            assert_eq!(module_name(tag, &config), format!("tag_{}", tag));
+        }
+    }
+
+    // Needs a nightly toolchain, cargo-edit and network access
+    #[test]
+    #[ignore]
+    fn test_api_module_compiles() {
+        let tmp_dir = TempDir::new("thruster-api-module").unwrap();
+        let config = Config {
+            api_module: Some("api".into()),
+            ..Config::default()
+        };
+        // bootstrap runs `cargo check` on the generated crate
+        bootstrap(
+            Path::new("example_apis/petstore.yaml"),
+            tmp_dir.path().join("nested").as_path(),
+            &config,
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_generate_types_dir() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let tmp_dir = TempDir::new("thruster-types").unwrap();
+        let types_dir = tmp_dir.path().join("types");
+        generate_types_dir(&types_dir, &spec, &Config::default()).unwrap();
+
+        for file in &["mod.rs", "pet.rs", "pets.rs", "error.rs"] {
+            assert!(types_dir.join(file).exists(), "missing {}", file);
+        }
+        let mut mod_rs = String::new();
+        File::open(types_dir.join("mod.rs"))
+            .unwrap()
+            .read_to_string(&mut mod_rs)
+            .unwrap();
+        assert!(mod_rs.contains("mod pet;\npub use self::pet::*;"));
+        assert!(mod_rs.contains("mod pets;\npub use self::pets::*;"));
+    }
 }
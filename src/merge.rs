@@ -0,0 +1,291 @@
+// Merging of several specs into one, for APIs split across files by domain.
+// Paths and components are combined, while the servers and info of the first
+// spec are kept for the whole.
+
+use std::collections::BTreeMap;
+
+use openapi3::OpenApi;
+use serde_json::{self, Map};
+use serde_json::Value as JsonValue;
+
+use swagger2::METHODS;
+use Result;
+
+/// Combine the paths and components of several specs into a single spec.
+/// An operation defined in more than one spec, either at the same route or
+/// under the same operationId, is an error, as is a component name used for
+/// different definitions. Identical components are shared.
+pub fn merge_specs(specs: &[OpenApi]) -> Result<OpenApi> {
+    let mut specs = specs.iter().map(serde_json::to_value);
+    let mut merged = match specs.next() {
+        Some(spec) => spec?,
+        None => bail!("No specs to merge"),
+    };
+    let mut operation_ids = BTreeMap::new();
+    record_operation_ids(&merged, 0, &mut operation_ids)?;
+    for (ix, spec) in specs.enumerate() {
+        let spec = spec?;
+        // The first spec was taken above
+        let ix = ix + 1;
+        record_operation_ids(&spec, ix, &mut operation_ids)?;
+        merge_paths(&mut merged, &spec, ix)?;
+        merge_components(&mut merged, &spec, ix)?;
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+fn record_operation_ids(
+    spec: &JsonValue,
+    ix: usize,
+    seen: &mut BTreeMap<String, usize>,
+) -> Result<()> {
+    let paths = match spec["paths"].as_object() {
+        Some(paths) => paths,
+        None => return Ok(()),
+    };
+    for path in paths.values() {
+        for method in METHODS {
+            if let Some(id) = path[*method]["operationId"].as_str() {
+                // Duplicates within a spec are reported along with its other errors
+                match seen.insert(id.into(), ix) {
+                    Some(other) if other != ix => bail!(
+                        "operationId '{}' is used in both spec {} and spec {}",
+                        id,
+                        other + 1,
+                        ix + 1
+                    ),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_paths(merged: &mut JsonValue, spec: &JsonValue, ix: usize) -> Result<()> {
+    let paths = match spec["paths"].as_object() {
+        Some(paths) => paths,
+        None => return Ok(()),
+    };
+    for (route, path) in paths {
+        let existing = &mut merged["paths"][route.as_str()];
+        if existing.is_null() {
+            *existing = path.clone();
+            continue;
+        }
+        // Operations on the same route may still come from different specs.
+        // Each spec's path-level parameters and servers apply only to its own
+        // operations, so are moved onto them
+        push_down_path_items(existing);
+        for method in METHODS {
+            if path[*method].is_null() {
+                continue;
+            }
+            if !existing[*method].is_null() {
+                bail!(
+                    "{} {} is defined in spec {} and an earlier spec",
+                    method.to_uppercase(),
+                    route,
+                    ix + 1
+                );
+            }
+            existing[*method] = with_path_items(path, method);
+        }
+    }
+    Ok(())
+}
+
+const PATH_ITEMS: &[&str] = &["parameters", "servers"];
+
+fn push_down_path_items(path: &mut JsonValue) {
+    for method in METHODS {
+        if !path[*method].is_null() {
+            path[*method] = with_path_items(path, method);
+        }
+    }
+    if let Some(path) = path.as_object_mut() {
+        for item in PATH_ITEMS {
+            path.remove(*item);
+        }
+    }
+}
+
+// An operation of the path, with the path's parameters and servers added.
+// Its own parameters override the path's of the same name and location
+fn with_path_items(path: &JsonValue, method: &str) -> JsonValue {
+    let mut operation = path[method].clone();
+    if let Some(params) = path["parameters"].as_array() {
+        let own = operation["parameters"].as_array().cloned().unwrap_or_default();
+        let mut merged: Vec<JsonValue> = params
+            .iter()
+            .filter(|param| {
+                !own.iter().any(|o| o["name"] == param["name"] && o["in"] == param["in"])
+            })
+            .cloned()
+            .collect();
+        merged.extend(own);
+        operation["parameters"] = JsonValue::Array(merged);
+    }
+    if operation["servers"].is_null() && !path["servers"].is_null() {
+        operation["servers"] = path["servers"].clone();
+    }
+    operation
+}
+
+fn merge_components(merged: &mut JsonValue, spec: &JsonValue, ix: usize) -> Result<()> {
+    let components = match spec["components"].as_object() {
+        Some(components) => components,
+        None => return Ok(()),
+    };
+    for (section, items) in components {
+        let items = match items.as_object() {
+            Some(items) => items,
+            None => continue,
+        };
+        if !merged["components"][section.as_str()].is_object() {
+            merged["components"][section.as_str()] = JsonValue::Object(Map::new());
+        }
+        for (name, item) in items {
+            let existing = &mut merged["components"][section.as_str()][name.as_str()];
+            if existing.is_null() {
+                *existing = item.clone();
+            } else if *existing != *item {
+                bail!(
+                    "Component {} in spec {} differs from the one of the same name \
+                     in an earlier spec",
+                    name,
+                    ix + 1
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {generate_sources_to_map, parse_spec, process, Config, Format};
+
+    static PETS: &str = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: pets
+servers:
+  - url: http://localhost:8000/v1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: The pets
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: '#/components/schemas/Pet'
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        schema:
+          type: string
+    get:
+      operationId: showPet
+      responses:
+        200:
+          description: The pet
+components:
+  schemas:
+    Pet:
+      properties:
+        name:
+          type: string
+    Error:
+      properties:
+        message:
+          type: string
+"#;
+
+    static OWNERS: &str = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: owners
+paths:
+  /owners:
+    get:
+      operationId: listOwners
+      responses:
+        200:
+          description: The owners
+  /pets:
+    post:
+      operationId: createPet
+      responses:
+        201:
+          description: Created
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        schema:
+          type: string
+    delete:
+      operationId: deletePet
+      responses:
+        204:
+          description: Deleted
+components:
+  schemas:
+    Owner:
+      properties:
+        name:
+          type: string
+    Error:
+      properties:
+        message:
+          type: string
+"#;
+
+    #[test]
+    fn test_merge_specs() {
+        let parse = |yaml: &str| parse_spec(yaml, Format::Yaml).unwrap();
+        let spec = merge_specs(&[parse(PETS), parse(OWNERS)]).unwrap();
+        assert_eq!(spec.servers.as_ref().unwrap()[0].url, "http://localhost:8000/v1");
+        assert_eq!(process::extract_entrypoints(&spec).len(), 5);
+        let schemas = spec.components.as_ref().unwrap().schemas.as_ref().unwrap();
+        assert_eq!(schemas.len(), 3);
+
+        let files = generate_sources_to_map(&spec, &Config::default()).unwrap();
+        let gen = &files["gen.rs"];
+        assert_eq!(gen.matches("pub fn mount_api").count(), 1);
+        assert!(gen.contains("_list_pets"));
+        assert!(gen.contains("_list_owners"));
+        assert!(gen.contains("_create_pet"));
+        // Both specs' operations on a route keep their path-level parameters
+        assert!(gen.contains("fn _show_pet(pet_id: String,)"));
+        assert!(gen.contains("fn _delete_pet(pet_id: String,)"));
+
+        let clash = OWNERS.replace("listOwners", "listPets");
+        let err = merge_specs(&[parse(PETS), parse(&clash)]).unwrap_err();
+        assert!(err.to_string()
+            .contains("operationId 'listPets' is used in both spec 1 and spec 2"));
+
+        let clash = OWNERS.replace("post:", "get:");
+        let err = merge_specs(&[parse(PETS), parse(&clash)]).unwrap_err();
+        assert!(err.to_string().contains("GET /pets is defined in spec 2"));
+
+        let clash = OWNERS.replace(
+            "message:\n          type: string",
+            "code:\n          type: integer",
+        );
+        let err = merge_specs(&[parse(PETS), parse(&clash)]).unwrap_err();
+        assert!(err.to_string().contains("Component Error in spec 2 differs"));
+    }
+}
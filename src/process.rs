@@ -1,26 +1,78 @@
 use openapi3::OpenApi;
 use openapi3::objects::*;
-use errors::ErrorKind;
+use openapi3::objects::Location as ParameterLocation;
+use errors::{Error, ErrorKind};
 use regex::Regex;
+use rocket::http::Status;
+use serde::{Serialize, Serializer};
 use serde_json::Value as JsonValue;
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::ops::Deref;
 
-use Result;
+use typegen::{self, KEYWORDS};
+use {warn, Result};
 use inflector::Inflector;
 
+/// String formats to map to types from other crates rather than to `String`.
+/// Each adds a dependency to the generated crate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Formats {
+    /// `date-time` as `chrono::DateTime<Utc>` and `date` as `chrono::NaiveDate`.
+    /// These are only parsed from JSON, so cannot be Rocket path or query parameters
+    pub chrono: bool,
+    /// `uuid` as `uuid::Uuid`
+    pub uuid: bool,
+}
+
+/// The map type of objects with `additionalProperties`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapType {
+    /// `std::collections::BTreeMap`, which iterates in key order
+    BTreeMap,
+    /// `std::collections::HashMap`
+    HashMap,
+    /// `indexmap::IndexMap`, which keeps the order of the JSON object.
+    /// Adds a dependency to the generated crate
+    IndexMap,
+}
+
+impl Default for MapType {
+    fn default() -> Self {
+        MapType::BTreeMap
+    }
+}
+
+impl MapType {
+    fn path(self) -> &'static str {
+        match self {
+            MapType::BTreeMap => "::std::collections::BTreeMap",
+            MapType::HashMap => "::std::collections::HashMap",
+            MapType::IndexMap => "::indexmap::IndexMap",
+        }
+    }
+}
+
+/// The options which change how schemas map to Rust types
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TypeOptions {
+    pub formats: Formats,
+    pub map_type: MapType,
+}
+
 #[derive(Clone, Debug)]
-pub struct Args(Vec<Arg>);
+pub struct Args<'a>(Vec<Arg<'a>>);
 
-impl Deref for Args {
-    type Target = Vec<Arg>;
-    fn deref(&self) -> &Vec<Arg> {
+impl<'a> Deref for Args<'a> {
+    type Target = Vec<Arg<'a>>;
+    fn deref(&self) -> &Vec<Arg<'a>> {
         &self.0
     }
 }
 
-impl From<Vec<Arg>> for Args {
-    fn from(v: Vec<Arg>) -> Args {
+impl<'a> From<Vec<Arg<'a>>> for Args<'a> {
+    fn from(v: Vec<Arg<'a>>) -> Args<'a> {
         Args(v)
     }
 }
@@ -29,19 +81,41 @@ impl From<Vec<Arg>> for Args {
 pub struct Entrypoint<'a> {
     route: Route<'a>,
     pub method: Method,
-    pub args: Args,
-    pub responses: Vec<Response>,
+    pub args: Args<'a>,
+    pub responses: Vec<Response<'a>>,
     pub operation_id: OperationId,
+    /// The operationId as written in the spec, which `operation_id` is made from
+    pub spec_operation_id: Option<String>,
     pub summary: Option<String>,
     pub description: Option<String>,
+    /// The path the route is mounted under, taken from the applicable `servers`
+    pub base_path: String,
+    /// The value the stub returns, for built-in routes which need no implementing
+    pub stub_result: Option<String>,
+    /// Whether the route returns the embedded spec itself, with no stub
+    pub serves_spec: bool,
+    // The spec's named schemas, which identical inline schemas are rendered as
+    schemas: Option<&'a BTreeMap<String, Schema>>,
+    // The options the types were built with, which validation must agree with
+    type_options: TypeOptions,
+    /// The credentials the route requires, each checked by a request guard
+    pub security: Vec<Security>,
+    /// The operation's vendor extensions (e.g. `x-rate-limit`), which are
+    /// passed through for custom templates to act on
+    pub extensions: BTreeMap<String, JsonValue>,
+    /// Whether the spec marks the operation as deprecated
+    pub deprecated: bool,
+    /// The operation's tags, the first of which names its module when stubs
+    /// and routes are split by tag
+    pub tags: Vec<String>,
 }
 
 impl<'a> Entrypoint<'a> {
     fn new(
         route: Route<'a>,
         method: Method,
-        args: Args,
-        responses: Vec<Response>,
+        args: Args<'a>,
+        responses: Vec<Response<'a>>,
         operation_id: OperationId,
         summary: Option<String>,
         description: Option<String>,
@@ -53,251 +127,2059 @@ impl<'a> Entrypoint<'a> {
             args,
             responses,
             operation_id,
+            spec_operation_id: None,
             summary,
             description,
+            base_path: "/".into(),
+            stub_result: None,
+            serves_spec: false,
+            schemas: None,
+            type_options: TypeOptions::default(),
+            security: Vec::new(),
+            extensions: BTreeMap::new(),
+            deprecated: false,
+            tags: Vec::new(),
         })
     }
 
     fn build(
         route: &'a str,
         method: Method,
-        operation: &Operation,
-        components: &Components,
+        operation: &'a Operation,
+        components: Option<&'a Components>,
     ) -> Result<Entrypoint<'a>> {
-        let args = build_args(operation, components)?;
-        let responses = build_responses(operation, components);
+        let mut problems = Vec::new();
+        let options = TypeOptions::default();
+        let entrypoint =
+            Entrypoint::build_with(route, method, operation, components, options, &mut problems);
+        for problem in problems {
+            eprintln!("{}", problem);
+        }
+        entrypoint
+    }
+
+    // Like `build`, but responses which fail to build are dropped and
+    // recorded in `problems` rather than printed
+    fn build_with(
+        route: &'a str,
+        method: Method,
+        operation: &'a Operation,
+        components: Option<&'a Components>,
+        options: TypeOptions,
+        problems: &mut Vec<Error>,
+    ) -> Result<Entrypoint<'a>> {
+        let args = build_args(operation, components, options)?;
+        let responses = build_responses(operation, components, options);
         let responses = responses
             .into_iter()
             .filter_map(|res| match res {
                 Ok(resp) => Some(resp),
                 Err(e) => {
-                    // TODO better error handling
-                    eprintln!("{}", e);
+                    problems.push(e);
                     None
                 }
             })
             .collect();
-        let operation_id = operation
-            .operation_id
-            .as_ref()
-            .ok_or(ErrorKind::from("No operation_id found"))?;
-        Entrypoint::new(
+        let operation_id = match operation.operation_id {
+            Some(ref id) => OperationId::new(id)?,
+            None => OperationId::from_route(method, route),
+        };
+        let mut entrypoint = Entrypoint::new(
             Route::from_str(&route)?,
             method,
             args,
             responses,
-            OperationId::new(operation_id)?,
+            operation_id,
             operation.summary.clone(),
             operation.description.clone(),
-        )
+        )?;
+        entrypoint.schemas = components.and_then(|c| c.schemas.as_ref());
+        entrypoint.type_options = options;
+        entrypoint.spec_operation_id = operation.operation_id.clone();
+        entrypoint.extensions = operation.extensions.clone();
+        entrypoint.deprecated = operation.deprecated == Some(true);
+        entrypoint.tags = operation.tags.clone().unwrap_or_default();
+        Ok(entrypoint)
+    }
+
+    /// The route as written in the spec, e.g. "/pets/{petId}"
+    pub fn path(&self) -> String {
+        self.route.spec_path()
+    }
+
+    /// The route in Rocket's syntax, e.g. "/pets/<pet_id>"
+    pub fn route(&self) -> String {
+        self.route.render()
+    }
+
+    /// The names of the arguments, along with the types the stub receives
+    /// them as. Anonymous objects are named as in the generated code
+    pub fn arg_types(&self) -> Vec<(&str, String)> {
+        let mut anon = self.anon_names();
+        self.args
+            .iter()
+            .map(|arg| (arg.name(), arg.type_.render(&mut anon)))
+            .collect()
     }
 
     pub fn build_template_args(&self) -> JsonValue {
-        let (args_json, anon_count) = self.args.iter().fold(
-            (Vec::new(), 1),
-            |(mut out, anon_count), arg| {
-                let rendered_type = arg.type_.render(anon_count, &self.operation_id);
-                let json = json!({
+        // The stub receives plain types; the route is responsible for
+        // unwrapping request guards such as `Json` before calling it
+        let mut anon = AnonNames::new(&self.operation_id, self.schemas);
+        let mut args_json = Vec::new();
+        let mut route_args_json = Vec::new();
+        let mut call_args = Vec::new();
+        let mut query_fields = Vec::new();
+        let mut header_fields = Vec::new();
+        let mut cookie_fields = Vec::new();
+        let mut data = None;
+        for arg in self.args.iter() {
+            let rendered = if arg.location == Location::Body && arg.type_.is_binary() {
+                // Binary bodies are streamed to the stub rather than parsed as JSON
+                "rocket::Data".into()
+            } else {
+                arg.type_.render(&mut anon)
+            };
+            // An optional query parameter with a default reaches the stub
+            // as a plain value
+            let default = match (arg.location, &arg.type_) {
+                (Location::Query, &NativeType::Option(ref inner)) => arg.default
+                    .and_then(|value| default_literal(inner, value))
+                    .map(|literal| (inner.render(&mut anon), literal)),
+                _ => None,
+            };
+            match (arg.location, &arg.type_) {
+                (Location::Body, type_) if type_.is_binary() => {
+                    route_args_json.push(json!({
+                        "name": arg.name,
+                        "type": rendered
+                    }));
+                    call_args.push(arg.name.clone());
+                    data = Some(&arg.name);
+                }
+                (Location::Body, &NativeType::Option(ref inner)) => {
+                    let inner = inner.render(&mut anon);
+                    route_args_json.push(json!({
+                        "name": arg.name,
+                        "type": format!("Option<Json<{}>>", inner)
+                    }));
+                    call_args.push(format!("{}.map(|b| b.into_inner())", arg.name));
+                    data = Some(&arg.name);
+                }
+                (Location::Body, _) => {
+                    route_args_json.push(json!({
+                        "name": arg.name,
+                        "type": format!("Json<{}>", rendered)
+                    }));
+                    call_args.push(format!("{}.into_inner()", arg.name));
+                    data = Some(&arg.name);
+                }
+                (Location::Query, _) => {
+                    // Query params are collected into a single FromForm struct
+                    let rename = if arg.spec_name != arg.name {
+                        Some(&arg.spec_name)
+                    } else {
+                        None
+                    };
+                    // Rocket only keeps the last value of a repeated key, so
+                    // arrays (`?tags=a&tags=b`) are accumulated by hand, as are
+                    // those split from a single delimited value (`?tags=a|b`)
+                    let (array, build) = match arg.type_ {
+                        NativeType::Array(_) => (
+                            true,
+                            format!("{{ if {0}.is_empty() {{ return Err(()); }} {0} }}", arg.name),
+                        ),
+                        NativeType::Option(ref inner) if inner.is_array() => (
+                            true,
+                            format!("if {0}.is_empty() {{ None }} else {{ Some({0}) }}", arg.name),
+                        ),
+                        NativeType::Option(_) => (false, arg.name.clone()),
+                        _ => (false, format!("{}.ok_or(())?", arg.name)),
+                    };
+                    query_fields.push(json!({
+                        "name": arg.name,
+                        "type": rendered,
+                        "key": arg.spec_name,
+                        "rename": rename,
+                        "array": array,
+                        "delimiter": arg.delimiter.map(|c| format!("{:?}", c)),
+                        "build": build,
+                        "documentation": arg.description.map(doc_comment)
+                    }));
+                    call_args.push(match default {
+                        Some((_, ref literal)) => {
+                            format!("query.{}.unwrap_or({})", arg.name, literal)
+                        }
+                        None => format!("query.{}", arg.name),
+                    });
+                }
+                (Location::Header, _) => {
+                    // Headers are extracted by a request guard, under their
+                    // original (case-insensitive) names
+                    header_fields.push(json!({
+                        "name": arg.name,
+                        "type": rendered,
+                        "build": param_build(&format!("headers.get_one({:?})", arg.spec_name), &arg.type_)
+                    }));
+                    call_args.push(format!("headers.{}", arg.name));
+                }
+                (Location::Cookie, _) => {
+                    // Likewise cookies, under their original names
+                    let get = format!("cookies.get({:?}).map(|c| c.value())", arg.spec_name);
+                    cookie_fields.push(json!({
+                        "name": arg.name,
+                        "type": rendered,
+                        "build": param_build(&get, &arg.type_)
+                    }));
+                    call_args.push(format!("cookies.{}", arg.name));
+                }
+                _ => {
+                    route_args_json.push(json!({
+                        "name": arg.name,
+                        "type": rendered
+                    }));
+                    call_args.push(arg.name.clone());
+                }
+            }
+            args_json.push(json!({
                 "name": arg.name,
-                "type": rendered_type.0
-            });
-                out.push(json);
-                (out, rendered_type.1)
-            },
-        );
+                "type": default.map(|(type_, _)| type_).unwrap_or(rendered)
+            }));
+        }
+        let query = if query_fields.is_empty() {
+            None
+        } else {
+            let query_name = format!("{}Query", self.operation_id.classcase());
+            route_args_json.push(json!({
+                "name": "query",
+                "type": query_name
+            }));
+            let repeated = query_fields.iter().any(|f| f["array"] == true);
+            if repeated {
+                // Renames are only understood by the derive
+                for field in &mut query_fields {
+                    field["rename"] = JsonValue::Null;
+                }
+            }
+            Some(json!({
+                "name": query_name,
+                "repeated": repeated,
+                "fields": query_fields
+            }))
+        };
+        let headers = if header_fields.is_empty() {
+            None
+        } else {
+            let headers_name = format!("{}Headers", self.operation_id.classcase());
+            route_args_json.push(json!({
+                "name": "headers",
+                "type": headers_name
+            }));
+            Some(json!({
+                "name": headers_name,
+                "fields": header_fields
+            }))
+        };
+        let cookies = if cookie_fields.is_empty() {
+            None
+        } else {
+            let cookies_name = format!("{}Cookies", self.operation_id.classcase());
+            route_args_json.push(json!({
+                "name": "cookies",
+                "type": cookies_name
+            }));
+            Some(json!({
+                "name": cookies_name,
+                "fields": cookie_fields
+            }))
+        };
+        // The guard refuses requests without the credential, and the stub
+        // receives it to check
+        for (security, name) in self.security.iter().zip(self.security_arg_names()) {
+            args_json.push(json!({"name": name, "type": "String"}));
+            route_args_json.push(json!({"name": name, "type": security.guard_name()}));
+            call_args.push(format!("{}.0", name));
+        }
+        let (result_type, response) = self.responder(self.result_type(&mut anon));
+        let error = self.error_type(&mut anon);
         json!({
             "method": self.method,
-            "query": self.query_param(),
+            "query": query,
+            "headers": headers,
+            "cookies": cookies,
+            "data": data,
             "route": self.route.render(),
-            // TODO verify that operation_id is valid
             "function": self.operation_id,
             "args": args_json,
-            "result_type": self.result_type(anon_count),
-            "documentation": self.docstring()
+            "route_args": route_args_json,
+            "call_args": call_args,
+            "result_type": result_type,
+            "response": response,
+            "error_type": error.as_ref().map(|e| e.0.as_str()).unwrap_or("()"),
+            "error": error.as_ref().map(|e| json!({"type": e.0, "status": e.1})),
+            "documentation": self.docstring(),
+            "example": self.stub_result,
+            "serves_spec": self.serves_spec,
+            "extensions": self.extensions,
+            "deprecated": self.deprecated
         })
     }
 
+    // How the route sends the success response, chosen by its media type, along
+    // with the type the stub returns. JSON is serialized from the result type,
+    // `text/plain` is sent as a `String`, and other media types are sent as text
+    // or raw bytes under their own content type
+    fn responder(&self, result_type: String) -> (String, JsonValue) {
+        let media_types = self.media_types();
+        let mime = match media_types.first() {
+            Some(mime) if result_type != "()" => *mime,
+            _ => return (result_type, json!({"json": true})),
+        };
+        if media_types.iter().any(|mime| is_json(mime)) {
+            (result_type, json!({"json": true}))
+        } else if mime == "text/plain" {
+            ("String".into(), json!({"plain": true}))
+        } else if mime.starts_with("text/") {
+            ("String".into(), json!({"mime": mime}))
+        } else {
+            ("Vec<u8>".into(), json!({"mime": mime}))
+        }
+    }
+
     fn docstring(&self) -> Option<String> {
-        match (self.summary.as_ref(), self.description.as_ref()) {
-            (Some(s), Some(d)) => Some(format!("/// {}\n/// {}\n", s, d)), // show both
-            (Some(s), None) => Some(format!("/// {}\n", s)),
-            (None, Some(ref d)) => Some(format!("/// {}\n", d)),
+        let doc = match (self.summary.as_ref(), self.description.as_ref()) {
+            (Some(s), Some(d)) => Some(format!("{}{}", doc_comment(s), doc_comment(d))), // show both
+            (Some(s), None) => Some(doc_comment(s)),
+            (None, Some(ref d)) => Some(doc_comment(d)),
             (None, None) => None,
+        };
+        // Parameters are documented in a list, as doc comments cannot be
+        // attached to function arguments
+        let params: Vec<String> = self.args
+            .iter()
+            .filter_map(|arg| {
+                arg.description
+                    .map(|d| doc_comment(&format!("* `{}`: {}", arg.name, d)))
+            })
+            .collect();
+        if params.is_empty() {
+            return doc;
+        }
+        let mut doc = doc.map(|doc| format!("{}///\n", doc)).unwrap_or_default();
+        doc.push_str("/// # Arguments\n///\n");
+        doc.push_str(&params.concat());
+        Some(doc)
+    }
+
+    // The content types of the first success response
+    fn media_types(&self) -> Vec<&'a str> {
+        self.responses
+            .iter()
+            .filter(|resp| resp.status_code.starts_with("2"))
+            .next()
+            .map(|resp| resp.content_types.clone())
+            .unwrap_or_default()
+    }
+
+    /// Add what the client template needs to call this entrypoint over HTTP
+    /// to its template args. Header and cookie parameters are not sent.
+    pub fn add_client_args(&self, args: &mut JsonValue) {
+        let mut client_args = Vec::new();
+        let mut query = Vec::new();
+        let mut body = None;
+        for (arg, arg_json) in self.args.iter().zip(args["args"].as_array().unwrap()) {
+            match arg.location {
+                Location::Path => {}
+                Location::Query => query.push(json!({"key": arg.spec_name, "name": arg.name})),
+                Location::Body => {
+                    body = Some(json!({
+                        "name": arg.name,
+                        "optional": arg.type_.is_option()
+                    }))
+                }
+                Location::Header | Location::Cookie => {
+                    warn(format!(
+                        "{} parameter '{}' of {} is not sent by the generated client",
+                        match arg.location {
+                            Location::Header => "header",
+                            _ => "cookie",
+                        },
+                        arg.spec_name,
+                        self.operation_id.0
+                    ));
+                    continue;
+                }
+            }
+            client_args.push(arg_json.clone());
+        }
+        args["client"] = json!({
+            "method": format!("{:?}", self.method),
+            "path": self.route.client_path(),
+            "path_args": self.route.route_args(),
+            "args": client_args,
+            "query": query,
+            "body": body,
+            "returns": args["result_type"] != "()",
+            "text": args["response"]["json"] != true && args["result_type"] == "String",
+            "bytes": args["response"]["json"] != true && args["result_type"] == "Vec<u8>"
+        });
+    }
+
+    /// Receive a binary request body as `route_type` rather than Rocket 0.3's
+    /// `Data`, passing the stub the `stub_type` built by the `call` expression.
+    /// Returns whether the operation takes a binary body
+    pub fn set_binary_body(
+        &self,
+        args: &mut JsonValue,
+        stub_type: &str,
+        route_type: &str,
+        call: &str,
+    ) -> bool {
+        let arg = match self.args
+            .iter()
+            .find(|arg| arg.location == Location::Body && arg.type_.is_binary())
+        {
+            Some(arg) => arg,
+            None => return false,
+        };
+        for &(list, type_) in &[("/args", stub_type), ("/route_args", route_type)] {
+            if let Some(items) = args.pointer_mut(list).and_then(|list| list.as_array_mut()) {
+                for item in items.iter_mut().filter(|item| item["name"] == arg.name.as_str()) {
+                    item["type"] = json!(type_);
+                }
+            }
+        }
+        *self.call_arg_mut(args, arg) = json!(call);
+        true
+    }
+
+    /// Accept a request body sent either as JSON or as a URL-encoded form, when
+    /// the spec allows both. A Rocket route receives an enum with a variant per
+    /// media type, decoded according to the `Content-Type` header, and an
+    /// actix-web route an `Either`. Either way the stub receives the body itself,
+    /// in its validated newtype if `validate` is set and it has one.
+    pub fn add_body_negotiation(&self, args: &mut JsonValue, actix: bool, validate: bool) {
+        let arg = match self.args.iter().find(|arg| arg.location == Location::Body) {
+            Some(arg) if arg.accepts_json_and_form() => arg,
+            _ => return,
+        };
+        let newtype = if validate {
+            self.validated_args()
+                .into_iter()
+                .find(|&(validated, _)| validated.name == arg.name)
+                .map(|(_, newtype)| newtype)
+        } else {
+            None
+        };
+        let (type_, optional) = match arg.type_ {
+            NativeType::Option(ref inner) => (inner.render(&mut self.anon_names()), true),
+            ref type_ => (type_.render(&mut self.anon_names()), false),
+        };
+        let type_ = newtype.unwrap_or(type_);
+        let (route_type, call) = if actix {
+            let either = format!("actix_web::Either<web::Json<{0}>, web::Form<{0}>>", type_);
+            let unwrap = "match body { actix_web::Either::Left(b) => b.into_inner(), \
+                          actix_web::Either::Right(b) => b.into_inner() }";
+            (either, unwrap.to_string())
+        } else {
+            // Not `{Op}Body`, which names the body's validated newtype
+            let name = format!("{}Content", self.operation_id.classcase());
+            let variants: Vec<JsonValue> = arg.content_types
+                .iter()
+                .filter_map(|mime| {
+                    let (variant, parse) = if is_json(mime) {
+                        ("Json", "::serde_json::from_str")
+                    } else if *mime == FORM {
+                        ("Form", "::serde_urlencoded::from_str")
+                    } else {
+                        return None;
+                    };
+                    let mut parts = mime.splitn(2, '/');
+                    Some(json!({
+                        "name": variant,
+                        "path": format!("{}::{}", name, variant),
+                        "type": type_,
+                        "top": parts.next(),
+                        "sub": parts.next(),
+                        "parse": parse
+                    }))
+                })
+                .collect();
+            args["body_enum"] = json!({
+                "name": name,
+                "type": type_,
+                "variants": variants
+            });
+            (name, "body.into_inner()".to_string())
+        };
+        // The route's body is always named `body`, as is the closure argument
+        // of an optional one
+        let (route_type, call) = if optional {
+            (format!("Option<{}>", route_type), format!("body.map(|body| {})", call))
+        } else {
+            (route_type, call)
+        };
+        for item in args["route_args"].as_array_mut().unwrap() {
+            if item["name"] == arg.name.as_str() {
+                item["type"] = json!(route_type);
+            }
+        }
+        *self.call_arg_mut(args, arg) = json!(call);
+    }
+
+    // The expression passing `arg` to the stub. The call args start with one
+    // per argument, in order, ahead of any for request guards
+    fn call_arg_mut<'j>(&self, args: &'j mut JsonValue, arg: &Arg) -> &'j mut JsonValue {
+        let ix = self.args
+            .iter()
+            .position(|other| other.name == arg.name)
+            .expect("argument of the entrypoint");
+        &mut args["call_args"][ix]
+    }
+
+    /// Whether the route decodes URL-encoded form bodies itself
+    pub fn uses_form_bodies(&self) -> bool {
+        self.args.iter().any(|arg| arg.location == Location::Body && arg.accepts_json_and_form())
+    }
+
+    /// Have Rocket 0.3 routes receive uuid path and query parameters as
+    /// rocket_contrib's `UUID`, which implements its param guards, and pass
+    /// them on to the stub as `uuid::Uuid`
+    pub fn add_uuid_guards(&self, args: &mut JsonValue) {
+        for arg in self.args.iter() {
+            let optional = match arg.type_ {
+                NativeType::Uuid => false,
+                NativeType::Option(ref inner) if **inner == NativeType::Uuid => true,
+                _ => continue,
+            };
+            let (list, call) = match arg.location {
+                Location::Path => ("/route_args", arg.name.clone()),
+                Location::Query => ("/query/fields", format!("query.{}", arg.name)),
+                _ => continue,
+            };
+            let (type_, unwrap) = if optional {
+                ("Option<rocket_contrib::UUID>", format!("{}.map(|id| id.into_inner())", call))
+            } else {
+                ("rocket_contrib::UUID", format!("{}.into_inner()", call))
+            };
+            if let Some(items) = args.pointer_mut(list).and_then(|list| list.as_array_mut()) {
+                for item in items.iter_mut().filter(|item| item["name"] == arg.name.as_str()) {
+                    item["type"] = json!(type_);
+                }
+            }
+            *self.call_arg_mut(args, arg) = json!(unwrap);
+        }
+    }
+
+    /// Whether any argument or response is sent as base64, other than within
+    /// a named or anonymous type
+    pub fn uses_base64(&self) -> bool {
+        self.args
+            .iter()
+            .map(|arg| &arg.type_)
+            .chain(self.responses.iter().filter_map(|resp| resp.return_type.as_ref()))
+            .any(|type_| type_.contains_base64())
+    }
+
+    /// Adapt the template args to actix-web's extractors. Path parameters are
+    /// extracted together as a tuple, and the query struct is deserialized by
+    /// serde, which does not support repeated keys
+    pub fn add_actix_args(&self, args: &mut JsonValue) {
+        self.set_binary_body(args, "Vec<u8>", "web::Bytes", "body.to_vec()");
+        let mut path_names = Vec::new();
+        let mut path_types = Vec::new();
+        let mut header_builds = Vec::new();
+        let mut cookie_builds = Vec::new();
+        for (arg, arg_json) in self.args.iter().zip(args["args"].as_array().unwrap()) {
+            match arg.location {
+                Location::Path => {
+                    path_names.push(arg.name.clone());
+                    path_types.push(arg_json["type"].as_str().unwrap().to_string());
+                }
+                Location::Header => {
+                    let get = format!("headers.get({:?}).and_then(|v| v.to_str().ok())", arg.spec_name);
+                    header_builds.push(param_build(&get, &arg.type_));
+                }
+                Location::Cookie => {
+                    let get = format!("request.cookie({:?}).as_ref().map(|c| c.value())", arg.spec_name);
+                    cookie_builds.push(param_build(&get, &arg.type_));
+                }
+                Location::Query => {
+                    let repeated = match arg.type_ {
+                        NativeType::Option(ref inner) => inner.is_array(),
+                        ref type_ => type_.is_array(),
+                    };
+                    if repeated {
+                        warn(format!(
+                            "Repeated query parameter '{}' of {} is not supported by actix-web",
+                            arg.spec_name, self.operation_id.0
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(fields) = args["headers"]["fields"].as_array_mut() {
+            for (field, build) in fields.iter_mut().zip(header_builds) {
+                field["build"] = json!(build);
+            }
+        }
+        if let Some(fields) = args["cookies"]["fields"].as_array_mut() {
+            for (field, build) in fields.iter_mut().zip(cookie_builds) {
+                field["build"] = json!(build);
+            }
+        }
+        if let Some(fields) = args["query"]["fields"].as_array_mut() {
+            for field in fields {
+                field["rename"] = JsonValue::Null;
+                if field["key"] != field["name"] {
+                    field["attribute"] = json!(format!("#[serde(rename = {})]", field["key"]));
+                }
+            }
+        }
+        let query_type = args["query"]["name"].clone();
+        let mut route_args: Vec<JsonValue> = args["route_args"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|arg| !path_names.iter().any(|name| arg["name"] == name.as_str()))
+            .cloned()
+            .map(|mut arg| {
+                if arg["name"] == "query" {
+                    arg["type"] = json!(format!("web::Query<{}>", query_type.as_str().unwrap()));
+                }
+                arg
+            })
+            .collect();
+        if !path_names.is_empty() {
+            route_args.insert(0, json!({
+                "name": "path",
+                "type": format!("web::Path<({},)>", path_types.join(", "))
+            }));
+            args["path"] = json!(format!("({},)", path_names.join(", ")));
+        }
+        args["route_args"] = JsonValue::Array(route_args);
+        args["route"] = json!(self.route.client_path());
+    }
+
+    // The arguments the credentials are passed as, named after their schemes
+    // unless a parameter or an argument the templates add is already
+    fn security_arg_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for security in &self.security {
+            let mut name = security.scheme.to_snake_case();
+            while self.args.iter().any(|arg| arg.name == name)
+                || ADDED_ARG_NAMES.contains(&name.as_str())
+                || names.contains(&name)
+            {
+                name = format!("{}_credential", name);
+            }
+            names.push(name);
         }
+        names
     }
 
-    fn result_type(&self, anon_count: u32) -> String {
-        // TODO just takes the first response type in the 200 range
-        match self.responses
+    /// Add a request to the template args for a test to send to this entrypoint,
+    /// filling in its required parameters with their examples, or else
+    /// placeholder values. Cookie parameters are not sent
+    pub fn add_test_args(&self, args: &mut JsonValue) {
+        let mut path = self.route.client_path();
+        let mut query = Vec::new();
+        let mut headers = Vec::new();
+        let mut body = None;
+        for arg in self.args.iter().filter(|arg| !arg.type_.is_option()) {
+            match arg.location {
+                Location::Path => {
+                    path = path.replace(&format!("{{{}}}", arg.name), &example_value(arg));
+                }
+                Location::Query => query.push(format!("{}={}", arg.spec_name, example_value(arg))),
+                Location::Header => {
+                    headers.push(json!({
+                        "name": format!("{:?}", arg.spec_name),
+                        "value": format!("{:?}", example_value(arg))
+                    }))
+                }
+                Location::Body => {
+                    let example = arg.example.cloned().unwrap_or(json!({}));
+                    body = Some(format!("{:?}", example.to_string()));
+                }
+                Location::Cookie => {}
+            }
+        }
+        if !query.is_empty() {
+            path = format!("{}?{}", path, query.join("&"));
+        }
+        for security in &self.security {
+            let header = match security.kind {
+                SecurityKind::ApiKey { ref name, location: Location::Header } => {
+                    Some((name.clone(), "example".to_string()))
+                }
+                SecurityKind::Bearer => Some(("Authorization".into(), "Bearer example".into())),
+                _ => None,
+            };
+            if let Some((name, value)) = header {
+                headers.push(json!({
+                    "name": format!("{:?}", name),
+                    "value": format!("{:?}", value)
+                }))
+            }
+        }
+        // Strings are passed to the template as Rust string literals
+        args["test"] = json!({
+            "path": format!("{:?}", path),
+            "method": format!("{:?}", self.method).to_uppercase(),
+            "headers": headers,
+            "body": body
+        });
+    }
+
+    /// Add `Accept` header negotiation to the template args when the success
+    /// response can be served as more than one content type. The stub is
+    /// passed the negotiated media type and returns the body already encoded
+    /// in it.
+    pub fn add_negotiation(&self, args: &mut JsonValue) {
+        let media_types = self.media_types();
+        if media_types.len() < 2 {
+            return;
+        }
+        let name = format!("{}MediaType", self.operation_id.classcase());
+        let variants: Vec<JsonValue> = media_types
+            .iter()
+            .map(|mime| {
+                let variant = mime.replace(|c: char| !c.is_alphanumeric(), "_")
+                    .to_class_case();
+                json!({
+                    "path": format!("{}::{}", name, variant),
+                    "name": variant,
+                    "mime": mime
+                })
+            })
+            .collect();
+        let accept = json!({"name": "accept", "type": name});
+        args["args"].as_array_mut().unwrap().push(accept.clone());
+        args["route_args"].as_array_mut().unwrap().push(accept);
+        args["call_args"].as_array_mut().unwrap().push(json!("accept"));
+        args["result_type"] = json!("String");
+        args["negotiate"] = json!({
+            "name": name,
+            "default": variants[0]["path"],
+            "variants": variants
+        });
+    }
+
+    /// The parameters whose schemas have validation keywords, along with the
+    /// names of the newtypes which validate them. Parameters with a default
+    /// are left unvalidated, and arrays are only validated in the query or
+    /// the request body
+    pub fn validated_params(&self) -> Vec<(String, &'a Schema)> {
+        self.validated_args()
+            .into_iter()
+            .map(|(arg, name)| (name, arg.schema.unwrap()))
+            .collect()
+    }
+
+    fn validated_args(&self) -> Vec<(&Arg<'a>, String)> {
+        self.args
+            .iter()
+            .filter(|arg| arg.default.is_none())
+            .filter(|arg| match arg.location {
+                Location::Query => true,
+                Location::Body => arg.is_array(),
+                _ => !arg.is_array(),
+            })
+            .filter(|arg| {
+                arg.schema
+                    .map_or(false, |schema| typegen::is_validated(schema, self.type_options))
+            })
+            .map(|arg| {
+                let name = format!("{}{}", self.operation_id.classcase(), arg.name.to_class_case());
+                (arg, name)
+            })
+            .collect()
+    }
+
+    /// Have the route receive parameters wrapped in the newtypes of
+    /// `validated_params`, so that Rocket rejects invalid values before the
+    /// stub is called
+    pub fn add_param_validation(&self, args: &mut JsonValue) {
+        for (arg, newtype) in self.validated_args() {
+            let type_ = if arg.type_.is_option() {
+                format!("Option<{}>", newtype)
+            } else {
+                newtype.clone()
+            };
+            let lists: &[&str] = if arg.location == Location::Body {
+                // Still received as JSON, so only the type within changes
+                let route_type = if arg.type_.is_option() {
+                    format!("Option<Json<{}>>", newtype)
+                } else {
+                    format!("Json<{}>", newtype)
+                };
+                for item in args["route_args"].as_array_mut().unwrap() {
+                    if item["name"] == arg.name.as_str() {
+                        item["type"] = json!(route_type);
+                    }
+                }
+                &["/args"]
+            } else {
+                if arg.is_array() {
+                    // Query arrays are accumulated into a `Vec`, then validated
+                    let build = if arg.type_.is_option() {
+                        format!(
+                            "if {0}.is_empty() {{ None }} else {{ Some({1}::new({0}).map_err(|_| ())?) }}",
+                            arg.name, newtype
+                        )
+                    } else {
+                        format!("{}::new({}).map_err(|_| ())?", newtype, arg.name)
+                    };
+                    if let Some(fields) = args["query"]["fields"].as_array_mut() {
+                        for field in fields.iter_mut().filter(|f| f["name"] == arg.name.as_str()) {
+                            field["build"] = json!(build);
+                        }
+                    }
+                }
+                &["/args", "/route_args", "/query/fields", "/headers/fields", "/cookies/fields"]
+            };
+            for list in lists {
+                if let Some(items) = args.pointer_mut(list).and_then(|list| list.as_array_mut()) {
+                    for item in items.iter_mut().filter(|item| item["name"] == arg.name.as_str()) {
+                        item["type"] = json!(type_);
+                    }
+                }
+            }
+        }
+    }
+
+    // Only the first response in the 200 range is used, unless `add_response_enum`
+    // is applied
+    fn success_response(&self) -> Option<&Response<'a>> {
+        self.success_responses().into_iter().next()
+    }
+
+    fn success_responses(&self) -> Vec<&Response<'a>> {
+        self.responses
             .iter()
             .filter(|resp| resp.status_code.starts_with("2"))
-            .next() {
-            Some(ref resp) => {
+            .collect()
+    }
+
+    /// The number of success responses whose bodies differ from that of the
+    /// first, and so are lost unless `add_response_enum` is applied
+    pub fn discarded_success_responses(&self) -> usize {
+        let responses = self.success_responses();
+        match responses.split_first() {
+            Some((first, rest)) => rest.iter()
+                .filter(|resp| resp.return_type != first.return_type)
+                .count(),
+            None => 0,
+        }
+    }
+
+    /// Have the stub return the example of its success response, if it has
+    /// one, rather than `unimplemented!()`. JSON examples are checked against
+    /// the response's schema while generating, and those which don't match
+    /// it are left out with a warning. Scalars are returned as literals, and
+    /// the rest parsed into the result type when the stub is called.
+    /// Negotiated responses and response enums are left unimplemented
+    pub fn add_example_return(&self, args: &mut JsonValue) {
+        if !args["negotiate"].is_null() || !args["responses"].is_null() {
+            return;
+        }
+        let response = match self.success_response() {
+            Some(response) => response,
+            None => return,
+        };
+        let example = match response.example {
+            Some(example) => example,
+            None => return,
+        };
+        let expr = if args["response"]["json"] == true {
+            let matches = response
+                .schema
+                .map_or(false, |schema| example_matches(schema, example, self.schemas, 0));
+            if !matches {
+                warn(format!(
+                    "The example response of operation '{}' does not match its schema \
+                     and has been ignored",
+                    self.operation_id.as_str()
+                ));
+                return;
+            }
+            let literal = response
+                .return_type
+                .as_ref()
+                .and_then(|type_| default_literal(type_, example));
+            Some(literal.unwrap_or_else(|| format!(
+                "::serde_json::from_str({:?}).unwrap()",
+                example.to_string()
+            )))
+        } else {
+            // Binary bodies have no JSON representation
+            match (args["result_type"].as_str(), example.as_str()) {
+                (Some("String"), Some(text)) => Some(format!("{:?}.into()", text)),
+                _ => None,
+            }
+        };
+        args["example"] = json!(expr);
+    }
+
+    /// Return an enum with a variant for each success response, so that the
+    /// stub chooses the status code. Does nothing for entrypoints with fewer
+    /// than two success responses
+    pub fn add_response_enum(&self, args: &mut JsonValue) {
+        let responses = self.success_responses();
+        if responses.len() < 2 {
+            return;
+        }
+        let name = format!("{}Response", self.operation_id.classcase());
+        let mut anon = self.anon_names();
+        let variants = response_variants(&name, &responses, 200, &mut anon);
+        args["result_type"] = json!(name);
+        args["response"] = json!({"plain": true});
+        let async_ = args["async"].clone();
+        args["responses"] = json!({
+            "name": name,
+            "variants": variants,
+            "async": async_
+        });
+    }
+
+    /// Return an enum with a variant for each error response, which responds
+    /// with the status code the variant came from, so that the stub chooses
+    /// which error to send. Does nothing for entrypoints with fewer than two
+    /// error responses
+    pub fn add_error_enum(&self, args: &mut JsonValue) {
+        let responses = self.error_responses();
+        if responses.len() < 2 {
+            return;
+        }
+        let name = format!("{}Error", self.operation_id.classcase());
+        // Named after the success responses, as in `anonymous_schemas`
+        let mut anon = self.anon_names();
+        for resp in self.success_responses() {
+            if let Some(ref type_) = resp.return_type {
+                type_.render(&mut anon);
+            }
+        }
+        let variants = response_variants(&name, &responses, 500, &mut anon);
+        args["error_type"] = json!(name);
+        args["error"] = JsonValue::Null;
+        let async_ = args["async"].clone();
+        args["errors"] = json!({
+            "name": name,
+            "variants": variants,
+            "async": async_
+        });
+    }
+
+    /// Wrap the success response in a type which also carries the values of
+    /// the headers it declares, for the route to set. Every header is optional,
+    /// so the stub need only fill in those it has values for.
+    pub fn add_response_headers(&self, args: &mut JsonValue) {
+        let resp = match self.success_response() {
+            Some(resp) if !resp.headers.is_empty() => resp,
+            _ => return,
+        };
+        if !args["responses"].is_null() || args["response"]["json"] != true {
+            warn(format!(
+                "The response headers of {} are only supported for a single JSON \
+                 response, and have been ignored",
+                self.operation_id.0
+            ));
+            return;
+        }
+        let name = format!("{}Response", self.operation_id.classcase());
+        let body = match resp.return_type {
+            Some(_) => Some(args["result_type"].clone()),
+            None => None,
+        };
+        let status = resp.status_code
+            .parse()
+            .ok()
+            .and_then(Status::from_code)
+            .map(|status| status.code)
+            .unwrap_or(200);
+        let fields: Vec<JsonValue> = resp.headers
+            .iter()
+            .map(|header| json!({"name": header.to_snake_case(), "header": header}))
+            .collect();
+        args["result_type"] = json!(name);
+        args["response"] = json!({"plain": true});
+        args["response_headers"] = json!({
+            "name": name,
+            "body": body,
+            "status": status,
+            "fields": fields
+        });
+    }
+
+    // The first documented error response with a body, unless `add_error_enum`
+    // is applied
+    fn error_response(&self) -> Option<(&Response<'a>, &NativeType<'a>)> {
+        self.error_responses()
+            .into_iter()
+            .filter_map(|resp| resp.return_type.as_ref().map(|type_| (resp, type_)))
+            .next()
+    }
+
+    fn error_responses(&self) -> Vec<&Response<'a>> {
+        self.responses
+            .iter()
+            .filter(|resp| {
+                resp.status_code.starts_with("4") || resp.status_code.starts_with("5")
+                    || resp.status_code == "default"
+            })
+            .collect()
+    }
+
+    fn result_type(&self, anon: &mut AnonNames) -> String {
+        match self.success_response() {
+            Some(resp) => {
                 match resp.return_type {
-                    Some(ref type_) => type_.render(anon_count, &self.operation_id).0,
+                    Some(ref type_) => type_.render(anon),
                     None => "()".into(),
                 }
             }
             None => {
-                eprintln!("Warning: no success code found");
+                warn("no success code found");
                 "()".into()
             }
         }
     }
 
-    fn query_param(&self) -> Option<String> {
-        let query_params: Vec<_> = self.args.iter()
-            .filter(|arg| arg.location == Location::Query)
-            .collect();
-        if query_params.len() == 0 {
-            None
-        } else {
-            Some("thing".into())
+    // The type and status code of the error response. A `default` response,
+    // or one whose code Rocket does not know, is sent as a 500
+    fn error_type(&self, anon: &mut AnonNames) -> Option<(String, u16)> {
+        self.error_response().map(|(resp, type_)| {
+            let status = resp.status_code
+                .parse()
+                .ok()
+                .and_then(Status::from_code)
+                .map(|status| status.code)
+                .unwrap_or(500);
+            (type_.render(anon), status)
+        })
+    }
+
+    /// The inline object schemas of the arguments and responses, under the
+    /// names `build_template_args` gives them. Identical schemas appear once
+    pub fn anonymous_schemas(&self) -> Vec<(String, Schema)> {
+        let mut anon = self.anon_names();
+        // Then those only named by `add_response_enum` and `add_error_enum`
+        for resp in self.success_responses().into_iter().chain(self.error_responses()) {
+            if let Some(ref type_) = resp.return_type {
+                type_.render(&mut anon);
+            }
+        }
+        anon.schemas
+    }
+
+    // The names given to anonymous schemas by `build_template_args`, visiting
+    // the types in the same order it does
+    fn anon_names(&self) -> AnonNames {
+        let mut anon = AnonNames::new(&self.operation_id, self.schemas);
+        for arg in self.args.iter() {
+            arg.type_.render(&mut anon);
+        }
+        if let Some(type_) = self.success_response().and_then(|r| r.return_type.as_ref()) {
+            type_.render(&mut anon);
         }
+        if let Some((_, type_)) = self.error_response() {
+            type_.render(&mut anon);
+        }
+        anon
     }
 
-    pub fn swagger_entrypoint() -> Entrypoint<'a> {
-        Entrypoint::new(
-            Route::from_str("/swagger".into()).unwrap(),
+    pub fn swagger_entrypoint(path: &'a str) -> Result<Entrypoint<'a>> {
+        let mut entrypoint = Entrypoint::new(
+            Route::from_str(path)?,
             Method::Get,
             Vec::new().into(),
-            vec![Response::new("200".into(),
-                               Some(NativeType::String),
-                               Some("application/json".into()))],
+            vec![Response::new("200",
+                               Some(NativeType::Any),
+                               vec!["application/json"])],
             OperationId::new("getSwagger").unwrap(),
             Some("OpenAPI schema in JSON format".into()),
             None,
-        ).unwrap()
+        )?;
+        entrypoint.serves_spec = true;
+        Ok(entrypoint)
     }
 }
 
+impl<'a> Entrypoint<'a> {
+    /// A route for liveness probes, whose stub reports that the service is up
+    pub fn health_entrypoint(path: &'a str) -> Result<Entrypoint<'a>> {
+        let mut entrypoint = Entrypoint::new(
+            Route::from_str(path)?,
+            Method::Get,
+            Vec::new().into(),
+            vec![Response::new("200",
+                               Some(NativeType::Any),
+                               vec!["application/json"])],
+            OperationId::new("getHealth").unwrap(),
+            Some("Service health check".into()),
+            None,
+        )?;
+        entrypoint.stub_result = Some(r#"vec![("status", "ok")].into_iter().collect()"#.into());
+        Ok(entrypoint)
+    }
+
+    /// Whether the two would clash if both were generated, either as routes
+    /// or as functions
+    pub fn conflicts_with(&self, other: &Entrypoint) -> bool {
+        (self.method == other.method && self.route == other.route)
+            || self.operation_id == other.operation_id
+    }
+}
+
+/// Extract the entrypoints of the spec with the default `TypeOptions`,
+/// printing and skipping any operations (or responses) which fail to build.
+/// They are in the order of `collect_entrypoints`
 pub fn extract_entrypoints(spec: &OpenApi) -> Vec<Entrypoint> {
+    let (entrypoints, errors) = collect_entrypoints(spec, TypeOptions::default());
+    for e in errors {
+        // TODO better error handling
+        eprintln!("{}", e);
+    }
+    entrypoints
+}
+
+// A summary of the entrypoint for tooling, rather than for the templates
+impl<'a> Serialize for Entrypoint<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        let mut anon = self.anon_names();
+        let args: Vec<JsonValue> = self.args
+            .iter()
+            .map(|arg| {
+                json!({
+                    "name": arg.name,
+                    "spec_name": arg.spec_name,
+                    "location": arg.location,
+                    "type": arg.type_.render(&mut anon)
+                })
+            })
+            .collect();
+        let result_type = self.success_response()
+            .and_then(|resp| resp.return_type.as_ref())
+            .map(|type_| type_.render(&mut anon));
+        let statuses: Vec<&str> = self.responses.iter().map(|resp| resp.status_code).collect();
+        json!({
+            "operation_id": self.operation_id,
+            "method": self.method,
+            "path": self.path(),
+            "base_path": self.base_path,
+            "summary": self.summary,
+            "description": self.description,
+            "args": args,
+            "result_type": result_type,
+            "responses": statuses,
+            "deprecated": self.deprecated
+        }).serialize(serializer)
+    }
+}
+
+/// Extract the entrypoints of the spec, along with the errors of any
+/// operations or responses which failed to build and so were left out.
+///
+/// Entrypoints are ordered by path, then by HTTP verb (GET, POST, PUT, PATCH,
+/// DELETE, HEAD, OPTIONS). The order the paths are declared in is not kept,
+/// as `OpenApi` holds them in a sorted map
+pub fn collect_entrypoints(
+    spec: &OpenApi,
+    options: TypeOptions,
+) -> (Vec<Entrypoint>, Vec<Error>) {
     let mut out = Vec::new();
-    let mut components = &Default::default();
-    components = spec.components.as_ref().unwrap_or(components);
+    let mut errors = Vec::new();
+    let components = spec.components.as_ref();
+    let root_base = servers_base_path(&spec.servers);
+    let schemes = security_schemes(spec);
+    let explicit_ids: Vec<OperationId> = spec
+        .paths
+        .values()
+        .flat_map(|path| path_operations(path))
+        .filter_map(|(_, op)| op.operation_id.as_ref())
+        .filter_map(|id| OperationId::new(id).ok())
+        .collect();
+    // openapi3 keeps paths in a sorted map, so routes are ordered by path
+    // rather than as declared, which at least keeps the output stable
     for (route, path) in &spec.paths {
-        for (method, op) in path_as_map(path) {
-            match Entrypoint::build(route, method, op, components) {
-                Ok(entrypoint) => out.push(entrypoint),
-                // TODO better error handling
-                Err(e) => eprintln!("{}", e),
+        let path_base = servers_base_path(&path.servers);
+        for (method, op) in path_operations(path) {
+            match Entrypoint::build_with(route, method, op, components, options, &mut errors) {
+                Ok(mut entrypoint) => {
+                    // The most specific `servers` wins: those of the operation,
+                    // then those of the path item, then those of the spec itself
+                    entrypoint.base_path = servers_base_path(&op.servers)
+                        .or_else(|| path_base.clone())
+                        .or_else(|| root_base.clone())
+                        .unwrap_or_else(|| "/".into());
+                    if op.operation_id.is_none() {
+                        // A made-up id gives way to those given in the spec,
+                        // and to those made up earlier
+                        let base = entrypoint.operation_id.0.clone();
+                        let mut n = 1;
+                        while explicit_ids.contains(&entrypoint.operation_id)
+                            || out.iter().any(|e: &Entrypoint| e.operation_id == entrypoint.operation_id)
+                        {
+                            n += 1;
+                            entrypoint.operation_id = OperationId(format!("{}_{}", base, n));
+                        }
+                    }
+                    // Likewise the operation's security requirements override the spec's
+                    if let Some(requirements) = op.security.as_ref().or(spec.security.as_ref()) {
+                        entrypoint.security =
+                            required_security(&entrypoint.operation_id, requirements, &schemes);
+                    }
+                    let clash = out.iter()
+                        .find(|e: &&Entrypoint| e.operation_id == entrypoint.operation_id)
+                        .map(|e| e.spec_operation_id.clone());
+                    match clash {
+                        // Both would generate a function of the same name
+                        Some(other) => {
+                            let mut msg = format!(
+                                "Duplicate operationId '{}' ({:?} {})",
+                                entrypoint.operation_id.0, method, route
+                            );
+                            if let (Some(a), Some(b)) = (other, op.operation_id.as_ref()) {
+                                if a != *b {
+                                    msg.push_str(&format!(
+                                        ", as '{}' and '{}' both become `{}`",
+                                        a, b, entrypoint.operation_id.0
+                                    ));
+                                }
+                            }
+                            errors.push(msg.into());
+                        }
+                        None => out.push(entrypoint),
+                    }
+                }
+                Err(e) => errors.push(e),
             }
         }
     }
+    (out, errors)
+}
+
+/// How the names in the spec map to the identifiers generated from them
+#[derive(Debug, Default, Serialize)]
+pub struct NameMap {
+    /// operationId -> function
+    pub operations: BTreeMap<String, String>,
+    /// function -> (parameter -> argument)
+    pub args: BTreeMap<String, BTreeMap<String, String>>,
+    /// Component schema -> type
+    pub types: BTreeMap<String, String>,
+}
+
+/// The identifiers generated from the spec's names, for mapping generated
+/// code back to the spec
+pub fn name_map(spec: &OpenApi, entrypoints: &[Entrypoint]) -> NameMap {
+    let mut map = NameMap::default();
+    for entry in entrypoints {
+        if let Some(ref id) = entry.spec_operation_id {
+            map.operations.insert(id.clone(), entry.operation_id.0.clone());
+        }
+        let args = entry.args
+            .iter()
+            .map(|arg| (arg.spec_name.clone(), arg.name.clone()))
+            .collect();
+        map.args.insert(entry.operation_id.0.clone(), args);
+    }
+    if let Some(schemas) = spec.components.as_ref().and_then(|c| c.schemas.as_ref()) {
+        for name in schemas.keys() {
+            map.types.insert(name.clone(), name.to_class_case());
+        }
+    }
+    map
+}
+
+/// Fail if two component schemas would generate types of the same name,
+/// e.g. "pet" and "Pet"
+pub fn check_type_names(spec: &OpenApi) -> Result<()> {
+    let mut seen: BTreeMap<String, &str> = BTreeMap::new();
+    let schemas = spec.components.as_ref().and_then(|c| c.schemas.as_ref());
+    for name in schemas.into_iter().flat_map(|schemas| schemas.keys()) {
+        if let Some(other) = seen.insert(name.to_class_case(), name) {
+            bail!(
+                "Schemas '{}' and '{}' both become the type `{}`",
+                other,
+                name,
+                name.to_class_case()
+            )
+        }
+    }
+    Ok(())
+}
+
+/// How a component schema is generated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaKind {
+    /// A struct named after the schema
+    Struct,
+    /// An enum named after the schema, of its values or its `oneOf` variants
+    Enum,
+    /// An alias of another type, e.g. `Vec<Pet>`
+    Alias,
+    /// Free-form, or containing inline objects, so some of its structure is
+    /// only available as JSON
+    Opaque,
+    /// A schema thruster cannot generate a type for
+    Unsupported,
+}
+
+/// The type generated for a component schema
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaType {
+    pub schema: String,
+    pub kind: SchemaKind,
+    /// The Rust type the schema is used as, where it could be worked out
+    pub rust_type: Option<String>,
+    /// Why the schema is unsupported
+    pub error: Option<String>,
+}
+
+/// The types generated for each of the spec's component schemas, for finding
+/// schemas which will not generate cleanly
+#[derive(Clone, Debug, Serialize)]
+pub struct TypeReport(pub Vec<SchemaType>);
+
+impl fmt::Display for TypeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.0 {
+            write!(f, "{}: ", entry.schema)?;
+            match (&entry.rust_type, &entry.error) {
+                (_, &Some(ref error)) => write!(f, "unsupported ({})", error)?,
+                (&Some(ref type_), _) => write!(f, "{:?} {}", entry.kind, type_)?,
+                (&None, &None) => write!(f, "{:?}", entry.kind)?,
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The type generated for each component schema, as `NativeType` sees it
+pub fn type_report(spec: &OpenApi) -> TypeReport {
+    let schemas = spec.components.as_ref().and_then(|c| c.schemas.as_ref());
+    let entries = schemas
+        .into_iter()
+        .flat_map(|schemas| schemas.iter())
+        .map(|(name, schema)| schema_type(name, schema))
+        .collect();
+    TypeReport(entries)
+}
+
+fn schema_type(name: &str, schema: &Schema) -> SchemaType {
+    let type_name = name.to_class_case();
+    let mut entry = SchemaType {
+        schema: name.into(),
+        kind: SchemaKind::Alias,
+        rust_type: None,
+        error: None,
+    };
+    if schema.all_of.is_some() && single_ref_all_of(schema).is_none() {
+        entry.kind = SchemaKind::Struct;
+        entry.rust_type = Some(type_name);
+        return entry;
+    }
+    let multi_typed = schema
+        .type_
+        .iter()
+        .filter(|type_| **type_ != SimpleTypes::Null)
+        .count() > 1;
+    if schema.one_of.is_some() || schema.any_of.is_some() || schema.enum_.is_some()
+        || multi_typed
+    {
+        entry.kind = SchemaKind::Enum;
+        entry.rust_type = Some(type_name);
+        return entry;
+    }
+    match NativeType::from_json_schema(schema, true) {
+        Ok(NativeType::Anonymous(_)) => {
+            entry.kind = SchemaKind::Struct;
+            entry.rust_type = Some(type_name);
+        }
+        Ok(native) => {
+            let mut inline_objects = false;
+            let rendered = native.render_with(&mut |_| {
+                inline_objects = true;
+                "::serde_json::Value".into()
+            });
+            if inline_objects || native == NativeType::Any {
+                entry.kind = SchemaKind::Opaque;
+            }
+            entry.rust_type = Some(rendered);
+        }
+        Err(e) => {
+            entry.kind = SchemaKind::Unsupported;
+            entry.error = Some(e.to_string());
+        }
+    }
+    entry
+}
+
+// The security schemes which can be enforced by a request guard, by name
+fn security_schemes(spec: &OpenApi) -> BTreeMap<String, SecurityKind> {
+    let mut out = BTreeMap::new();
+    let schemes = spec
+        .components
+        .as_ref()
+        .and_then(|components| ::serde_json::to_value(&components.security_schemes).ok())
+        .unwrap_or(JsonValue::Null);
+    if let Some(schemes) = schemes.as_object() {
+        for (name, scheme) in schemes {
+            let key_name = scheme["name"].as_str().unwrap_or("").to_string();
+            let bearer = scheme["scheme"]
+                .as_str()
+                .map_or(false, |s| s.eq_ignore_ascii_case("bearer"));
+            let kind = match (scheme["type"].as_str(), scheme["in"].as_str()) {
+                (Some("apiKey"), Some("header")) => SecurityKind::ApiKey {
+                    name: key_name,
+                    location: Location::Header,
+                },
+                (Some("apiKey"), Some("cookie")) => SecurityKind::ApiKey {
+                    name: key_name,
+                    location: Location::Cookie,
+                },
+                (Some("http"), _) if bearer => SecurityKind::Bearer,
+                _ => continue,
+            };
+            out.insert(name.clone(), kind);
+        }
+    }
     out
 }
 
+// The credentials an operation requires. Of several alternative requirements
+// only the first is enforced, and none if any allows anonymous access
+fn required_security(
+    operation_id: &OperationId,
+    requirements: &[BTreeMap<String, Vec<String>>],
+    schemes: &BTreeMap<String, SecurityKind>,
+) -> Vec<Security> {
+    if requirements.iter().any(|requirement| requirement.is_empty()) {
+        return Vec::new();
+    }
+    let requirement = match requirements.first() {
+        Some(requirement) => requirement,
+        None => return Vec::new(),
+    };
+    if requirements.len() > 1 {
+        warn(format!(
+            "Only the first of the alternative security requirements of {} is enforced",
+            operation_id.0
+        ));
+    }
+    requirement
+        .keys()
+        .filter_map(|scheme| match schemes.get(scheme) {
+            Some(kind) => Some(Security {
+                scheme: scheme.clone(),
+                kind: kind.clone(),
+            }),
+            None => {
+                warn(format!(
+                    "Security scheme '{}' of {} is not supported, so is not enforced",
+                    scheme, operation_id.0
+                ));
+                None
+            }
+        })
+        .collect()
+}
+
+/// The distinct security schemes required across all entrypoints, each of
+/// which has a request guard
+pub fn security_guards(entrypoints: &[Entrypoint]) -> Vec<&Security> {
+    let mut out: Vec<&Security> = Vec::new();
+    for security in entrypoints.iter().flat_map(|entry| entry.security.iter()) {
+        if !out.iter().any(|other| other.scheme == security.scheme) {
+            out.push(security);
+        }
+    }
+    out
+}
+
+// The base path of the first server, if any servers are given
+fn servers_base_path(servers: &Option<Vec<Server>>) -> Option<String> {
+    servers
+        .as_ref()
+        .and_then(|servers| servers.first())
+        .map(|server| {
+            let mut url = server.url.clone();
+            if let Some(ref variables) = server.variables {
+                for (name, variable) in variables {
+                    url = url.replace(&format!("{{{}}}", name), &variable.default);
+                }
+            }
+            base_path(&url)
+        })
+}
+
+/// The path component of a server URL, e.g. "http://localhost:8080/v1/" -> "/v1".
+/// Relative URLs are taken to be paths.
+pub fn base_path(url: &str) -> String {
+    let path = match url.find("://") {
+        Some(ix) => {
+            let rest = &url[ix + 3..];
+            rest.find('/').map(|ix| &rest[ix..]).unwrap_or("")
+        }
+        None => url,
+    };
+    let path = path.trim_right_matches('/');
+    if path.is_empty() {
+        "/".into()
+    } else if path.starts_with('/') {
+        path.into()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+// Collect the distinct error status codes documented across all entrypoints
+pub fn error_statuses(entrypoints: &[Entrypoint]) -> Vec<u16> {
+    let codes: BTreeSet<u16> = entrypoints
+        .iter()
+        .flat_map(|entry| entry.responses.iter())
+        .filter_map(|resp| resp.status_code.parse::<u16>().ok())
+        .filter(|&code| code >= 400)
+        .collect();
+    codes.into_iter().collect()
+}
+
+// Names the inline object schemas of an entrypoint in the order they are
+// encountered, reusing the name of an identical named schema or one seen earlier
+struct AnonNames<'a> {
+    prefix: String,
+    named: Option<&'a BTreeMap<String, Schema>>,
+    schemas: Vec<(String, Schema)>,
+}
+
+impl<'a> AnonNames<'a> {
+    fn new(
+        operation_id: &OperationId,
+        named: Option<&'a BTreeMap<String, Schema>>,
+    ) -> AnonNames<'a> {
+        AnonNames {
+            prefix: operation_id.classcase(),
+            named,
+            schemas: Vec::new(),
+        }
+    }
+
+    fn name(&mut self, schema: &Schema) -> String {
+        // A schema written out in full where a named one would do is the
+        // named type, rather than a copy of it
+        if let Some(named) = self.named {
+            if let Some((name, _)) = named.iter().find(|&(_, s)| s == schema) {
+                return name.clone();
+            }
+        }
+        if let Some(&(ref name, _)) = self.schemas.iter().find(|&&(_, ref s)| s == schema) {
+            return name.clone();
+        }
+        let name = format!("{}AnonArg{}", self.prefix, self.schemas.len() + 1);
+        self.schemas.push((name.clone(), schema.clone()));
+        name
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct OperationId(String);
 
 impl OperationId {
-    // TODO make this from<&str> instead
+    // The id becomes a function name, so it is snake_cased. Ids which would
+    // still not be a legal identifier (a leading digit, or a keyword) are
+    // prefixed with `op_`, e.g. "123go" -> "op_123go"
     fn new(s: &str) -> Result<OperationId> {
+        if s.is_empty() {
+            bail!("Empty operationId")
+        }
         for byte in s.as_bytes() {
             match *byte {
-                b'A'...b'Z' | b'a'...b'z' | b'_' => (),
+                b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'_' => (),
                 b => bail!("Invalid operationId char '{}'", b),
             }
         }
-        Ok(OperationId(s.to_snake_case()))
+        let mut name = s.to_snake_case();
+        if name.is_empty() {
+            bail!("operationId '{}' has no usable characters", s)
+        }
+        if name.starts_with(|c: char| c.is_numeric()) || KEYWORDS.contains(&name.as_str()) {
+            name.insert_str(0, "op_");
+        }
+        Ok(OperationId(name))
+    }
+
+    // An id for an operation which has none, from its method and route,
+    // e.g. GET "/pets/{petId}" -> "get_pets_pet_id"
+    fn from_route(method: Method, route: &str) -> OperationId {
+        let words: String = route
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { ' ' })
+            .collect();
+        OperationId(format!("{:?} {}", method, words).to_snake_case())
     }
 
     fn classcase(&self) -> String {
         self.0.to_class_case()
     }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 #[derive(Debug, Clone)]
-pub struct Arg {
+pub struct Arg<'a> {
     name: String,
-    type_: NativeType,
+    // The name as it appears in the spec
+    spec_name: String,
+    type_: NativeType<'a>,
     location: Location,
+    description: Option<&'a str>,
+    // The schema's `default`, applied to optional query parameters
+    default: Option<&'a JsonValue>,
+    // The schema of a parameter
+    schema: Option<&'a Schema>,
+    // An example value from the spec, used by generated tests
+    example: Option<&'a JsonValue>,
+    // The media types a request body may be sent as
+    content_types: Vec<&'a str>,
+    // The separator between the values of an array query parameter sent as a
+    // single value (e.g. `?tags=a|b`), rather than as repeated keys
+    delimiter: Option<char>,
 }
 
-impl Arg {
-    fn new(name: &str, type_: NativeType, location: Location) -> Self {
+impl<'a> Arg<'a> {
+    fn new(name: &str, type_: NativeType<'a>, location: Location) -> Self {
         Self {
             name: name.to_snake_case(),
+            spec_name: name.into(),
             type_,
             location,
+            description: None,
+            default: None,
+            schema: None,
+            example: None,
+            content_types: Vec::new(),
+            delimiter: None,
         }
     }
+
+    fn is_array(&self) -> bool {
+        match self.type_ {
+            NativeType::Option(ref inner) => inner.is_array(),
+            ref type_ => type_.is_array(),
+        }
+    }
+
+    // Whether a request body may be sent both as JSON and as a URL-encoded form
+    fn accepts_json_and_form(&self) -> bool {
+        self.content_types.iter().any(|mime| is_json(mime))
+            && self.content_types.iter().any(|mime| *mime == FORM)
+    }
 }
 
-impl Arg {
-    fn build_from_parameter(parameter: &Parameter) -> Result<Arg> {
-        let required = parameter.required.unwrap_or(false);
-        let native_type = NativeType::from_json_schema(&parameter.schema, required)?;
-        Ok(Arg::new(&parameter.name, native_type, parameter.in_))
+impl<'a> Arg<'a> {
+    /// The argument's name in the generated code
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The argument's name in the spec
+    pub fn spec_name(&self) -> &str {
+        &self.spec_name
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn native_type(&self) -> &NativeType<'a> {
+        &self.type_
+    }
+
+    fn build_from_parameter(parameter: &'a Parameter, options: TypeOptions) -> Result<Arg<'a>> {
+        let mut required = parameter.required.unwrap_or(false);
+        if parameter.in_ == ParameterLocation::Query && parameter.allow_empty_value == Some(true) {
+            // A present-but-empty value (`?key=`) is valid, and will not parse
+            // as anything other than a string, so the field must be optional.
+            // `allowReserved` needs no handling since Rocket percent-decodes
+            // the query string before parsing it
+            required = false;
+        }
+        let native_type = NativeType::from_json_schema_with(&parameter.schema, required, options)?;
+        let mut arg = Arg::new(&parameter.name, native_type, parameter.in_.into());
+        arg.description = parameter.description.as_ref().map(|d| d.as_str());
+        arg.default = parameter.schema.default.as_ref();
+        arg.schema = Some(&parameter.schema);
+        arg.example = parameter.schema.example.as_ref();
+        let is_array = match arg.type_ {
+            NativeType::Option(ref inner) => inner.is_array(),
+            ref type_ => type_.is_array(),
+        };
+        if arg.location == Location::Query && is_array {
+            arg.delimiter = query_delimiter(parameter);
+        }
+        Ok(arg)
+    }
+
+    fn build_from_request_body(
+        request_body: &'a RequestBody,
+        options: TypeOptions,
+    ) -> Result<Arg<'a>> {
+        // Prefer JSON, since that is what the generated route can deserialize
+        let mut content = request_body.content.iter();
+        let (mime, media) = match content.clone().find(|&(mime, _)| mime == "application/json") {
+            Some(json) => json,
+            None => content
+                .next()
+                .ok_or(ErrorKind::from("Request body has no content"))?,
+        };
+        // Unlike responses, request bodies are optional unless stated otherwise
+        let required = request_body.required.unwrap_or(false);
+        let native_type = if mime == "application/octet-stream" {
+            // Streamed to the stub as raw bytes, whatever the schema (if any) says
+            let bytes = NativeType::Bytes;
+            if required {
+                bytes
+            } else {
+                NativeType::Option(Box::new(bytes))
+            }
+        } else {
+            let schema = media
+                .schema
+                .as_ref()
+                .ok_or(ErrorKind::from("Request body has no schema"))?;
+            NativeType::from_json_schema_with(schema, required, options)?
+        };
+        let mut arg = Arg::new("body", native_type, Location::Body);
+        arg.schema = media.schema.as_ref();
+        arg.description = request_body.description.as_ref().map(|d| d.as_str());
+        arg.example = media
+            .example
+            .as_ref()
+            .or(media.schema.as_ref().and_then(|schema| schema.example.as_ref()));
+        arg.content_types = request_body.content.keys().map(|k| k.as_str()).collect();
+        Ok(arg)
     }
 }
 
-fn build_args(operation: &Operation, components: &Components) -> Result<Args> {
-    let op_parameters = match operation.parameters.as_ref() {
-        Some(p) => p,
-        None => return Ok(Vec::new().into()),
+// The separator implied by the `style` and `explode` of an array query
+// parameter. Exploded arrays, the default for `form` style, repeat the key
+fn query_delimiter(parameter: &Parameter) -> Option<char> {
+    let style = parameter.style.as_ref().map(|s| s.as_str()).unwrap_or("form");
+    let explode = parameter.explode.unwrap_or(style == "form");
+    let delimiter = match style {
+        "form" => ',',
+        "spaceDelimited" => ' ',
+        "pipeDelimited" => '|',
+        _ => {
+            warn(format!(
+                "Query parameter '{}' has unsupported style '{}', and is expected as repeated keys",
+                parameter.name, style
+            ));
+            return None;
+        }
     };
-    op_parameters
-        .iter()
-        .map(|maybe| {
-            maybe
-                .resolve_ref_opt(&components.parameters)
-                .map_err(|e| e.into())
-                .and_then(Arg::build_from_parameter)
-        })
-        .collect::<Result<Vec<Arg>>>()
-        .map(|v| v.into())
+    if explode {
+        None
+    } else {
+        Some(delimiter)
+    }
+}
+
+fn build_args<'a>(
+    operation: &'a Operation,
+    components: Option<&'a Components>,
+    options: TypeOptions,
+) -> Result<Args<'a>> {
+    let parameters = components.map(|c| &c.parameters).unwrap_or(&None);
+    let mut args = match operation.parameters.as_ref() {
+        Some(op_parameters) => op_parameters
+            .iter()
+            .map(|maybe| {
+                maybe
+                    .resolve_ref_opt(parameters)
+                    .map_err(|e| e.into())
+                    .and_then(|parameter| Arg::build_from_parameter(parameter, options))
+            })
+            .collect::<Result<Vec<Arg>>>()?,
+        None => Vec::new(),
+    };
+    if let Some(ref maybe) = operation.request_body {
+        let request_bodies = components.map(|c| &c.request_bodies).unwrap_or(&None);
+        let request_body = maybe.resolve_ref_opt(request_bodies)?;
+        args.push(Arg::build_from_request_body(request_body, options)?);
+    }
+    Ok(args.into())
 }
 
 #[derive(Debug, Default, Clone, new)]
-pub struct Response {
-    pub status_code: String,
-    pub return_type: Option<NativeType>,
-    pub content_type: Option<String>,
+pub struct Response<'a> {
+    pub status_code: &'a str,
+    pub return_type: Option<NativeType<'a>>,
+    pub content_types: Vec<&'a str>,
+    /// An example body, from the media type or else its schema
+    #[new(default)]
+    pub example: Option<&'a JsonValue>,
+    /// The schema of the body, which the example is checked against
+    #[new(default)]
+    pub schema: Option<&'a Schema>,
+    /// The names of the headers the response declares
+    #[new(default)]
+    pub headers: Vec<&'a str>,
 }
 
-impl Response {
+impl<'a> Response<'a> {
     fn build_from_response_obj(
-        status_code: String,
-        response_obj: &ResponseObj,
-    ) -> Result<Response> {
-        match response_obj.content {
-            None => return Ok(Response::new(status_code, None, None)), // No data returned
+        status_code: &'a str,
+        response_obj: &'a ResponseObj,
+        schemas: Option<&'a BTreeMap<String, Schema>>,
+        options: TypeOptions,
+    ) -> Result<Response<'a>> {
+        let mut response = match response_obj.content {
+            None => Response::new(status_code, None, Vec::new()), // No data returned
             Some(ref content_map) => {
+                // All content types are retained for negotiation, but the
+                // type is taken from the first
+                let content_types = content_map.keys().map(|k| k.as_str()).collect();
                 content_map
-                    .iter()
+                    .values()
                     .next()
                     .ok_or("Content map empty".into())
-                    .and_then(|(content_type, media)| {
-                        media
-                                .schema
-                                .as_ref()
-                                .ok_or("Media schema not found".into())
-                                // For responses, the default required state is 'true'
-                                .and_then(|maybe| NativeType::from_json_schema(maybe, true))
-                                .map(|typ| {
-                                    Response::new(
-                                        status_code,
-                                        Some(typ),
-                                        Some(content_type.clone()),
-                                    )
-                                })
-                    })
+                    .and_then(|media| {
+                        let schema = media
+                            .schema
+                            .as_ref()
+                            .ok_or(ErrorKind::from("Media schema not found"))?;
+                        // For responses, the default required state is 'true'
+                        let typ = NativeType::from_json_schema_with(schema, true, options)?;
+                        let mut response = Response::new(status_code, Some(typ), content_types);
+                        response.example = media.example.as_ref().or_else(|| schema_example(schema, schemas));
+                        response.schema = Some(schema);
+                        Ok(response)
+                    })?
             }
+        };
+        if let Some(ref headers) = response_obj.headers {
+            response.headers = headers.keys().map(|name| name.as_str()).collect();
         }
+        Ok(response)
     }
 }
 
-fn build_responses(operation: &Operation, components: &Components) -> Vec<Result<Response>> {
+fn build_responses<'a>(
+    operation: &'a Operation,
+    components: Option<&'a Components>,
+    options: TypeOptions,
+) -> Vec<Result<Response<'a>>> {
+    let responses = components.map(|c| &c.responses).unwrap_or(&None);
+    let schemas = components.and_then(|c| c.schemas.as_ref());
     operation
         .responses
         .iter()
         .map(|(code, maybe)| {
-            let response_obj = maybe.resolve_ref_opt(&components.responses)?;
-            Response::build_from_response_obj(code.clone(), response_obj)
+            let response_obj = maybe.resolve_ref_opt(responses)?;
+            Response::build_from_response_obj(code, response_obj, schemas, options)
+        })
+        .collect()
+}
+
+// A variant per response of a response or error enum, named after the reason
+// for its status code. Codes Rocket does not know are sent as `default_status`
+fn response_variants(
+    name: &str,
+    responses: &[&Response],
+    default_status: u16,
+    anon: &mut AnonNames,
+) -> Vec<JsonValue> {
+    responses
+        .iter()
+        .map(|resp| {
+            let status = resp.status_code.parse().ok().and_then(Status::from_code);
+            let variant = match status {
+                Some(status) => status.reason.to_class_case(),
+                None => format!("Status{}", resp.status_code.to_class_case()),
+            };
+            json!({
+                "path": format!("{}::{}", name, variant),
+                "name": variant,
+                "status": status.map(|status| status.code).unwrap_or(default_status),
+                "type": resp.return_type.as_ref().map(|type_| type_.render(anon))
+            })
         })
         .collect()
 }
 
+// A schema's example, following a reference to a component schema
+fn schema_example<'a>(
+    schema: &'a Schema,
+    schemas: Option<&'a BTreeMap<String, Schema>>,
+) -> Option<&'a JsonValue> {
+    if let Some(ref example) = schema.example {
+        return Some(example);
+    }
+    let name = match schema.ref_.as_ref().map(|ref_| ref_name(ref_)) {
+        Some(Ok(name)) => name,
+        _ => return None,
+    };
+    schemas
+        .and_then(|schemas| schemas.get(name))
+        .and_then(|schema| schema.example.as_ref())
+}
+
+// Whether a JSON value is of the shape a schema describes: its types, its
+// enum values and its required properties. Other keywords are not checked.
+// `depth` guards against references which refer to each other in a loop
+fn example_matches(
+    schema: &Schema,
+    value: &JsonValue,
+    schemas: Option<&BTreeMap<String, Schema>>,
+    depth: usize,
+) -> bool {
+    use openapi3::objects::SimpleTypes::*;
+    if depth > 32 {
+        return false;
+    }
+    let matches = |schema: &Schema, value: &JsonValue| {
+        example_matches(schema, value, schemas, depth + 1)
+    };
+    if let Some(ref ref_) = schema.ref_ {
+        return match ref_name(ref_).ok().and_then(|name| schemas.and_then(|s| s.get(name))) {
+            Some(target) => matches(target, value),
+            None => false,
+        };
+    }
+    if value.is_null() && schema.nullable == Some(true) {
+        return true;
+    }
+    if let Some(ref values) = schema.enum_ {
+        if !values.contains(value) {
+            return false;
+        }
+    }
+    if let Some(ref subschemas) = schema.all_of {
+        if !subschemas.iter().all(|sub| matches(sub, value)) {
+            return false;
+        }
+    }
+    for subschemas in schema.one_of.iter().chain(schema.any_of.iter()) {
+        if !subschemas.iter().any(|sub| matches(sub, value)) {
+            return false;
+        }
+    }
+    let object_matches = || {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => return false,
+        };
+        if schema.required.iter().any(|name| !object.contains_key(name)) {
+            return false;
+        }
+        object.iter().all(|(name, value)| match schema.properties.get(name) {
+            Some(prop) => matches(prop, value),
+            None => match additional_properties(schema) {
+                Ok(Some(values)) => matches(&values, value),
+                Ok(None) => true,
+                Err(_) => false,
+            },
+        })
+    };
+    if schema.type_.is_empty() {
+        // Properties imply an object
+        return schema.properties.is_empty() || object_matches();
+    }
+    schema.type_.iter().any(|type_| match *type_ {
+        Integer => value.is_i64() || value.is_u64(),
+        Number => value.is_number(),
+        String => value.is_string(),
+        Boolean => value.is_boolean(),
+        Null => value.is_null(),
+        Array => match value.as_array() {
+            Some(values) => match schema.items.first() {
+                Some(items) => values.iter().all(|value| matches(items, value)),
+                None => true,
+            },
+            None => false,
+        },
+        Object => object_matches(),
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Location {
+    Path,
+    Query,
+    Header,
+    Cookie,
+    Body,
+}
+
+/// A credential which an operation requires
+#[derive(Clone, Debug, PartialEq)]
+pub struct Security {
+    /// The name of the scheme in `components.securitySchemes`
+    pub scheme: String,
+    pub kind: SecurityKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecurityKind {
+    /// A key sent in the named header or cookie
+    ApiKey { name: String, location: Location },
+    /// A token sent as `Authorization: Bearer <token>`
+    Bearer,
+}
+
+impl Security {
+    /// The request guard's type, e.g. "api_key" -> "ApiKeyGuard"
+    pub fn guard_name(&self) -> String {
+        format!("{}Guard", self.scheme.to_class_case())
+    }
+
+    /// An expression for the credential as an `Option<String>`, taken from
+    /// the `request` of a Rocket or an actix-web request guard
+    pub fn credential(&self, actix: bool) -> String {
+        let header = |name: &str| if actix {
+            format!("request.headers().get({:?}).and_then(|v| v.to_str().ok())", name)
+        } else {
+            format!("request.headers().get_one({:?})", name)
+        };
+        match self.kind {
+            SecurityKind::ApiKey { ref name, location: Location::Cookie } => {
+                if actix {
+                    format!("request.cookie({:?}).map(|c| c.value().to_string())", name)
+                } else {
+                    format!("request.cookies().get({:?}).map(|c| c.value().to_string())", name)
+                }
+            }
+            SecurityKind::ApiKey { ref name, .. } => {
+                format!("{}.map(|v| v.to_string())", header(name))
+            }
+            SecurityKind::Bearer => format!(
+                "{}.and_then(|v| if v.starts_with(\"Bearer \") {{ \
+                 Some(v[7..].to_string()) }} else {{ None }})",
+                header("Authorization")
+            ),
+        }
+    }
+}
+
+impl From<ParameterLocation> for Location {
+    fn from(loc: ParameterLocation) -> Location {
+        match loc {
+            ParameterLocation::Path => Location::Path,
+            ParameterLocation::Query => Location::Query,
+            ParameterLocation::Header => Location::Header,
+            ParameterLocation::Cookie => Location::Cookie,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Method {
@@ -306,119 +2188,415 @@ pub enum Method {
     Put,
     Patch,
     Delete,
+    Head,
+    Options,
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub enum NativeType {
+pub enum NativeType<'a> {
     I32,
     I64,
     F32,
     F64,
     Bool,
     String,
+    // Raw bytes (`format: binary`)
+    Bytes,
+    // Bytes sent as base64 text (`format: byte`), decoded by `typegen::BASE64`
+    Base64,
+    // `format: date-time` and `format: date`, with `Formats::chrono`
+    DateTime,
+    Date,
+    // `format: uuid`, with `Formats::uuid`
+    Uuid,
     Named(String),
-    Array(Vec<NativeType>),
-    Option(Box<NativeType>),
-    Anonymous(Box<Schema>),
+    Array(Vec<NativeType<'a>>),
+    Option(Box<NativeType<'a>>),
+    // A map from strings to values of the given type
+    Map(MapType, Box<NativeType<'a>>),
+    // Borrowed from the spec where possible to avoid copying large schemas
+    Anonymous(Cow<'a, Schema>),
+    // Any JSON value at all
+    Any,
+    // `type: "null"`, which can only be `null`
+    Unit,
 }
 
-impl NativeType {
-    fn from_json_schema(schema: &Schema, required: bool) -> Result<Self> {
+impl<'a> NativeType<'a> {
+    /// Map a JSON schema to the Rust type which represents it. Non-required
+    /// schemas are wrapped in `Option`.
+    ///
+    /// Note that `not` constraints cannot be expressed as a Rust type. They are
+    /// reported with a warning and otherwise ignored, so the generated type
+    /// accepts values which the spec forbids.
+    ///
+    /// ```
+    /// # extern crate openapi3;
+    /// # extern crate serde_json;
+    /// # extern crate thruster;
+    /// use openapi3::objects::Schema;
+    /// use thruster::process::NativeType;
+    ///
+    /// # fn main() {
+    /// let schema: Schema = serde_json::from_str(
+    ///     r#"{"type": "array", "items": {"type": "string"}}"#
+    /// ).unwrap();
+    /// let native = NativeType::from_json_schema(&schema, true).unwrap();
+    /// assert_eq!(native, NativeType::Array(vec![NativeType::String]));
+    /// assert_eq!(native.render_with(&mut |_| "Anon".into()), "Vec<String>");
+    /// # }
+    /// ```
+    pub fn from_json_schema(schema: &'a Schema, required: bool) -> Result<Self> {
+        NativeType::from_json_schema_with(schema, required, TypeOptions::default())
+    }
+
+    /// Like `from_json_schema`, but with the formats and map type of `options`
+    pub fn from_json_schema_with(
+        schema: &'a Schema,
+        required: bool,
+        options: TypeOptions,
+    ) -> Result<Self> {
+        if schema.not.is_some() {
+            warn("schema uses `not`, which cannot be enforced by the generated type; \
+                  generating the base type only");
+        }
+        use openapi3::objects::SimpleTypes::Null;
+        // `null` alongside other types, or as the other half of a `oneOf`,
+        // makes the schema nullable
+        let non_null: Vec<_> = schema.type_.iter().filter(|type_| **type_ != Null).collect();
+        let null_variant = nullable_variant(schema);
+        let null_typed = (!non_null.is_empty() && non_null.len() < schema.type_.len())
+            || null_variant.is_some();
         let out = if let Some(ref ref_) = schema.ref_ {
             // If the schema is a reference, grab the name
-            match ref_.rfind("/") {
-                None => bail!("Reference {} is not valid path", ref_),
-                Some(loc) => {
-                    let refname = ref_.split_at(loc + 1).1;
-                    NativeType::Named(refname.into())
-                }
-            }
+            NativeType::Named(ref_name(ref_)?.into())
+        } else if let Some(ref_) = single_ref_all_of(schema) {
+            // `allOf: [$ref]` is commonly used to annotate a reference
+            NativeType::Named(ref_name(ref_)?.into())
+        } else if let Some(variant) = null_variant {
+            NativeType::from_json_schema_with(variant, true, options)?
+        } else if let Some(values) = map_values(schema)? {
+            let values = NativeType::from_json_schema_with(&values, true, options)?.into_owned();
+            NativeType::Map(options.map_type, Box::new(values))
+        } else if is_free_form(schema) {
+            NativeType::Any
         } else {
-            match schema.type_.len() {
-                0 => NativeType::Anonymous(Box::new(schema.clone())), // assume it is an object
+            match non_null.len() {
+                // Assume it is an object
+                0 if schema.type_.is_empty() => NativeType::Anonymous(Cow::Borrowed(schema)),
+                0 => NativeType::Unit,
                 1 => {
                     // If the type is a primitive, pluck it from the schema
                     // Otherwise, return the schema
                     use openapi3::objects::SimpleTypes::*;
-                    match *(schema.type_.first().unwrap()) {
-                        Object => NativeType::Anonymous(Box::new(schema.clone())),
+                    match *non_null[0] {
+                        Object => NativeType::Anonymous(Cow::Borrowed(schema)),
                         Boolean => NativeType::Bool,
-                        Integer => NativeType::I64,
-                        Null => bail!("Null is not valid as per spec"),
-                        Number => NativeType::F64,
-                        String => NativeType::String,
+                        // Unrecognised formats fall back to the widest type
+                        Integer => match schema.format.as_ref().map(|f| f.as_str()) {
+                            Some("int32") => NativeType::I32,
+                            _ => NativeType::I64,
+                        },
+                        Null => unreachable!("null types are filtered out above"),
+                        Number => match schema.format.as_ref().map(|f| f.as_str()) {
+                            Some("float") => NativeType::F32,
+                            _ => NativeType::F64,
+                        },
+                        String => match schema.format.as_ref().map(|f| f.as_str()) {
+                            Some("binary") => NativeType::Bytes,
+                            Some("byte") => NativeType::Base64,
+                            Some("date-time") if options.formats.chrono => NativeType::DateTime,
+                            Some("date") if options.formats.chrono => NativeType::Date,
+                            Some("uuid") if options.formats.uuid => NativeType::Uuid,
+                            _ => NativeType::String,
+                        },
                         Array => {
+                            // `items` is normally a single schema, which openapi3
+                            // exposes as a one-element list
                             if schema.items.len() == 0 {
                                 bail!("Items missing for array schema")
                             }
+                            // A list of item schemas, one per position, has no
+                            // equivalent in OpenAPI 3 and would be silently
+                            // reduced to its first schema
+                            if schema.items.len() > 1 {
+                                bail!(
+                                    "Array schema has {} item schemas, but tuple items are not supported",
+                                    schema.items.len()
+                                )
+                            }
+                            // Requiredness applies to the array, not its elements
                             let natives = schema
                                 .items
                                 .iter()
-                                .map(|schema| NativeType::from_json_schema(schema, required))
+                                .map(|schema| NativeType::from_json_schema_with(schema, true, options))
                                 .collect::<Result<Vec<_>>>()?;
                             NativeType::Array(natives)
                         }
                     }
                 }
-                other => bail!("Schema type is array of len {}", other),
+                // Several types, as OpenAPI 3.1 allows, become an untagged union
+                _ => NativeType::Anonymous(Cow::Borrowed(schema)),
             }
         };
-        if !required {
-            Ok(NativeType::Option(Box::new(out)))
-        } else {
-            Ok(out)
+        // An optional field may be absent, a nullable one may be `null`.
+        // A field which may be either is doubly wrapped, so the two can be told apart
+        let nullable = schema.nullable == Some(true) || null_typed;
+        Ok(match (required, nullable) {
+            (true, false) => out,
+            (true, true) | (false, false) => NativeType::Option(Box::new(out)),
+            (false, true) => NativeType::Option(Box::new(NativeType::Option(Box::new(out)))),
+        })
+    }
+
+    pub(crate) fn is_option(&self) -> bool {
+        match *self {
+            NativeType::Option(_) => true,
+            _ => false,
+        }
+    }
+
+    // Detach the type from the schema it was built from
+    pub fn into_owned<'b>(self) -> NativeType<'b> {
+        use self::NativeType::*;
+        match self {
+            I32 => I32,
+            I64 => I64,
+            F32 => F32,
+            F64 => F64,
+            Bool => Bool,
+            String => String,
+            Bytes => Bytes,
+            Base64 => Base64,
+            DateTime => DateTime,
+            Date => Date,
+            Uuid => Uuid,
+            Named(name) => Named(name),
+            Array(natives) => Array(natives.into_iter().map(|native| native.into_owned()).collect()),
+            Option(native) => Option(Box::new(native.into_owned())),
+            Map(map_type, native) => Map(map_type, Box::new(native.into_owned())),
+            Anonymous(schema) => Anonymous(Cow::Owned(schema.into_owned())),
+            Any => Any,
+            Unit => Unit,
+        }
+    }
+
+    // Whether the type is `Bytes`, or optionally so
+    fn is_binary(&self) -> bool {
+        match *self {
+            NativeType::Bytes => true,
+            NativeType::Option(ref native) => native.is_binary(),
+            _ => false,
         }
     }
 
-    fn render(&self, mut anon_count: u32, operation_id: &OperationId) -> (String, u32) {
+    fn contains_base64(&self) -> bool {
+        match *self {
+            NativeType::Base64 => true,
+            NativeType::Array(ref natives) => natives.iter().any(|native| native.contains_base64()),
+            NativeType::Option(ref native) | NativeType::Map(_, ref native) => native.contains_base64(),
+            _ => false,
+        }
+    }
+
+    fn is_array(&self) -> bool {
+        match *self {
+            NativeType::Array(_) => true,
+            _ => false,
+        }
+    }
+
+    fn render(&self, anon: &mut AnonNames) -> String {
+        self.render_with(&mut |schema| anon.name(schema))
+    }
+
+    /// Render the type as Rust source. Anonymous schemas have no name of their
+    /// own, so `anon` is called to name each one encountered.
+    pub fn render_with<F: FnMut(&Schema) -> String>(&self, anon: &mut F) -> String {
         use self::NativeType::*;
-        let res = match *self {
+        match *self {
             I32 => "i32".into(),
             I64 => "i64".into(),
             F32 => "f32".into(),
             F64 => "f64".into(),
             Bool => "bool".into(),
             String => "String".into(),
+            Bytes => "Vec<u8>".into(),
+            Base64 => "Base64".into(),
+            DateTime => "::chrono::DateTime<::chrono::Utc>".into(),
+            Date => "::chrono::NaiveDate".into(),
+            Uuid => "::uuid::Uuid".into(),
             Named(ref s) => s.clone(),
-            Array(ref natives) => {
-                let rendered_type = natives.first().unwrap().render(anon_count, operation_id);
-                anon_count = rendered_type.1;
-                format!("Vec<{}>", rendered_type.0)
-            }
-            Option(ref native) => {
-                let rendered_type = native.render(anon_count, operation_id);
-                anon_count = rendered_type.1;
-                format!("Option<{}>", rendered_type.0)
+            Array(ref natives) => format!("Vec<{}>", natives.first().unwrap().render_with(anon)),
+            Option(ref native) => format!("Option<{}>", native.render_with(anon)),
+            Map(map_type, ref native) => {
+                format!("{}<String, {}>", map_type.path(), native.render_with(anon))
             }
-            Anonymous(_) => {
-                anon_count += 1;
-                format!("{}AnonArg{}", operation_id.classcase(), anon_count - 1)
-            }
-        };
-        (res, anon_count)
+            Anonymous(ref schema) => anon(&**schema),
+            Any => "::serde_json::Value".into(),
+            Unit => "()".into(),
+        }
+    }
+}
+
+// The other subschema of a `oneOf` (or `anyOf`) of two, one of which is
+// `type: "null"`, as commonly used to make a schema nullable
+fn nullable_variant(schema: &Schema) -> Option<&Schema> {
+    use openapi3::objects::SimpleTypes::Null;
+    let subschemas = match schema.one_of.as_ref().or(schema.any_of.as_ref()) {
+        Some(subschemas) if subschemas.len() == 2 => subschemas,
+        _ => return None,
+    };
+    match (subschemas[0].type_ == [Null], subschemas[1].type_ == [Null]) {
+        (true, false) => Some(&subschemas[1]),
+        (false, true) => Some(&subschemas[0]),
+        _ => None,
+    }
+}
+
+/// Whether the schema places no constraints on the shape of its value, e.g. `{}`.
+/// (Boolean schemas such as `true` cannot be represented by openapi3's `Schema`)
+pub fn is_free_form(schema: &Schema) -> bool {
+    use openapi3::objects::SimpleTypes::Object;
+    schema.ref_.is_none()
+        && (schema.type_.is_empty() || schema.type_ == [Object])
+        && schema.properties.is_empty()
+        && schema.all_of.is_none()
+        && schema.one_of.is_none()
+        && schema.any_of.is_none()
+        && additional_properties(schema).map_or(true, |values| values.is_none())
+}
+
+/// The schema of the values of an object's additional properties, if they
+/// are constrained, i.e. `additionalProperties` is a non-empty schema
+pub fn additional_properties(schema: &Schema) -> Result<Option<Schema>> {
+    match schema.additional_properties {
+        Some(JsonValue::Object(ref values)) if !values.is_empty() => {
+            Ok(Some(::serde_json::from_value(JsonValue::Object(values.clone()))?))
+        }
+        _ => Ok(None),
+    }
+}
+
+// The values of a schema which is a pure map, with additional properties but
+// no fixed ones
+fn map_values(schema: &Schema) -> Result<Option<Schema>> {
+    use openapi3::objects::SimpleTypes::Object;
+    let object = schema.type_.is_empty() || schema.type_ == [Object];
+    if !object || !schema.properties.is_empty() || schema.all_of.is_some()
+        || schema.one_of.is_some() || schema.any_of.is_some()
+    {
+        return Ok(None);
     }
+    additional_properties(schema)
 }
 
+// An expression parsing a header or cookie value as a `Result<T, ()>`, where
+// `get` evaluates to the raw `Option<&str>`. Arrays are sent comma-separated
+fn param_build(get: &str, type_: &NativeType) -> String {
+    let parse_list = "v.split(',').map(|v| v.trim().parse().map_err(|_| ())).collect::<Result<_, _>>()";
+    match *type_ {
+        NativeType::Option(ref inner) if inner.is_array() => format!(
+            "match {} {{ Some(v) => {}.map(Some), None => Ok(None) }}",
+            get, parse_list
+        ),
+        NativeType::Option(_) => format!(
+            "match {} {{ Some(v) => v.parse().map(Some).map_err(|_| ()), None => Ok(None) }}",
+            get
+        ),
+        NativeType::Array(_) => format!("{}.ok_or(()).and_then(|v| {})", get, parse_list),
+        _ => format!("{}.ok_or(()).and_then(|v| v.parse().map_err(|_| ()))", get),
+    }
+}
 
-fn path_as_map(path: &Path) -> BTreeMap<Method, &Operation> {
-    use self::Method::*;
-    let mut map = BTreeMap::new();
-    if let Some(ref op) = path.get {
-        map.insert(Get, op);
+// A value for a parameter in a generated test: its example if it has one,
+// otherwise a placeholder of the right type
+fn example_value(arg: &Arg) -> String {
+    use self::NativeType::*;
+    match arg.example {
+        Some(&JsonValue::String(ref value)) => return value.clone(),
+        Some(value) => return value.to_string(),
+        None => {}
     }
-    if let Some(ref op) = path.post {
-        map.insert(Post, op);
+    match arg.type_ {
+        I32 | I64 => "1".into(),
+        F32 | F64 => "1.0".into(),
+        Bool => "true".into(),
+        _ => "example".into(),
     }
-    if let Some(ref op) = path.put {
-        map.insert(Put, op);
+}
+
+/// A Rust expression for a schema's `default` value, for those types whose
+/// values can be written as literals
+pub(crate) fn default_literal(type_: &NativeType, value: &JsonValue) -> Option<String> {
+    use self::NativeType::*;
+    match *type_ {
+        I32 | I64 => value.as_i64().map(|v| v.to_string()),
+        F32 | F64 => value.as_f64().map(|v| format!("{:?}", v)),
+        Bool => value.as_bool().map(|v| v.to_string()),
+        String => value.as_str().map(|v| format!("{:?}.into()", v)),
+        _ => None,
+    }
+}
+
+const FORM: &str = "application/x-www-form-urlencoded";
+
+// Whether a media type is JSON, e.g. `application/json` or `application/problem+json`
+fn is_json(mime: &str) -> bool {
+    mime == "application/json" || mime.ends_with("+json")
+}
+
+// The reference of an `allOf` which composes a single referenced schema
+// and adds no properties of its own
+fn single_ref_all_of(schema: &Schema) -> Option<&str> {
+    match schema.all_of {
+        Some(ref subschemas) if subschemas.len() == 1 && schema.properties.is_empty() => {
+            subschemas[0].ref_.as_ref().map(|ref_| ref_.as_str())
+        }
+        _ => None,
     }
-    if let Some(ref op) = path.patch {
-        map.insert(Patch, op);
+}
+
+// Format text as a doc comment, one `///` line per line of text
+pub(crate) fn doc_comment(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("/// {}\n", line).replace("/// \n", "///\n"))
+        .collect()
+}
+
+// Extract the type name from a reference, e.g. "#/components/schemas/Pet" -> "Pet"
+pub(crate) fn ref_name(ref_: &str) -> Result<&str> {
+    if !ref_.starts_with('#') {
+        bail!(
+            "Reference {} points into another file, load the spec from a file \
+             so that it can be resolved",
+            ref_
+        );
     }
-    if let Some(ref op) = path.delete {
-        map.insert(Delete, op);
+    match ref_.rfind("/") {
+        None => bail!("Reference {} is not valid path", ref_),
+        Some(loc) => Ok(ref_.split_at(loc + 1).1),
     }
-    map
+}
+
+
+// The operations of a path item in a fixed HTTP-verb order, independent of
+// how `Method` happens to be declared
+fn path_operations(path: &Path) -> Vec<(Method, &Operation)> {
+    use self::Method::*;
+    let operations = vec![
+        (Get, &path.get),
+        (Post, &path.post),
+        (Put, &path.put),
+        (Patch, &path.patch),
+        (Delete, &path.delete),
+        (Head, &path.head),
+        (Options, &path.options),
+    ];
+    operations
+        .into_iter()
+        .filter_map(|(method, op)| op.as_ref().map(|op| (method, op)))
+        .collect()
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -427,6 +2605,7 @@ enum RouteSegment<'a> {
     RouteArg(&'a str),
 }
 
+// The segments of an absolute route, after the leading slash
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Route<'a>(Vec<RouteSegment<'a>>);
 
@@ -438,8 +2617,24 @@ impl<'a> Route<'a> {
             !(section.contains('{') || section.contains('}'))
         }
 
+        let invalid = |reason: &str| ErrorKind::InvalidRoute(route.into(), reason.into());
+        if route.is_empty() {
+            bail!(invalid("it is empty"));
+        }
+        if !route.starts_with('/') {
+            bail!(invalid("it does not start with '/'"));
+        }
+        // A trailing slash is dropped, so "/pets/" is the same route as "/pets"
+        let path = route[1..].trim_right_matches('/');
+        if path.is_empty() {
+            return Ok(Route(Vec::new()));
+        }
+        if path.split('/').any(|segment| segment.is_empty()) {
+            bail!(invalid("it has an empty segment"));
+        }
+
         let re_route_arg = Regex::new(r"^\{(.+)\}$").unwrap();
-        let segments = route
+        let segments = path
             .split("/")
             .map(|segment| {
                 re_route_arg
@@ -459,14 +2654,37 @@ impl<'a> Route<'a> {
     }
 
     fn render(&self) -> String {
+        self.join(|section| match *section {
+            RouteSegment::Path(path) => path.into(),
+            RouteSegment::RouteArg(route_arg) => format!("<{}>", route_arg.to_snake_case()),
+        })
+    }
+
+    // The route as written in the spec
+    fn spec_path(&self) -> String {
+        self.join(|section| match *section {
+            RouteSegment::Path(path) => path.into(),
+            RouteSegment::RouteArg(route_arg) => format!("{{{}}}", route_arg),
+        })
+    }
+
+    // The route as a `format!` string, with route args as named arguments
+    fn client_path(&self) -> String {
+        self.join(|section| match *section {
+            RouteSegment::Path(path) => path.into(),
+            RouteSegment::RouteArg(route_arg) => format!("{{{}}}", route_arg.to_snake_case()),
+        })
+    }
+
+    // The rendered segments, each after a slash
+    fn join<F: Fn(&RouteSegment) -> String>(&self, render: F) -> String {
+        if self.0.is_empty() {
+            return "/".into();
+        }
         self.0
             .iter()
-            .map(|section| match *section {
-                RouteSegment::Path(path) => path.into(),
-                RouteSegment::RouteArg(route_arg) => format!("<{}>", route_arg.to_snake_case()),
-            })
-            .collect::<Vec<_>>()
-            .join("/")
+            .map(|section| format!("/{}", render(section)))
+            .collect()
     }
 
     fn route_args(&self) -> Vec<String> {
@@ -481,6 +2699,9 @@ impl<'a> Route<'a> {
 }
 
 
+// The arguments of a route besides its parameters
+const ADDED_ARG_NAMES: &[&str] = &["query", "headers", "cookies", "accept", "limits", "path", "request"];
+
 fn validate_route_args(route: &Route, args: &Args) -> Result<()> {
     let mut route_args = route.route_args();
     let mut path_args: Vec<&str> = args.iter()
@@ -495,6 +2716,26 @@ fn validate_route_args(route: &Route, args: &Args) -> Result<()> {
     if !(route_args == path_args) {
         bail!("Path args mismatch - expected {:?}, found {:?}", route_args, path_args)
     }
+    // Names differing only in case or separators become the same argument
+    for (ix, arg) in args.iter().enumerate() {
+        if let Some(other) = args[..ix].iter().find(|other| other.name == arg.name) {
+            bail!(
+                "Parameters '{}' and '{}' both become the argument `{}`",
+                other.spec_name,
+                arg.spec_name,
+                arg.name
+            )
+        }
+    }
+    // A route only matches when every segment is present
+    for arg in args.iter().filter(|arg| arg.location == Location::Path) {
+        if arg.type_.is_option() {
+            bail!(
+                "Path parameter '{}' must be declared `required: true`",
+                arg.spec_name
+            )
+        }
+    }
     Ok(())
 }
 
@@ -508,15 +2749,96 @@ mod tests {
     fn test_parse_route_args() {
         use self::RouteSegment::*;
         let res = Route::from_str("/pets/{petId}/name/{petName}").unwrap();
-        let expect = vec![Path(""), Path("pets"), RouteArg("petId"), Path("name"),
-                          RouteArg("petName")];
+        let expect = vec![Path("pets"), RouteArg("petId"), Path("name"), RouteArg("petName")];
         assert_eq!(res.0, expect);
+        assert_eq!(res.render(), "/pets/<pet_id>/name/<pet_name>");
 
         assert!(Route::from_str("/pets/{petId}/name/x{bogus}x").is_err());
         assert!(Route::from_str("/pets/{petId}/name/x{bogus}").is_err());
         assert!(Route::from_str("/pets/{petId}/name/{bogus}x").is_err());
     }
 
+    #[test]
+    fn test_route_validation() {
+        let err = Route::from_str("").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid route '': it is empty");
+        let err = Route::from_str("pets").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid route 'pets': it does not start with '/'");
+        assert!(Route::from_str("/pets//toys").is_err());
+
+        let route = Route::from_str("/pets/").unwrap();
+        assert_eq!(route, Route::from_str("/pets").unwrap());
+        assert_eq!(route.render(), "/pets");
+        let root = Route::from_str("/").unwrap();
+        assert!(root.0.is_empty());
+        assert_eq!(root.render(), "/");
+        assert_eq!(root.client_path(), "/");
+    }
+
+    #[test]
+    fn test_optional_path_param() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: optional
+  version: 1
+paths:
+  /pets/{petId}:
+    get:
+      operationId: showPet
+      parameters:
+        - name: petId
+          in: path
+          required: false
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let (entrypoints, errors) = collect_entrypoints(&api, TypeOptions::default());
+        assert!(entrypoints.is_empty());
+        assert_eq!(
+            errors[0].to_string(),
+            "Path parameter 'petId' must be declared `required: true`"
+        );
+    }
+
+    #[test]
+    fn test_serialize_entrypoints() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let show = &entrypoints[2];
+        assert_eq!(show.path(), "/pets/{petId}");
+        assert_eq!(show.route(), "/pets/<pet_id>");
+        assert_eq!(show.arg_types(), vec![("pet_id", "String".to_string())]);
+        assert_eq!(show.args[0].spec_name(), "petId");
+        assert_eq!(show.args[0].location(), Location::Path);
+
+        let json = serde_json::to_value(&entrypoints).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+        assert_eq!(
+            json[0],
+            json!({
+                "operation_id": "list_pets",
+                "method": "get",
+                "path": "/pets",
+                "base_path": "/v1",
+                "summary": "List all pets",
+                "description": null,
+                "args": [
+                    {"name": "limit", "spec_name": "limit", "location": "query", "type": "Option<i32>"}
+                ],
+                "result_type": "Pets",
+                "responses": ["200", "default"],
+                "deprecated": false
+            })
+        );
+        assert_eq!(json[1]["result_type"], JsonValue::Null);
+    }
+
     #[test]
     fn test_extract_entrypoints() {
         // TODO test contents of entrypoints
@@ -526,6 +2848,134 @@ mod tests {
         assert_eq!(entrypoints.len(), 3);
     }
 
+    #[test]
+    fn test_collect_entrypoint_errors() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: broken
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      responses:
+        200:
+          description: Some pets
+    post:
+      operationId: create-pet
+      responses:
+        201:
+          description: Invalid operationId
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let (entrypoints, errors) = collect_entrypoints(&api, TypeOptions::default());
+        assert_eq!(entrypoints.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("Invalid operationId char"));
+        assert_eq!(extract_entrypoints(&api).len(), 1);
+    }
+
+    #[test]
+    fn test_extensions() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: extensions
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      x-foo: bar
+      x-rate-limit: 100
+      responses:
+        200:
+          description: Some pets
+    post:
+      operationId: createPet
+      responses:
+        201:
+          description: Created
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let args = entrypoints[0].build_template_args();
+        assert_eq!(args["extensions"]["x-foo"], "bar");
+        assert_eq!(args["extensions"]["x-rate-limit"], 100);
+        assert_eq!(entrypoints[1].build_template_args()["extensions"], json!({}));
+    }
+
+    #[test]
+    fn test_missing_operation_id() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: anonymous
+  version: 1
+paths:
+  /pets/{petId}:
+    get:
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+    delete:
+      responses:
+        204:
+          description: Deleted
+  /zoo:
+    get:
+      operationId: delete_pets_pet_id
+      responses:
+        200:
+          description: Taken
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let (entrypoints, errors) = collect_entrypoints(&api, TypeOptions::default());
+        assert!(errors.is_empty());
+        let ids: Vec<&str> = entrypoints.iter().map(|e| e.operation_id.0.as_str()).collect();
+        // Explicit ids win over made-up ones, wherever they appear
+        assert_eq!(ids, vec!["get_pets_pet_id", "delete_pets_pet_id_2", "delete_pets_pet_id"]);
+        let args = entrypoints[0].build_template_args();
+        assert_eq!(args["function"], "get_pets_pet_id");
+    }
+
+    #[test]
+    fn test_head_and_options() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: methods
+  version: 1
+paths:
+  /pets:
+    head:
+      operationId: checkPets
+      responses:
+        200:
+          description: Pets exist
+    options:
+      operationId: petsPreflight
+      responses:
+        204:
+          description: CORS preflight
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        assert_eq!(entrypoints.len(), 2);
+        assert_eq!(entrypoints[0].method, Method::Head);
+        assert_eq!(entrypoints[1].method, Method::Options);
+        let tmpl_args = entrypoints[1].build_template_args();
+        assert_eq!(tmpl_args["method"], "options");
+        assert_eq!(tmpl_args["function"], "pets_preflight");
+    }
+
     #[test]
     fn test_atom_schemafy() {
         let schema = r#"{"type": "integer"}"#;
@@ -558,7 +3008,168 @@ mod tests {
             }
         }"#;
         let expect_schema: Schema = serde_json::from_str(expectstr).unwrap();
-        assert_eq!(native, NativeType::Anonymous(Box::new(expect_schema)));
+        assert_eq!(native, NativeType::Anonymous(Cow::Owned(expect_schema)));
+    }
+
+    #[test]
+    fn test_numeric_formats() {
+        fn render(json: JsonValue) -> String {
+            let schema: Schema = serde_json::from_value(json).unwrap();
+            NativeType::from_json_schema(&schema, true)
+                .unwrap()
+                .render_with(&mut |_| unreachable!())
+        }
+        assert_eq!(render(json!({"type": "integer", "format": "int32"})), "i32");
+        assert_eq!(render(json!({"type": "integer", "format": "int64"})), "i64");
+        assert_eq!(render(json!({"type": "integer", "format": "int8"})), "i64");
+        assert_eq!(render(json!({"type": "integer"})), "i64");
+        assert_eq!(render(json!({"type": "number", "format": "float"})), "f32");
+        assert_eq!(render(json!({"type": "number", "format": "double"})), "f64");
+        assert_eq!(render(json!({"type": "number"})), "f64");
+    }
+
+    #[test]
+    fn test_nullable() {
+        let schema: Schema = serde_json::from_value(json!({"type": "string"})).unwrap();
+        let nullable: Schema =
+            serde_json::from_value(json!({"type": "string", "nullable": true})).unwrap();
+        let render = |schema: &Schema, required| {
+            NativeType::from_json_schema(schema, required)
+                .unwrap()
+                .render_with(&mut |_| unreachable!())
+        };
+        assert_eq!(render(&schema, true), "String");
+        assert_eq!(render(&schema, false), "Option<String>");
+        assert_eq!(render(&nullable, true), "Option<String>");
+        assert_eq!(render(&nullable, false), "Option<Option<String>>");
+    }
+
+    #[test]
+    fn test_multiple_types() {
+        let schema: Schema = serde_json::from_value(json!({"type": ["string", "integer"]})).unwrap();
+        let native = NativeType::from_json_schema(&schema, true).unwrap();
+        assert_eq!(native.render_with(&mut |_| "Anon".into()), "Anon");
+        let schema: Schema =
+            serde_json::from_value(json!({"type": ["string", "integer", "null"]})).unwrap();
+        let native = NativeType::from_json_schema(&schema, true).unwrap();
+        assert_eq!(native.render_with(&mut |_| "Anon".into()), "Option<Anon>");
+        assert_eq!(schema_type("Id", &schema).kind, SchemaKind::Enum);
+    }
+
+    #[test]
+    fn test_null_type() {
+        let render = |value: JsonValue, required| {
+            let schema: Schema = serde_json::from_value(value).unwrap();
+            NativeType::from_json_schema(&schema, required)
+                .unwrap()
+                .render_with(&mut |_| unreachable!())
+        };
+        assert_eq!(render(json!({"type": ["string", "null"]}), true), "Option<String>");
+        assert_eq!(render(json!({"type": ["null", "integer"]}), false), "Option<Option<i64>>");
+        assert_eq!(render(json!({"type": "null"}), true), "()");
+        let one_of = json!({"oneOf": [{"$ref": "#/components/schemas/Pet"}, {"type": "null"}]});
+        assert_eq!(render(one_of, true), "Option<Pet>");
+    }
+
+    #[test]
+    fn test_map_schema() {
+        let map: Schema = serde_json::from_value(json!({
+            "type": "object",
+            "additionalProperties": {"type": "integer"}
+        })).unwrap();
+        let native = NativeType::from_json_schema(&map, true).unwrap();
+        assert_eq!(native, NativeType::Map(MapType::BTreeMap, Box::new(NativeType::I64)));
+        assert_eq!(
+            native.render_with(&mut |_| unreachable!()),
+            "::std::collections::BTreeMap<String, i64>"
+        );
+        let options = TypeOptions {
+            map_type: MapType::HashMap,
+            ..TypeOptions::default()
+        };
+        let native = NativeType::from_json_schema_with(&map, true, options).unwrap();
+        assert_eq!(
+            native.render_with(&mut |_| unreachable!()),
+            "::std::collections::HashMap<String, i64>"
+        );
+
+        let free_form: Schema =
+            serde_json::from_value(json!({"type": "object", "additionalProperties": {}})).unwrap();
+        let native = NativeType::from_json_schema(&free_form, true).unwrap();
+        assert_eq!(native, NativeType::Any);
+    }
+
+    #[test]
+    fn test_string_formats() {
+        let render = |format: &str| {
+            let schema: Schema =
+                serde_json::from_value(json!({"type": "string", "format": format})).unwrap();
+            NativeType::from_json_schema(&schema, true)
+                .unwrap()
+                .render_with(&mut |_| unreachable!())
+        };
+        assert_eq!(render("binary"), "Vec<u8>");
+        assert_eq!(render("byte"), "Base64");
+        assert_eq!(render("password"), "String");
+    }
+
+    #[test]
+    fn test_date_formats() {
+        let date_time: Schema =
+            serde_json::from_value(json!({"type": "string", "format": "date-time"})).unwrap();
+        let date: Schema =
+            serde_json::from_value(json!({"type": "string", "format": "date"})).unwrap();
+        let render = |schema: &Schema, chrono: bool| {
+            let formats = Formats {
+                chrono,
+                ..Formats::default()
+            };
+            let options = TypeOptions {
+                formats,
+                ..TypeOptions::default()
+            };
+            NativeType::from_json_schema_with(schema, true, options)
+                .unwrap()
+                .render_with(&mut |_| unreachable!())
+        };
+        assert_eq!(render(&date_time, false), "String");
+        assert_eq!(render(&date_time, true), "::chrono::DateTime<::chrono::Utc>");
+        assert_eq!(render(&date, true), "::chrono::NaiveDate");
+    }
+
+    #[test]
+    fn test_uuid_format() {
+        let schema: Schema =
+            serde_json::from_value(json!({"type": "string", "format": "uuid"})).unwrap();
+        let render = |uuid: bool| {
+            let formats = Formats {
+                uuid,
+                ..Formats::default()
+            };
+            let options = TypeOptions {
+                formats,
+                ..TypeOptions::default()
+            };
+            NativeType::from_json_schema_with(&schema, true, options)
+                .unwrap()
+                .render_with(&mut |_| unreachable!())
+        };
+        assert_eq!(render(false), "String");
+        assert_eq!(render(true), "::uuid::Uuid");
+    }
+
+    #[test]
+    fn test_not_schema_warns() {
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "string",
+            "not": {"enum": ["forbidden"]}
+        })).unwrap();
+        ::take_warnings();
+        let native = NativeType::from_json_schema(&schema, true).unwrap();
+        assert_eq!(native, NativeType::String);
+        let warnings = ::take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("`not`"));
     }
 
     #[test]
@@ -578,6 +3189,47 @@ mod tests {
         assert_eq!(native, expect);
     }
 
+    #[test]
+    fn test_single_schema_items() {
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {"type": "integer"}
+        })).unwrap();
+        let native = NativeType::from_json_schema(&schema, true).unwrap();
+        assert_eq!(native, NativeType::Array(vec![NativeType::I64]));
+        let native = NativeType::from_json_schema(&schema, false).unwrap();
+        assert_eq!(native.render_with(&mut |_| unreachable!()), "Option<Vec<i64>>");
+    }
+
+    #[test]
+    fn test_nested_array_items() {
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {"type": "array", "items": {"type": "integer"}}
+        })).unwrap();
+        let native = NativeType::from_json_schema(&schema, true).unwrap();
+        assert_eq!(native, NativeType::Array(vec![NativeType::Array(vec![NativeType::I64])]));
+        assert_eq!(native.render_with(&mut |_| unreachable!()), "Vec<Vec<i64>>");
+
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": [{"type": "integer"}, {"type": "string"}]
+        })).unwrap();
+        let err = NativeType::from_json_schema(&schema, true).unwrap_err();
+        assert!(err.to_string().contains("tuple items are not supported"));
+    }
+
+    #[test]
+    fn test_free_form_schema() {
+        let schema: Schema = serde_json::from_str("{}").unwrap();
+        let native = NativeType::from_json_schema(&schema, true).unwrap();
+        assert_eq!(native, NativeType::Any);
+        assert_eq!(native.render_with(&mut |_| unreachable!()), "::serde_json::Value");
+        let schema: Schema = serde_json::from_str(r#"{"type": "object"}"#).unwrap();
+        let native = NativeType::from_json_schema(&schema, false).unwrap();
+        assert_eq!(native.render_with(&mut |_| unreachable!()), "Option<::serde_json::Value>");
+    }
+
     #[test]
     fn test_entrypoint_render() {
 
@@ -591,23 +3243,22 @@ mod tests {
             let args = vec![
                 Arg::new(
                     "arg_one".into(),
-                    NativeType::Anonymous(Box::new(inner_schema.clone())),
+                    NativeType::Anonymous(Cow::Owned(inner_schema.clone())),
                     Location::Path),
                 Arg::new(
                     "arg_two".into(),
-                    NativeType::Anonymous(Box::new(inner_schema.clone())),
+                    NativeType::Anonymous(Cow::Owned(inner_schema.clone())),
                     Location::Path),
                 Arg::new(
-                    // TODO this should fail with duplicate arg
-                    "ArgOne".into(),
-                    NativeType::Anonymous(Box::new(inner_schema.clone())),
+                    "argThree".into(),
+                    NativeType::Anonymous(Cow::Owned(inner_schema.clone())),
                     Location::Query),
             ].into();
             let responses = vec![
                 Response::new(
                     "200".into(),
                     None,
-                    None)
+                    Vec::new())
             ];
             Entrypoint::new(
                 Route::from_str(routestr).unwrap(),
@@ -629,10 +3280,831 @@ mod tests {
         assert_eq!(entrypoint.operation_id.0, "my_operation_id");
     }
 
+    #[test]
+    fn test_stub_receives_unwrapped_body() {
+        use handlebars::{self, Handlebars};
+        let args = vec![
+            Arg::new("petId", NativeType::I64, Location::Path),
+            Arg::new("body", NativeType::Named("Pet".into()), Location::Body),
+        ].into();
+        let entrypoint = Entrypoint::new(
+            Route::from_str("/pets/{petId}").unwrap(),
+            Method::Put,
+            args,
+            vec![Response::new("200", Some(NativeType::Named("Pet".into())), Vec::new())],
+            OperationId::new("updatePet").unwrap(),
+            None,
+            None,
+        ).unwrap();
+
+        let tmpl_args = entrypoint.build_template_args();
+        assert_eq!(tmpl_args["route_args"][1]["type"], "Json<Pet>");
+        assert_eq!(tmpl_args["call_args"][1], "body.into_inner()");
+
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(handlebars::no_escape);
+        handlebars.register_template_string("stub", ::templates::STUB_TEMPLATE).unwrap();
+        let mut out = Vec::new();
+        ::generate_function_stubs(&mut out, &handlebars, &vec![entrypoint], &::Config::default())
+            .unwrap();
+        let stub = String::from_utf8(out).unwrap();
+        assert!(stub.contains("pub fn update_pet(pet_id: i64,body: Pet,)"));
+    }
+
+    #[test]
+    fn test_error_type() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: errors
+  version: 1
+paths:
+  /pets/{petId}:
+    get:
+      operationId: showPetById
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+        404:
+          description: No such pet
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/NotFound'
+        default:
+          description: Unexpected error
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Error'
+    delete:
+      operationId: deletePet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        204:
+          description: Deleted
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let show = entrypoints
+            .iter()
+            .find(|e| e.operation_id == OperationId::new("showPetById").unwrap())
+            .unwrap()
+            .build_template_args();
+        assert_eq!(show["error_type"], "NotFound");
+        assert_eq!(show["error"]["status"], 404);
+        let delete = entrypoints
+            .iter()
+            .find(|e| e.operation_id == OperationId::new("deletePet").unwrap())
+            .unwrap()
+            .build_template_args();
+        assert_eq!(delete["error_type"], "()");
+        assert!(delete["error"].is_null());
+
+        let api = OpenApi::from_string(include_str!("../example_apis/petstore.yaml")).unwrap();
+        let list_pets = &extract_entrypoints(&api)[0].build_template_args();
+        assert_eq!(list_pets["error_type"], "Error");
+        assert_eq!(list_pets["error"]["status"], 500);
+    }
+
+    #[test]
+    fn test_request_body() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: bodies
+  version: 1
+paths:
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/xml:
+            schema:
+              type: string
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Pet'
+      responses:
+        201:
+          description: Created
+    put:
+      operationId: replacePet
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Pet'
+      responses:
+        200:
+          description: Replaced
+    patch:
+      operationId: patchPet
+      requestBody:
+        content:
+          application/json: {}
+      responses:
+        200:
+          description: Patched
+components:
+  schemas:
+    Pet:
+      properties:
+        id:
+          type: integer
+"#;
+        let spec = OpenApi::from_string(yaml).unwrap();
+        let path = &spec.paths["/pets"];
+        let components = spec.components.as_ref();
+
+        let create = path.post.as_ref().unwrap();
+        let entrypoint = Entrypoint::build("/pets", Method::Post, create, components).unwrap();
+        assert_eq!(entrypoint.args.len(), 1);
+        assert_eq!(entrypoint.args[0].location, Location::Body);
+        assert_eq!(entrypoint.args[0].type_, NativeType::Named("Pet".into()));
+        let tmpl_args = entrypoint.build_template_args();
+        assert_eq!(tmpl_args["data"], "body");
+        assert_eq!(tmpl_args["route_args"][0]["type"], "Json<Pet>");
+
+        let replace = path.put.as_ref().unwrap();
+        let entrypoint = Entrypoint::build("/pets", Method::Put, replace, components).unwrap();
+        assert_eq!(
+            entrypoint.args[0].type_,
+            NativeType::Option(Box::new(NativeType::Named("Pet".into())))
+        );
+
+        let patch = path.patch.as_ref().unwrap();
+        let err = Entrypoint::build("/pets", Method::Patch, patch, components).unwrap_err();
+        assert!(err.to_string().contains("Request body has no schema"));
+    }
+
+    #[test]
+    fn test_allow_empty_value() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: empty
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: pageSize
+          in: query
+          required: true
+          allowEmptyValue: true
+          schema:
+            type: integer
+        - name: tag
+          in: query
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: Some pets
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let tmpl_args = entrypoints[0].build_template_args();
+        let query = &tmpl_args["query"];
+        assert_eq!(query["name"], "ListPetsQuery");
+        assert_eq!(query["fields"][0]["name"], "page_size");
+        assert_eq!(query["fields"][0]["type"], "Option<i64>");
+        assert_eq!(query["fields"][0]["rename"], "pageSize");
+        assert_eq!(query["fields"][1]["type"], "String");
+        assert_eq!(tmpl_args["call_args"][0], "query.page_size");
+    }
+
+    #[test]
+    fn test_array_query_params() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: arrays
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: tags
+          in: query
+          required: true
+          schema:
+            type: array
+            items:
+              type: string
+        - name: ownerIds
+          in: query
+          schema:
+            type: array
+            items:
+              type: integer
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        200:
+          description: Some pets
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let tmpl_args = entrypoints[0].build_template_args();
+        let query = &tmpl_args["query"];
+        assert_eq!(query["repeated"], true);
+        let fields = query["fields"].as_array().unwrap();
+        assert_eq!(fields[0]["name"], "tags");
+        assert_eq!(fields[0]["type"], "Vec<String>");
+        assert_eq!(fields[0]["array"], true);
+        assert_eq!(fields[1]["name"], "owner_ids");
+        assert_eq!(fields[1]["key"], "ownerIds");
+        assert!(fields[1]["rename"].is_null());
+        assert_eq!(fields[1]["type"], "Option<Vec<i64>>");
+        assert_eq!(fields[1]["build"], "if owner_ids.is_empty() { None } else { Some(owner_ids) }");
+        assert_eq!(fields[2]["array"], false);
+        assert_eq!(fields[2]["build"], "limit");
+
+        // `?tags=a&tags=b` is gathered by a hand-written FromForm impl
+        let gen = ::test_util::render_endpoints(yaml, &::Config::default());
+        assert!(!gen.contains("#[derive(FromForm)]"));
+        assert!(gen.contains("impl<'f> rocket::request::FromForm<'f> for ListPetsQuery {"));
+        assert!(gen.contains("\"tags\" => {"));
+        assert!(gen.contains("tags.push(value);"));
+    }
+
+    #[test]
+    fn test_delimited_query_params() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: arrays
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: tags
+          in: query
+          style: pipeDelimited
+          schema:
+            type: array
+            items:
+              type: string
+        - name: ids
+          in: query
+          style: form
+          explode: false
+          schema:
+            type: array
+            items:
+              type: integer
+        - name: colors
+          in: query
+          style: pipeDelimited
+          explode: true
+          schema:
+            type: array
+            items:
+              type: string
+      responses:
+        200:
+          description: Some pets
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let tmpl_args = entrypoints[0].build_template_args();
+        let fields = tmpl_args["query"]["fields"].as_array().unwrap();
+        assert_eq!(fields[0]["delimiter"], "'|'");
+        assert_eq!(fields[1]["delimiter"], "','");
+        assert!(fields[2]["delimiter"].is_null());
+
+        // `?tags=a|b` is split into its values
+        let gen = ::test_util::render_endpoints(yaml, &::Config::default());
+        assert!(gen.contains(
+            "\"tags\" => {\n                    \
+             // Each value is decoded once split, so encoded delimiters are kept\n                    \
+             for value in value.as_str().split('|') {\n                        \
+             let value = rocket::request::FromFormValue::from_form_value(value.into())"
+        ));
+        assert!(gen.contains("for value in value.as_str().split(',') {"));
+        assert!(!gen.contains("url_decode"));
+        assert!(gen.contains("colors.push(value);"));
+    }
+
+    #[test]
+    fn test_query_defaults() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: defaults
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+            default: 20
+        - name: order
+          in: query
+          schema:
+            type: string
+            default: asc
+      responses:
+        200:
+          description: Some pets
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let tmpl_args = entrypoints[0].build_template_args();
+        // The query string may still omit it
+        assert_eq!(tmpl_args["query"]["fields"][0]["type"], "Option<i64>");
+        assert_eq!(tmpl_args["args"][0]["type"], "i64");
+        assert_eq!(tmpl_args["call_args"][0], "query.limit.unwrap_or(20)");
+        assert_eq!(tmpl_args["args"][1]["type"], "String");
+        assert_eq!(tmpl_args["call_args"][1], "query.order.unwrap_or(\"asc\".into())");
+    }
+
+    #[test]
+    fn test_header_params() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: headers
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: Authorization
+          in: header
+          required: true
+          schema:
+            type: string
+        - name: X-Request-Id
+          in: header
+          schema:
+            type: integer
+      responses:
+        200:
+          description: Some pets
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let tmpl_args = entrypoints[0].build_template_args();
+        let headers = &tmpl_args["headers"];
+        assert_eq!(headers["name"], "ListPetsHeaders");
+        let fields = headers["fields"].as_array().unwrap();
+        assert_eq!(fields[0]["name"], "authorization");
+        assert_eq!(fields[0]["type"], "String");
+        assert_eq!(
+            fields[0]["build"],
+            "headers.get_one(\"Authorization\").ok_or(()).and_then(|v| v.parse().map_err(|_| ()))"
+        );
+        assert_eq!(fields[1]["name"], "x_request_id");
+        assert_eq!(fields[1]["type"], "Option<i64>");
+        assert!(fields[1]["build"].as_str().unwrap().contains("headers.get_one(\"X-Request-Id\")"));
+        assert_eq!(tmpl_args["route_args"][0]["type"], "ListPetsHeaders");
+        assert_eq!(tmpl_args["call_args"][0], "headers.authorization");
+        assert_eq!(tmpl_args["call_args"][1], "headers.x_request_id");
+        // The stub receives the headers as plain arguments
+        assert_eq!(tmpl_args["args"][0]["type"], "String");
+
+        let gen = ::test_util::render_endpoints(yaml, &::Config::default());
+        assert!(gen.contains("pub struct ListPetsHeaders {"));
+        assert!(gen.contains("rocket::request::FromRequest<'a, 'r> for ListPetsHeaders {"));
+        assert!(gen.contains("fn _list_pets(headers: ListPetsHeaders,)"));
+    }
+
+    #[test]
+    fn test_test_args() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: examples
+  version: 1
+paths:
+  /pets/{petId}:
+    put:
+      operationId: updatePet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: integer
+            example: 42
+        - name: dryRun
+          in: query
+          required: true
+          schema:
+            type: boolean
+        - name: X-Request-Id
+          in: header
+          required: true
+          schema:
+            type: string
+            example: abc
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              type: object
+              properties:
+                name:
+                  type: string
+              example:
+                name: Rex
+      responses:
+        204:
+          description: Updated
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let mut tmpl_args = entrypoints[0].build_template_args();
+        entrypoints[0].add_test_args(&mut tmpl_args);
+        let test = &tmpl_args["test"];
+        assert_eq!(test["path"], "\"/pets/42?dryRun=true\"");
+        assert_eq!(test["method"], "PUT");
+        assert_eq!(test["headers"][0]["name"], "\"X-Request-Id\"");
+        assert_eq!(test["headers"][0]["value"], "\"abc\"");
+        assert_eq!(test["body"], r#""{\"name\":\"Rex\"}""#);
+    }
+
+    #[test]
+    fn test_cookie_params() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: cookies
+  version: 1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: session_id
+          in: cookie
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: Some pets
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let tmpl_args = entrypoints[0].build_template_args();
+        let cookies = &tmpl_args["cookies"];
+        assert_eq!(cookies["name"], "ListPetsCookies");
+        assert_eq!(
+            cookies["fields"][0]["build"],
+            "cookies.get(\"session_id\").map(|c| c.value()).ok_or(()).and_then(|v| v.parse().map_err(|_| ()))"
+        );
+        assert_eq!(tmpl_args["route_args"][0]["type"], "ListPetsCookies");
+        assert_eq!(tmpl_args["call_args"][0], "cookies.session_id");
+        assert_eq!(tmpl_args["args"][0]["type"], "String");
+
+        let gen = ::test_util::render_endpoints(yaml, &::Config::default());
+        assert!(gen.contains("pub struct ListPetsCookies {"));
+        assert!(gen.contains("let cookies = request.cookies();"));
+        assert!(gen.contains("fn _list_pets(cookies: ListPetsCookies,)"));
+        assert!(!gen.contains("session_id: String,)"));
+    }
+
+    #[test]
+    fn test_query_field_documentation() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let list_pets = entrypoints
+            .iter()
+            .find(|e| e.operation_id.0 == "list_pets")
+            .unwrap();
+        let query = &list_pets.build_template_args()["query"];
+        assert_eq!(
+            query["fields"][0]["documentation"],
+            "/// How many items to return at one time (max 100)\n"
+        );
+
+        let undocumented = Arg::new("limit", NativeType::I64, Location::Query);
+        let entrypoint = Entrypoint::new(
+            Route::from_str("/pets").unwrap(),
+            Method::Get,
+            vec![undocumented].into(),
+            Vec::new(),
+            OperationId::new("listPets").unwrap(),
+            None,
+            None,
+        ).unwrap();
+        let query = &entrypoint.build_template_args()["query"];
+        assert!(query["fields"][0]["documentation"].is_null());
+    }
+
+    #[test]
+    fn test_parameter_documentation() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  version: 1.0.0
+  title: Pets
+paths:
+  /pets/{petId}:
+    get:
+      summary: Info for a specific pet
+      operationId: showPetById
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          description: The pet's unique id
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        assert_eq!(
+            entrypoints[0].build_template_args()["documentation"],
+            "/// Info for a specific pet\n///\n/// # Arguments\n///\n/// * `pet_id`: The pet's unique id\n"
+        );
+
+        let files = ::generate_sources_to_map(&api, &::Config::default()).unwrap();
+        assert!(files["stub.rs"].contains(
+            "/// * `pet_id`: The pet's unique id\npub fn show_pet_by_id("
+        ));
+    }
+
+    #[test]
+    fn test_swagger_entrypoint_path() {
+        let entrypoint = Entrypoint::swagger_entrypoint("/openapi.json").unwrap();
+        assert_eq!(entrypoint.build_template_args()["route"], "/openapi.json");
+        assert!(Entrypoint::swagger_entrypoint("/{bad").is_err());
+    }
+
     #[test]
     fn test_operation_id_validity() {
         assert!(OperationId::new("thisIS_ invalid").is_err());
+        assert!(OperationId::new("").is_err());
         let opid = OperationId::new("thisIS_valid").unwrap();
         assert_eq!(opid.0, "this_is_valid");
+        assert_eq!(OperationId::new("123go").unwrap().0, "op_123go");
+        assert_eq!(OperationId::new("match").unwrap().0, "op_match");
+        assert_eq!(OperationId::new("getPet2").unwrap().0, "get_pet2");
+    }
+
+    #[test]
+    fn test_duplicate_operation_ids() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: duplicates
+  version: 1
+paths:
+  /things:
+    get:
+      operationId: getThing
+      responses:
+        200:
+          description: A thing
+  /other-things:
+    get:
+      operationId: getThing
+      responses:
+        200:
+          description: Another thing
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let (entrypoints, errors) = collect_entrypoints(&api, TypeOptions::default());
+        assert_eq!(entrypoints.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("Duplicate operationId 'get_thing'"));
+    }
+
+    #[test]
+    fn test_colliding_names() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: collisions
+  version: 1
+paths:
+  /pets/{petId}:
+    get:
+      operationId: getPet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+        - name: pet_id
+          in: query
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+    put:
+      operationId: get_pet
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        200:
+          description: A pet
+components:
+  schemas:
+    pet:
+      type: string
+    Pet:
+      type: string
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let (entrypoints, errors) = collect_entrypoints(&api, TypeOptions::default());
+        assert_eq!(entrypoints.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string()
+            .contains("Parameters 'petId' and 'pet_id' both become the argument `pet_id`"));
+        assert!(check_type_names(&api).unwrap_err().to_string()
+            .contains("Schemas 'Pet' and 'pet' both become the type `Pet`"));
+
+        let yaml = yaml.replace("        - name: pet_id\n", "        - name: name\n");
+        let api = OpenApi::from_string(&yaml).unwrap();
+        let (entrypoints, errors) = collect_entrypoints(&api, TypeOptions::default());
+        assert_eq!(entrypoints.len(), 1);
+        assert!(errors[0].to_string().contains(
+            "Duplicate operationId 'get_pet' (Put /pets/{petId}), \
+             as 'getPet' and 'get_pet' both become `get_pet`"
+        ));
+
+        let names = name_map(&api, &entrypoints);
+        assert_eq!(names.operations["getPet"], "get_pet");
+        assert_eq!(names.args["get_pet"]["petId"], "pet_id");
+        assert_eq!(names.args["get_pet"]["name"], "name");
+        assert_eq!(names.types["pet"], "Pet");
+    }
+
+    #[test]
+    fn test_type_report() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let api = OpenApi::from_string(yaml).unwrap();
+        let report = type_report(&api);
+        let pet = report.0.iter().find(|entry| entry.schema == "Pet").unwrap();
+        assert_eq!(pet.kind, SchemaKind::Struct);
+        assert_eq!(pet.rust_type, Some("Pet".into()));
+        let pets = report.0.iter().find(|entry| entry.schema == "Pets").unwrap();
+        assert_eq!(pets.kind, SchemaKind::Alias);
+        assert_eq!(pets.rust_type, Some("Vec<Pet>".into()));
+        let text = report.to_string();
+        assert!(text.contains("Pet: Struct Pet\n"));
+        assert!(text.contains("Pets: Alias Vec<Pet>\n"));
+
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Anything": {"type": "object"},
+            "Rows": {"type": "array", "items": {"properties": {"id": {"type": "integer"}}}},
+            "Bare": {"type": "array"}
+        })).unwrap();
+        let types: Vec<SchemaType> = schemas
+            .iter()
+            .map(|(name, schema)| schema_type(name, schema))
+            .collect();
+        assert_eq!(types[0].kind, SchemaKind::Opaque);
+        assert_eq!(types[0].rust_type, Some("::serde_json::Value".into()));
+        assert_eq!(types[1].kind, SchemaKind::Unsupported);
+        assert!(types[1].error.is_some());
+        assert_eq!(types[2].kind, SchemaKind::Opaque);
+        assert_eq!(types[2].rust_type, Some("Vec<::serde_json::Value>".into()));
+    }
+
+    #[test]
+    fn test_anonymous_arg_and_result_names() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: anon
+  version: 1
+paths:
+  /things:
+    post:
+      operationId: createThing
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              properties:
+                name:
+                  type: string
+      responses:
+        201:
+          description: Created
+          content:
+            application/json:
+              schema:
+                properties:
+                  id:
+                    type: integer
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let entrypoints = extract_entrypoints(&api);
+        let entrypoint = &entrypoints[0];
+        let args = entrypoint.build_template_args();
+        assert_eq!(args["args"][0]["type"], "CreateThingAnonArg1");
+        assert_eq!(args["result_type"], "CreateThingAnonArg2");
+        let names: Vec<String> = entrypoint
+            .anonymous_schemas()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["CreateThingAnonArg1", "CreateThingAnonArg2"]);
+        // Rendering again names them the same way
+        assert_eq!(entrypoint.build_template_args(), args);
+    }
+
+    #[test]
+    fn test_entrypoint_order() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: ordering
+  version: 1
+paths:
+  /things:
+    delete:
+      operationId: deleteThings
+      responses:
+        204:
+          description: Deleted
+    post:
+      operationId: createThing
+      responses:
+        201:
+          description: Created
+    get:
+      operationId: listThings
+      responses:
+        200:
+          description: Things
+  /other-things:
+    patch:
+      operationId: patchOtherThings
+      responses:
+        200:
+          description: Patched
+    put:
+      operationId: putOtherThings
+      responses:
+        200:
+          description: Replaced
+"#;
+        let api = OpenApi::from_string(yaml).unwrap();
+        let order = |api: &OpenApi| -> Vec<String> {
+            extract_entrypoints(api)
+                .iter()
+                .map(|e| e.operation_id.0.clone())
+                .collect()
+        };
+        let expected = vec![
+            "put_other_things",
+            "patch_other_things",
+            "list_things",
+            "create_thing",
+            "delete_things",
+        ];
+        assert_eq!(order(&api), expected);
+        // Paths are sorted whichever order they are declared in
+        let (things, other_things) = yaml.split_at(yaml.find("  /other-things:").unwrap());
+        let (header, things) = things.split_at(things.find("  /things:").unwrap());
+        let reordered = format!("{}{}{}", header, other_things, things);
+        assert!(reordered.find("/other-things").unwrap() < reordered.find("/things:").unwrap());
+        assert_eq!(order(&OpenApi::from_string(&reordered).unwrap()), expected);
     }
 }
@@ -0,0 +1,465 @@
+// Conversion of Swagger 2.0 documents into the OpenAPI 3 structure consumed
+// by the rest of the pipeline. Only the parts thruster makes use of are
+// carried across: paths, parameters, bodies, responses, definitions and
+// security.
+
+use openapi3::OpenApi;
+use serde_json::{self, Map};
+use serde_json::Value as JsonValue;
+
+use {warn, Result};
+
+// The keys of a path item's operations, the same in both versions
+pub(crate) const METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// Whether the document declares itself to be Swagger 2.0. An unquoted
+/// `swagger: 2.0` in YAML is read as a number
+pub fn is_swagger2(spec: &JsonValue) -> bool {
+    spec["swagger"] == "2.0" || spec["swagger"].as_f64() == Some(2.0)
+}
+
+/// Convert a Swagger 2.0 document into an `OpenApi`
+pub fn from_swagger2(spec: &JsonValue) -> Result<OpenApi> {
+    if !is_swagger2(spec) {
+        bail!("Expected a Swagger 2.0 document");
+    }
+    Ok(serde_json::from_value(convert(spec))?)
+}
+
+fn convert(spec: &JsonValue) -> JsonValue {
+    let consumes = media_type(&spec["consumes"]);
+    let produces = media_type(&spec["produces"]);
+
+    let mut paths = Map::new();
+    if let Some(spec_paths) = spec["paths"].as_object() {
+        for (route, path) in spec_paths {
+            let mut out = Map::new();
+            for method in METHODS {
+                let op = &path[*method];
+                if op.is_null() {
+                    continue;
+                }
+                let consumes = media_type(&op["consumes"]).unwrap_or(consumes.clone());
+                let produces = media_type(&op["produces"]).unwrap_or(produces.clone());
+                out.insert(
+                    method.to_string(),
+                    convert_operation(op, &path["parameters"], consumes, produces),
+                );
+            }
+            paths.insert(route.clone(), JsonValue::Object(out));
+        }
+    }
+
+    let mut components = Map::new();
+    if let Some(definitions) = spec.get("definitions") {
+        components.insert("schemas".into(), definitions.clone());
+    }
+    if let Some(parameters) = spec["parameters"].as_object() {
+        let parameters = parameters
+            .iter()
+            .filter(|&(_, param)| param["in"] != "body")
+            .map(|(name, param)| (name.clone(), convert_parameter(param)))
+            .collect();
+        components.insert("parameters".into(), JsonValue::Object(parameters));
+    }
+    if let Some(responses) = spec["responses"].as_object() {
+        let produces = produces.unwrap_or("application/json".into());
+        let responses = responses
+            .iter()
+            .map(|(name, response)| (name.clone(), convert_response(response, &produces)))
+            .collect();
+        components.insert("responses".into(), JsonValue::Object(responses));
+    }
+    if let Some(definitions) = spec["securityDefinitions"].as_object() {
+        let schemes = definitions
+            .iter()
+            .filter_map(|(name, scheme)| {
+                convert_security_scheme(name, scheme).map(|scheme| (name.clone(), scheme))
+            })
+            .collect();
+        components.insert("securitySchemes".into(), JsonValue::Object(schemes));
+    }
+
+    let mut out = json!({
+        "openapi": "3.0.0",
+        "info": spec["info"],
+        "paths": paths,
+        "components": components,
+    });
+    // Requirements are written the same in both
+    if let Some(security) = spec.get("security") {
+        out["security"] = security.clone();
+    }
+    if let Some(host) = spec["host"].as_str() {
+        let scheme = spec["schemes"][0].as_str().unwrap_or("http");
+        let base_path = spec["basePath"].as_str().unwrap_or("");
+        out["servers"] = json!([{"url": format!("{}://{}{}", scheme, host, base_path)}]);
+    }
+    rewrite_refs(&mut out);
+    out
+}
+
+// The first of a `consumes` or `produces` list
+fn media_type(types: &JsonValue) -> Option<String> {
+    types[0].as_str().map(|s| s.into())
+}
+
+fn convert_operation(
+    op: &JsonValue,
+    path_params: &JsonValue,
+    consumes: Option<String>,
+    produces: Option<String>,
+) -> JsonValue {
+    let mut out = Map::new();
+    for key in &["operationId", "summary", "description", "tags", "deprecated", "security"] {
+        if let Some(value) = op.get(*key) {
+            out.insert(key.to_string(), value.clone());
+        }
+    }
+    // Vendor extensions mean the same in both
+    if let Some(op) = op.as_object() {
+        for (key, value) in op.iter().filter(|&(key, _)| key.starts_with("x-")) {
+            out.insert(key.clone(), value.clone());
+        }
+    }
+
+    let mut parameters = Vec::new();
+    let all_params = path_params
+        .as_array()
+        .into_iter()
+        .chain(op["parameters"].as_array())
+        .flat_map(|params| params.iter());
+    for param in all_params {
+        match param["in"].as_str() {
+            Some("body") => {
+                let consumes = consumes.clone().unwrap_or("application/json".into());
+                let mut content = Map::new();
+                content.insert(consumes, json!({"schema": param["schema"]}));
+                out.insert(
+                    "requestBody".into(),
+                    json!({
+                        "description": param["description"],
+                        "required": param["required"].as_bool().unwrap_or(false),
+                        "content": content,
+                    }),
+                );
+            }
+            Some("formData") => warn(format!(
+                "formData parameter '{}' is not supported and has been dropped",
+                param["name"].as_str().unwrap_or("")
+            )),
+            _ => parameters.push(convert_parameter(param)),
+        }
+    }
+    if !parameters.is_empty() {
+        out.insert("parameters".into(), JsonValue::Array(parameters));
+    }
+
+    let produces = produces.unwrap_or("application/json".into());
+    let responses = op["responses"]
+        .as_object()
+        .map(|responses| {
+            responses
+                .iter()
+                .map(|(code, response)| (code.clone(), convert_response(response, &produces)))
+                .collect()
+        })
+        .unwrap_or_default();
+    out.insert("responses".into(), JsonValue::Object(responses));
+    JsonValue::Object(out)
+}
+
+// Swagger 2.0 parameters describe their type inline, where OpenAPI 3 has a schema
+fn convert_parameter(param: &JsonValue) -> JsonValue {
+    if param.get("$ref").is_some() {
+        return param.clone();
+    }
+    let mut out = Map::new();
+    let mut schema = Map::new();
+    if let Some(param) = param.as_object() {
+        for (key, value) in param {
+            match key.as_str() {
+                "name" | "in" | "description" | "required" | "allowEmptyValue" => {
+                    out.insert(key.clone(), value.clone());
+                }
+                "collectionFormat" => {}
+                _ => {
+                    schema.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    if param["type"] == "array" {
+        collection_format(param, &mut out);
+    }
+    out.insert("schema".into(), JsonValue::Object(schema));
+    JsonValue::Object(out)
+}
+
+// The `style` and `explode` of an array parameter, from its `collectionFormat`.
+// Swagger 2.0 defaults to comma separated values, which is already OpenAPI 3's
+// default outside of the query
+fn collection_format(param: &JsonValue, out: &mut Map<String, JsonValue>) {
+    let format = param["collectionFormat"].as_str().unwrap_or("csv");
+    let query = param["in"] == "query";
+    let (style, explode) = match format {
+        "csv" if query => ("form", false),
+        "csv" => return,
+        "ssv" if query => ("spaceDelimited", false),
+        "pipes" if query => ("pipeDelimited", false),
+        "multi" if query => ("form", true),
+        _ => {
+            warn(format!(
+                "collectionFormat '{}' of parameter '{}' has no OpenAPI 3 equivalent \
+                 and has been dropped",
+                format,
+                param["name"].as_str().unwrap_or("")
+            ));
+            return;
+        }
+    };
+    out.insert("style".into(), json!(style));
+    out.insert("explode".into(), json!(explode));
+}
+
+// Swagger 2.0's security definitions as OpenAPI 3 security schemes, whose
+// OAuth2 flows are renamed and nested
+fn convert_security_scheme(name: &str, scheme: &JsonValue) -> Option<JsonValue> {
+    let mut out = match scheme["type"].as_str() {
+        Some("basic") => json!({"type": "http", "scheme": "basic"}),
+        Some("apiKey") => json!({"type": "apiKey", "name": scheme["name"], "in": scheme["in"]}),
+        Some("oauth2") => {
+            let flow = match scheme["flow"].as_str() {
+                Some("implicit") => "implicit",
+                Some("password") => "password",
+                Some("application") => "clientCredentials",
+                Some("accessCode") => "authorizationCode",
+                _ => {
+                    warn(format!(
+                        "Security definition '{}' has an unknown OAuth2 flow and has been dropped",
+                        name
+                    ));
+                    return None;
+                }
+            };
+            let mut details = Map::new();
+            for key in &["authorizationUrl", "tokenUrl", "scopes"] {
+                if let Some(value) = scheme.get(*key) {
+                    details.insert(key.to_string(), value.clone());
+                }
+            }
+            let mut flows = Map::new();
+            flows.insert(flow.into(), JsonValue::Object(details));
+            json!({"type": "oauth2", "flows": flows})
+        }
+        _ => {
+            warn(format!(
+                "Security definition '{}' has an unknown type and has been dropped",
+                name
+            ));
+            return None;
+        }
+    };
+    if let Some(description) = scheme.get("description") {
+        out["description"] = description.clone();
+    }
+    Some(out)
+}
+
+fn convert_response(response: &JsonValue, produces: &str) -> JsonValue {
+    if response.get("$ref").is_some() {
+        return response.clone();
+    }
+    let mut out = json!({ "description": response["description"] });
+    if let Some(schema) = response.get("schema") {
+        let mut content = Map::new();
+        content.insert(produces.into(), json!({ "schema": schema }));
+        out["content"] = JsonValue::Object(content);
+    }
+    out
+}
+
+// Point references at their new homes under `components`
+fn rewrite_refs(value: &mut JsonValue) {
+    match *value {
+        JsonValue::Object(ref mut map) => {
+            for (key, value) in map.iter_mut() {
+                if key == "$ref" {
+                    let new_ref = value.as_str().map(rewrite_ref);
+                    if let Some(new_ref) = new_ref {
+                        *value = JsonValue::String(new_ref);
+                    }
+                } else {
+                    rewrite_refs(value);
+                }
+            }
+        }
+        JsonValue::Array(ref mut values) => {
+            for value in values {
+                rewrite_refs(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn rewrite_ref(ref_: &str) -> String {
+    let moves = [
+        ("#/definitions/", "#/components/schemas/"),
+        ("#/parameters/", "#/components/parameters/"),
+        ("#/responses/", "#/components/responses/"),
+    ];
+    for &(from, to) in &moves {
+        if ref_.starts_with(from) {
+            return format!("{}{}", to, &ref_[from.len()..]);
+        }
+    }
+    ref_.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use process;
+
+    #[test]
+    fn test_from_swagger2() {
+        let spec = json!({
+            "swagger": "2.0",
+            "info": {"title": "petstore", "version": "1.0.0"},
+            "host": "petstore.swagger.io",
+            "basePath": "/v1",
+            "schemes": ["http"],
+            "paths": {
+                "/pets/{petId}": {
+                    "get": {
+                        "operationId": "showPetById",
+                        "parameters": [
+                            {"name": "petId", "in": "path", "required": true, "type": "string"}
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "A pet",
+                                "schema": {"$ref": "#/definitions/Pet"}
+                            }
+                        }
+                    },
+                    "put": {
+                        "operationId": "updatePet",
+                        "parameters": [
+                            {"name": "petId", "in": "path", "required": true, "type": "string"},
+                            {
+                                "name": "pet",
+                                "in": "body",
+                                "required": true,
+                                "schema": {"$ref": "#/definitions/Pet"}
+                            }
+                        ],
+                        "responses": {"204": {"description": "Updated"}}
+                    }
+                }
+            },
+            "definitions": {
+                "Pet": {
+                    "required": ["id"],
+                    "properties": {"id": {"type": "integer", "format": "int64"}}
+                }
+            }
+        });
+        assert!(is_swagger2(&spec));
+        let api = from_swagger2(&spec).unwrap();
+        assert_eq!(api.servers.as_ref().unwrap()[0].url, "http://petstore.swagger.io/v1");
+        let schemas = api.components.as_ref().unwrap().schemas.as_ref().unwrap();
+        assert!(schemas.contains_key("Pet"));
+
+        let entrypoints = process::extract_entrypoints(&api);
+        assert_eq!(entrypoints.len(), 2);
+        let show = entrypoints[0].build_template_args();
+        assert_eq!(show["function"], "show_pet_by_id");
+        assert_eq!(show["result_type"], "Pet");
+        assert_eq!(show["args"][0]["type"], "String");
+        let update = entrypoints[1].build_template_args();
+        assert_eq!(update["data"], "body");
+        assert_eq!(update["args"][1]["type"], "Pet");
+    }
+
+    #[test]
+    fn test_collection_format() {
+        let param = |format: &str, location: &str| {
+            convert_parameter(&json!({
+                "name": "tags",
+                "in": location,
+                "type": "array",
+                "items": {"type": "string"},
+                "collectionFormat": format
+            }))
+        };
+        let converted = param("csv", "query");
+        assert_eq!(converted["style"], "form");
+        assert_eq!(converted["explode"], false);
+        assert!(converted["schema"].get("collectionFormat").is_none());
+        assert_eq!(param("ssv", "query")["style"], "spaceDelimited");
+        assert_eq!(param("pipes", "query")["style"], "pipeDelimited");
+        assert_eq!(param("multi", "query")["explode"], true);
+        assert!(param("csv", "header").get("style").is_none());
+
+        // Arrays are comma separated unless stated otherwise
+        let converted = convert_parameter(&json!({
+            "name": "ids",
+            "in": "query",
+            "type": "array",
+            "items": {"type": "integer"}
+        }));
+        assert_eq!(converted["explode"], false);
+
+        ::take_warnings();
+        assert!(param("tsv", "query").get("style").is_none());
+        assert!(::take_warnings()[0].contains("collectionFormat 'tsv'"));
+    }
+
+    #[test]
+    fn test_security_definitions() {
+        let spec = json!({
+            "swagger": "2.0",
+            "info": {"title": "secure", "version": "1.0.0"},
+            "paths": {},
+            "security": [{"key": []}],
+            "securityDefinitions": {
+                "basic": {"type": "basic"},
+                "key": {"type": "apiKey", "name": "X-Api-Key", "in": "header"},
+                "oauth": {
+                    "type": "oauth2",
+                    "flow": "accessCode",
+                    "authorizationUrl": "https://example.com/authorize",
+                    "tokenUrl": "https://example.com/token",
+                    "scopes": {"read": "Read access"}
+                }
+            }
+        });
+        let converted = convert(&spec);
+        let schemes = &converted["components"]["securitySchemes"];
+        assert_eq!(schemes["basic"], json!({"type": "http", "scheme": "basic"}));
+        assert_eq!(
+            schemes["key"],
+            json!({"type": "apiKey", "name": "X-Api-Key", "in": "header"})
+        );
+        let flow = &schemes["oauth"]["flows"]["authorizationCode"];
+        assert_eq!(flow["tokenUrl"], "https://example.com/token");
+        assert_eq!(flow["scopes"]["read"], "Read access");
+        assert_eq!(converted["security"], json!([{"key": []}]));
+        assert!(from_swagger2(&spec).is_ok());
+    }
+
+    #[test]
+    fn test_not_swagger2() {
+        let spec = json!({"openapi": "3.0.0"});
+        assert!(!is_swagger2(&spec));
+        assert!(from_swagger2(&spec).is_err());
+        assert!(!is_swagger2(&json!({"swagger": 3.0})));
+    }
+
+    #[test]
+    fn test_numeric_version() {
+        assert!(is_swagger2(&json!({"swagger": 2.0})));
+    }
+}
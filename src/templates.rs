@@ -0,0 +1,11 @@
+// Templates are embedded so that generation works regardless of the working directory
+
+pub const GEN_TEMPLATE: &str = include_str!("../templates/gen.hbs");
+pub const STUB_TEMPLATE: &str = include_str!("../templates/stub.hbs");
+pub const MAIN_TEMPLATE: &str = include_str!("../templates/main.hbs");
+pub const DOCKERFILE_TEMPLATE: &str = include_str!("../templates/dockerfile.hbs");
+pub const CLIENT_TEMPLATE: &str = include_str!("../templates/client.hbs");
+pub const ACTIX_GEN_TEMPLATE: &str = include_str!("../templates/actix_gen.hbs");
+pub const ACTIX_MAIN_TEMPLATE: &str = include_str!("../templates/actix_main.hbs");
+pub const TESTS_TEMPLATE: &str = include_str!("../templates/tests.hbs");
+pub const ACTIX_TESTS_TEMPLATE: &str = include_str!("../templates/actix_tests.hbs");
@@ -0,0 +1,2031 @@
+use openapi3::objects::{Schema, SimpleTypes};
+use inflector::Inflector;
+use std::collections::{BTreeMap, BTreeSet};
+
+use process::{self, additional_properties, default_literal, doc_comment, is_free_form, ref_name,
+              NativeType, TypeOptions};
+use serde_json::Value as JsonValue;
+use regex::Regex;
+use {warn, Config, Framework, Result, ResultExt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllOfStrategy {
+    /// Copy the properties of every subschema into a single struct
+    Merge,
+    /// Keep referenced subschemas as `#[serde(flatten)]` fields
+    Flatten,
+}
+
+impl Default for AllOfStrategy {
+    fn default() -> Self {
+        AllOfStrategy::Merge
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Field {
+    name: String,
+    type_: String,
+    attrs: Vec<String>,
+    // The function giving the value of a required field when it is missing
+    default: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct StructDef {
+    name: String,
+    derives: Vec<String>,
+    fields: Vec<Field>,
+}
+
+impl StructDef {
+    fn render(&self) -> String {
+        let mut out = format!(
+            "#[derive({})]\npub struct {} {{\n",
+            self.derives.join(", "),
+            self.name
+        );
+        for field in &self.fields {
+            for attr in &field.attrs {
+                out.push_str(&format!("    {}\n", attr));
+            }
+            out.push_str(&format!("    pub {}: {},\n", field.name, field.type_));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // A builder setting the fields one at a time. Optional fields are `None`
+    // and defaulted ones take their defaults unless set, while `build` fails
+    // if any other field is unset
+    fn render_builder(&self) -> String {
+        let mut out = format!(
+            "#[derive(Debug, Clone, Default)]\npub struct {}Builder {{\n",
+            self.name
+        );
+        let setter_types: Vec<&str> = self.fields.iter().map(|field| field.setter_type()).collect();
+        for (field, type_) in self.fields.iter().zip(&setter_types) {
+            out.push_str(&format!("    {}: Option<{}>,\n", field.name, type_));
+        }
+        out.push_str(&format!(
+            "}}\n\nimpl {name} {{\n    pub fn builder() -> {name}Builder {{\n        \
+             {name}Builder::default()\n    }}\n}}\n\nimpl {name}Builder {{\n",
+            name = self.name
+        ));
+        for (field, type_) in self.fields.iter().zip(&setter_types) {
+            out.push_str(&format!(
+                "    pub fn with_{setter}(mut self, {name}: {type_}) -> Self {{\n        \
+                 self.{name} = Some({name});\n        self\n    }}\n\n",
+                setter = field.name.trim_right_matches('_'),
+                name = field.name,
+                type_ = type_
+            ));
+        }
+        out.push_str(&format!(
+            "    pub fn build(self) -> Result<{name}, String> {{\n        Ok({name} {{\n",
+            name = self.name
+        ));
+        for field in &self.fields {
+            let value = match field.default {
+                _ if field.is_optional() => format!("self.{}", field.name),
+                Some(ref default) => format!("self.{}.unwrap_or_else({})", field.name, default),
+                None => format!(
+                    "self.{name}.ok_or_else(|| \"{name} is required\".to_string())?",
+                    name = field.name
+                ),
+            };
+            out.push_str(&format!("            {}: {},\n", field.name, value));
+        }
+        out.push_str("        })\n    }\n}\n");
+        out
+    }
+}
+
+impl Field {
+    fn is_optional(&self) -> bool {
+        self.type_.starts_with("Option<")
+    }
+
+    // The type a builder sets the field with, which is the value of an
+    // optional field
+    fn setter_type(&self) -> &str {
+        if self.is_optional() {
+            &self.type_["Option<".len()..self.type_.len() - 1]
+        } else {
+            &self.type_
+        }
+    }
+}
+
+// A wrapper around a scalar which checks the schema's constraints whenever
+// it is constructed or deserialized
+#[derive(Clone, Debug)]
+struct Newtype {
+    name: String,
+    inner: String,
+    checks: Vec<String>,
+    // Whether the inner type is a scalar, rather than an array, so can be
+    // compared and parsed from a string
+    scalar: bool,
+    // Whether to derive `Eq` and `Hash` along with `PartialEq`, so structs
+    // deriving them may hold the newtype
+    hash: bool,
+    // Whether to implement Rocket's `FromParam` and form traits, so the
+    // newtype can be used for path and query parameters, and whether for
+    // Rocket 0.5 rather than 0.3
+    rocket_impls: bool,
+    async_rocket: bool,
+    // Whether the inner type is an array of scalars, which Rocket 0.5 can
+    // collect from repeated query keys
+    scalar_items: bool,
+}
+
+impl Newtype {
+    fn render(&self) -> String {
+        let mut out = format!(
+            "#[derive(Debug, Clone, {partial_eq}Serialize)]\npub struct {name}({inner});\n\n\
+             impl {name} {{\n    pub fn new(value: {inner}) -> Result<Self, String> {{\n",
+            partial_eq = if self.hash {
+                "PartialEq, Eq, Hash, "
+            } else if self.scalar {
+                "PartialEq, "
+            } else {
+                ""
+            },
+            name = self.name,
+            inner = self.inner
+        );
+        for check in &self.checks {
+            out.push_str(check);
+        }
+        out.push_str(&format!(
+            "        Ok({name}(value))\n    }}\n\n\
+             \x20   pub fn into_inner(self) -> {inner} {{\n        self.0\n    }}\n}}\n\n\
+             impl<'de> ::serde::Deserialize<'de> for {name} {{\n\
+             \x20   fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>\n\
+             \x20   where\n        D: ::serde::Deserializer<'de>,\n    {{\n\
+             \x20       let value = <{inner} as ::serde::Deserialize>::deserialize(deserializer)?;\n\
+             \x20       {name}::new(value).map_err(::serde::de::Error::custom)\n    }}\n}}\n",
+            name = self.name,
+            inner = self.inner
+        ));
+        if !self.scalar {
+            if self.rocket_impls && self.async_rocket && self.scalar_items {
+                out.push_str(&rocket_05_form_impl(&self.name, &self.inner));
+            }
+            return out;
+        }
+        out.push_str(&format!(
+            "\nimpl ::std::str::FromStr for {name} {{\n\
+             \x20   type Err = String;\n\n\
+             \x20   fn from_str(s: &str) -> Result<Self, String> {{\n\
+             \x20       let value = s.parse::<{inner}>()\n\
+             \x20           .map_err(|_| format!(\"{{:?}} is not a valid {inner}\", s))?;\n\
+             \x20       {name}::new(value)\n    }}\n}}\n",
+            name = self.name,
+            inner = self.inner
+        ));
+        if self.rocket_impls && self.async_rocket {
+            out.push_str(&rocket_05_param_impls(&self.name));
+        } else if self.rocket_impls {
+            out.push_str(&rocket_param_impls(&self.name));
+        }
+        out
+    }
+}
+
+// Rocket 0.3's `FromParam` and `FromFormValue` for a type implementing `FromStr`
+fn rocket_param_impls(name: &str) -> String {
+    format!(
+        "\nimpl<'a> ::rocket::request::FromParam<'a> for {name} {{\n\
+         \x20   type Error = String;\n\n\
+         \x20   fn from_param(param: &'a ::rocket::http::RawStr) -> Result<Self, String> {{\n\
+         \x20       param.percent_decode().map_err(|e| e.to_string())?.parse()\n    }}\n}}\n\n\
+         impl<'v> ::rocket::request::FromFormValue<'v> for {name} {{\n\
+         \x20   type Error = String;\n\n\
+         \x20   fn from_form_value(value: &'v ::rocket::http::RawStr) -> Result<Self, String> {{\n\
+         \x20       value.url_decode().map_err(|e| e.to_string())?.parse()\n    }}\n}}\n",
+        name = name
+    )
+}
+
+// Rocket 0.5's equivalents of `rocket_param_impls`. Its parameters arrive
+// already decoded
+fn rocket_05_param_impls(name: &str) -> String {
+    format!(
+        "\nimpl<'a> ::rocket::request::FromParam<'a> for {name} {{\n\
+         \x20   type Error = String;\n\n\
+         \x20   fn from_param(param: &'a str) -> Result<Self, String> {{\n\
+         \x20       param.parse()\n    }}\n}}\n\n\
+         #[::rocket::async_trait]\n\
+         impl<'v> ::rocket::form::FromFormField<'v> for {name} {{\n\
+         \x20   fn from_value(field: ::rocket::form::ValueField<'v>) -> ::rocket::form::Result<'v, Self> {{\n\
+         \x20       field.value.parse().map_err(|e: String| ::rocket::form::Error::validation(e).into())\n    }}\n}}\n",
+        name = name
+    )
+}
+
+// Rocket 0.5's `FromForm` for a newtype around an array, which collects the
+// values as the array does and then checks them
+fn rocket_05_form_impl(name: &str, inner: &str) -> String {
+    format!(
+        "\n#[::rocket::async_trait]\n\
+         impl<'v> ::rocket::form::FromForm<'v> for {name} {{\n\
+         \x20   type Context = <{inner} as ::rocket::form::FromForm<'v>>::Context;\n\n\
+         \x20   fn init(opts: ::rocket::form::Options) -> Self::Context {{\n\
+         \x20       <{inner} as ::rocket::form::FromForm<'v>>::init(opts)\n    }}\n\n\
+         \x20   fn push_value(ctxt: &mut Self::Context, field: ::rocket::form::ValueField<'v>) {{\n\
+         \x20       <{inner} as ::rocket::form::FromForm<'v>>::push_value(ctxt, field)\n    }}\n\n\
+         \x20   async fn push_data(ctxt: &mut Self::Context, field: ::rocket::form::DataField<'v, '_>) {{\n\
+         \x20       <{inner} as ::rocket::form::FromForm<'v>>::push_data(ctxt, field).await\n    }}\n\n\
+         \x20   fn finalize(ctxt: Self::Context) -> ::rocket::form::Result<'v, Self> {{\n\
+         \x20       let value = <{inner} as ::rocket::form::FromForm<'v>>::finalize(ctxt)?;\n\
+         \x20       {name}::new(value).map_err(|e| ::rocket::form::Error::validation(e).into())\n    }}\n}}\n",
+        name = name,
+        inner = inner
+    )
+}
+
+// A scalar component schema wrapped in a newtype of its own, rather than
+// aliased, so it is distinct from other values of the same type
+struct ScalarNewtype {
+    name: String,
+    inner: String,
+    derives: Vec<String>,
+    // Whether the inner type can be parsed from a string, so the newtype can too
+    parses: bool,
+    // Whether to implement Rocket's `FromParam` and form value traits, and
+    // whether for Rocket 0.5 rather than 0.3
+    rocket_impls: bool,
+    async_rocket: bool,
+}
+
+impl ScalarNewtype {
+    fn render(&self) -> String {
+        let mut out = format!(
+            "#[derive({})]\n#[serde(transparent)]\npub struct {}(pub {});\n",
+            self.derives.join(", "),
+            self.name,
+            self.inner
+        );
+        if self.parses {
+            out.push_str(&format!(
+                "\nimpl ::std::str::FromStr for {name} {{\n\
+                 \x20   type Err = String;\n\n\
+                 \x20   fn from_str(s: &str) -> Result<Self, String> {{\n\
+                 \x20       s.parse::<{inner}>()\n\
+                 \x20           .map({name})\n\
+                 \x20           .map_err(|_| format!(\"{{:?}} is not a valid {inner}\", s))\n    }}\n}}\n",
+                name = self.name,
+                inner = self.inner
+            ));
+            if self.rocket_impls && self.async_rocket {
+                out.push_str(&rocket_05_param_impls(&self.name));
+            } else if self.rocket_impls {
+                out.push_str(&rocket_param_impls(&self.name));
+            }
+        }
+        out
+    }
+}
+
+// A string or integer schema restricted to a set of values
+#[derive(Clone, Debug)]
+struct EnumDef {
+    name: String,
+    // Variant names, paired with the values they (de)serialize as
+    variants: Vec<(String, String)>,
+    // Whether the values are integers, which become the variants' discriminants
+    integer: bool,
+}
+
+impl EnumDef {
+    // The enum of a schema declared with `enum`, if its values can be variants
+    fn from_schema(name: &str, schema: &Schema) -> Option<EnumDef> {
+        if let Some(values) = string_enum(schema) {
+            let variants = values
+                .iter()
+                .enumerate()
+                .map(|(ix, value)| (variant_name(value, ix), value.to_string()))
+                .collect();
+            return Some(EnumDef {
+                name: name.into(),
+                variants: unique_variants(variants),
+                integer: false,
+            });
+        }
+        let values = match integer_enum(schema) {
+            Some(values) => values,
+            None => return None,
+        };
+        // Variants are named by `x-enum-varnames` if given, otherwise by value
+        let names = match schema.extensions.get("x-enum-varnames").and_then(|n| n.as_array()) {
+            Some(names) if names.len() == values.len() => Some(names),
+            _ => None,
+        };
+        let variants = values
+            .iter()
+            .enumerate()
+            .map(|(ix, value)| {
+                let variant = match names.and_then(|names| names[ix].as_str()) {
+                    Some(name) => variant_name(name, ix),
+                    None if *value < 0 => format!("ValueMinus{}", &value.to_string()[1..]),
+                    None => format!("Value{}", value),
+                };
+                (variant, value.to_string())
+            })
+            .collect();
+        Some(EnumDef {
+            name: name.into(),
+            variants: unique_variants(variants),
+            integer: true,
+        })
+    }
+
+    // The path of the variant which (de)serializes as the value
+    fn variant(&self, value: &JsonValue) -> Option<String> {
+        let value = match *value {
+            JsonValue::String(ref s) if !self.integer => s.clone(),
+            JsonValue::Number(ref n) if self.integer => n.to_string(),
+            _ => return None,
+        };
+        self.variants
+            .iter()
+            .find(|&&(_, ref v)| *v == value)
+            .map(|&(ref variant, _)| format!("{}::{}", self.name, variant))
+    }
+
+    fn render(&self) -> String {
+        if self.integer {
+            return self.render_integer();
+        }
+        // Unit variants are always hashable
+        let mut out = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n\
+             pub enum {} {{\n",
+            self.name
+        );
+        for &(ref variant, ref value) in &self.variants {
+            if variant != value {
+                out.push_str(&format!("    #[serde(rename = {:?})]\n", value));
+            }
+            out.push_str(&format!("    {},\n", variant));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // Integers are converted to and from the variants, so unknown values are
+    // rejected when deserializing
+    fn render_integer(&self) -> String {
+        let mut out = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n\
+             #[serde(into = \"i64\", try_from = \"i64\")]\n\
+             #[repr(i64)]\n\
+             pub enum {} {{\n",
+            self.name
+        );
+        for &(ref variant, ref value) in &self.variants {
+            out.push_str(&format!("    {} = {},\n", variant, value));
+        }
+        out.push_str(&format!(
+            "}}\n\n\
+             impl From<{name}> for i64 {{\n\
+             \x20   fn from(value: {name}) -> i64 {{\n\
+             \x20       value as i64\n    }}\n}}\n\n\
+             impl ::std::convert::TryFrom<i64> for {name} {{\n\
+             \x20   type Error = String;\n\n\
+             \x20   fn try_from(value: i64) -> Result<Self, String> {{\n\
+             \x20       match value {{\n",
+            name = self.name
+        ));
+        for &(ref variant, ref value) in &self.variants {
+            out.push_str(&format!("            {} => Ok({}::{}),\n", value, self.name, variant));
+        }
+        out.push_str(&format!(
+            "            _ => Err(format!(\"{{}} is not a valid {}\", value)),\n        }}\n    }}\n}}\n",
+            self.name
+        ));
+        out
+    }
+}
+
+// A schema which is one of several others. Tagged by the discriminator
+// property when there is one, otherwise serde picks the first variant which fits
+#[derive(Clone, Debug)]
+struct UnionDef {
+    name: String,
+    derives: Vec<String>,
+    tag: Option<String>,
+    // Variant names, paired with the types they hold
+    variants: Vec<(String, String)>,
+}
+
+impl UnionDef {
+    fn render(&self) -> String {
+        let mut out = format!("#[derive({})]\n", self.derives.join(", "));
+        match self.tag {
+            Some(ref tag) => out.push_str(&format!("#[serde(tag = {:?})]\n", tag)),
+            None => out.push_str("#[serde(untagged)]\n"),
+        }
+        out.push_str(&format!("pub enum {} {{\n", self.name));
+        for &(ref variant, ref type_) in &self.variants {
+            out.push_str(&format!("    {}({}),\n", variant, type_));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+// The subschemas of a `oneOf`, or failing that an `anyOf`. Both are generated
+// alike, as a value matching several `anyOf` subschemas takes the first
+fn one_of(schema: &Schema) -> Option<&[Schema]> {
+    schema
+        .one_of
+        .as_ref()
+        .or(schema.any_of.as_ref())
+        .map(|subschemas| subschemas.as_slice())
+}
+
+// A schema of each of the types of a schema with several, as OpenAPI 3.1
+// allows, which is generated like a `oneOf` of them. `null` only makes the
+// schema nullable, so has no subschema
+fn type_variants(schema: &Schema) -> Option<Vec<Schema>> {
+    let types: Vec<&SimpleTypes> = schema
+        .type_
+        .iter()
+        .filter(|type_| **type_ != SimpleTypes::Null)
+        .collect();
+    if types.len() < 2 {
+        return None;
+    }
+    let variants = types
+        .into_iter()
+        .map(|type_| {
+            let mut variant = schema.clone();
+            variant.type_ = vec![type_.clone()];
+            variant.nullable = None;
+            variant
+        })
+        .collect();
+    Some(variants)
+}
+
+// The subschemas a schema is composed of, through any of `allOf`, `oneOf` and `anyOf`
+fn subschema_lists(schema: &Schema) -> Vec<&Vec<Schema>> {
+    vec![&schema.all_of, &schema.one_of, &schema.any_of]
+        .into_iter()
+        .filter_map(|subschemas| subschemas.as_ref())
+        .collect()
+}
+
+// The Rust expression for a property's `default`, if it is simple enough to
+// write out. `enum_name` is the name given to an inline enum
+fn default_expr(
+    prop: &Schema,
+    enum_name: &str,
+    value: &JsonValue,
+    options: TypeOptions,
+) -> Result<Option<String>> {
+    if let Some(def) = EnumDef::from_schema(enum_name, prop) {
+        return Ok(def.variant(value));
+    }
+    let native = NativeType::from_json_schema_with(prop, true, options)?;
+    Ok(default_literal(&native, value))
+}
+
+// The type of a property when it is always present
+fn required_type(prop: &Schema, enum_name: &str, options: TypeOptions) -> Result<String> {
+    if EnumDef::from_schema(enum_name, prop).is_some() {
+        return Ok(enum_name.into());
+    }
+    let native = NativeType::from_json_schema_with(prop, true, options)?;
+    Ok(native.render_with(&mut |_| unreachable!()))
+}
+
+// Wrap a type name in as many `Option`s as the native type has
+fn wrap_options(native: &NativeType, name: String) -> String {
+    match *native {
+        NativeType::Option(ref inner) => format!("Option<{}>", wrap_options(inner, name)),
+        _ => name,
+    }
+}
+
+// The values of a string schema declared with `enum`
+fn string_enum(schema: &Schema) -> Option<Vec<&str>> {
+    use openapi3::objects::SimpleTypes::String as StringType;
+    if schema.ref_.is_some() || schema.type_ != [StringType] {
+        return None;
+    }
+    schema
+        .enum_
+        .as_ref()
+        .and_then(|values| values.iter().map(|v| v.as_str()).collect())
+}
+
+// The values of an integer (or whole number) schema declared with `enum`
+fn integer_enum(schema: &Schema) -> Option<Vec<i64>> {
+    use openapi3::objects::SimpleTypes::{Integer, Number};
+    if schema.ref_.is_some() || (schema.type_ != [Integer] && schema.type_ != [Number]) {
+        return None;
+    }
+    schema
+        .enum_
+        .as_ref()
+        .and_then(|values| values.iter().map(|v| v.as_i64()).collect())
+}
+
+// A ClassCase variant name for an enum value. Values without any usable
+// characters are numbered instead
+fn variant_name(value: &str, index: usize) -> String {
+    let name: String = value
+        .to_class_case()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect();
+    if name.is_empty() {
+        format!("Variant{}", index)
+    } else if name.starts_with(|c: char| c.is_numeric()) {
+        format!("V{}", name)
+    } else if name == "Self" {
+        // The only keyword written in ClassCase
+        "Self_".into()
+    } else {
+        name
+    }
+}
+
+// The variants less any repeated values, numbering the names which different
+// values would otherwise share, e.g. those of "a-b" and "a_b"
+fn unique_variants(variants: Vec<(String, String)>) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    for (name, value) in variants {
+        if out.iter().any(|&(_, ref other)| *other == value) {
+            continue;
+        }
+        let mut unique = name.clone();
+        let mut n = 1;
+        while out.iter().any(|&(ref other, _)| *other == unique) {
+            n += 1;
+            unique = format!("{}{}", name, n);
+        }
+        out.push((unique, value));
+    }
+    out
+}
+
+// Collects struct definitions, including those for nested anonymous objects
+struct TypeGen<'a> {
+    schemas: &'a BTreeMap<String, Schema>,
+    config: &'a Config,
+    out: Vec<String>,
+}
+
+impl<'a> TypeGen<'a> {
+    fn options(&self) -> TypeOptions {
+        self.config.type_options()
+    }
+
+    fn object(&mut self, name: &str, schema: &Schema) -> Result<()> {
+        if self.config.split_read_write && schema.properties.values().any(is_read_only) {
+            // Read-only properties are assigned by the server, so clients
+            // need not send them even when they are always present in responses
+            let required: Vec<String> = schema
+                .required
+                .iter()
+                .filter(|prop_name| !schema.properties.get(*prop_name).map_or(false, is_read_only))
+                .cloned()
+                .collect();
+            let request_name = format!("{}Request", name);
+            let mut fields = self.property_fields(&request_name, &schema.properties, &required)?;
+            // and anything they do send is ignored
+            for (field, prop) in fields.iter_mut().zip(schema.properties.values()) {
+                if is_read_only(prop) {
+                    field.attrs.push("#[serde(skip_deserializing)]".into());
+                }
+            }
+            let extra = self.extra_field(&request_name, schema, &fields)?;
+            fields.extend(extra);
+            let derives = self.derives(schema);
+            self.push_struct(StructDef { name: request_name, derives, fields });
+        }
+        let mut fields = self.property_fields(name, &schema.properties, &schema.required)?;
+        let extra = self.extra_field(name, schema, &fields)?;
+        fields.extend(extra);
+        let derives = self.derives(schema);
+        self.push_struct(StructDef { name: name.into(), derives, fields });
+        Ok(())
+    }
+
+    fn push_struct(&mut self, def: StructDef) {
+        self.out.push(def.render());
+        if self.config.builders {
+            self.out.push(def.render_builder());
+        }
+    }
+
+    // A field collecting an object's additional properties, alongside the
+    // fields for its fixed properties. It is named `extra`, or `extra_2` and
+    // so on if a property already is
+    fn extra_field(
+        &mut self,
+        name: &str,
+        schema: &Schema,
+        fields: &[Field],
+    ) -> Result<Option<Field>> {
+        let values = match schema.additional_properties {
+            Some(JsonValue::Bool(true)) => NativeType::Any,
+            Some(JsonValue::Object(_)) => match additional_properties(schema)? {
+                Some(values) => {
+                    NativeType::from_json_schema_with(&values, true, self.options())?.into_owned()
+                }
+                None => NativeType::Any,
+            },
+            _ => return Ok(None),
+        };
+        let nested_name = format!("{}Extra", name);
+        let mut nested = Vec::new();
+        let map = NativeType::Map(self.config.map_type, Box::new(values));
+        let type_ = map.render_with(&mut |schema| {
+            nested.push(schema.clone());
+            nested_name.clone()
+        });
+        for schema in nested {
+            self.nested(&nested_name, &schema)?;
+        }
+        let mut field_name = "extra".to_string();
+        let mut n = 1;
+        while fields.iter().any(|field| field.name == field_name) {
+            n += 1;
+            field_name = format!("extra_{}", n);
+        }
+        Ok(Some(Field {
+            name: field_name,
+            type_,
+            attrs: vec!["#[serde(flatten)]".into()],
+            default: Some("Default::default".into()),
+        }))
+    }
+
+    // A type alias for a map, defining the type of its values if they are
+    // an anonymous object
+    fn map(&mut self, name: &str, native: &NativeType) -> Result<()> {
+        let nested_name = format!("{}Value", name);
+        let mut nested = Vec::new();
+        let type_ = native.render_with(&mut |schema| {
+            nested.push(schema.clone());
+            nested_name.clone()
+        });
+        for schema in nested {
+            self.nested(&nested_name, &schema)?;
+        }
+        self.out.push(format!("pub type {} = {};\n", name, type_));
+        Ok(())
+    }
+
+    // Define an anonymous schema found within another
+    fn nested(&mut self, name: &str, schema: &Schema) -> Result<()> {
+        if let Some(ref subschemas) = schema.all_of {
+            self.all_of(name, subschemas)
+        } else if let Some(subschemas) = type_variants(schema) {
+            self.one_of(name, schema, &subschemas)
+        } else if let Some(subschemas) = one_of(schema) {
+            self.one_of(name, schema, subschemas)
+        } else {
+            self.object(name, schema)
+        }
+    }
+
+    // An enum with a variant per subschema, tagged by the discriminator
+    // property if there is one and untagged otherwise
+    fn one_of(&mut self, name: &str, schema: &Schema, subschemas: &[Schema]) -> Result<()> {
+        let mut variants = Vec::new();
+        for (ix, sub) in subschemas.iter().enumerate() {
+            let variant = match sub.ref_ {
+                Some(ref ref_) => ref_name(ref_)?.to_class_case(),
+                // One of several types, e.g. `String` for "string"
+                None if schema.type_.len() > 1 => format!("{:?}", sub.type_[0]),
+                None => format!("Variant{}", ix),
+            };
+            let nested_name = format!("{}{}", name, variant);
+            let mut nested = Vec::new();
+            let native = NativeType::from_json_schema_with(sub, true, self.options())?;
+            let type_ = native.render_with(&mut |schema| {
+                nested.push(schema.clone());
+                nested_name.clone()
+            });
+            for schema in nested {
+                self.nested(&nested_name, &schema)?;
+            }
+            variants.push((variant, type_));
+        }
+        let union = UnionDef {
+            name: name.into(),
+            derives: self.derives(schema),
+            tag: schema.discriminator.as_ref().map(|d| d.property_name.clone()),
+            variants,
+        };
+        self.out.push(union.render());
+        Ok(())
+    }
+
+    fn all_of(&mut self, name: &str, subschemas: &[Schema]) -> Result<()> {
+        let fields = match self.config.all_of {
+            AllOfStrategy::Merge => {
+                let mut properties = BTreeMap::new();
+                let mut required = Vec::new();
+                self.merge_into(subschemas, &mut properties, &mut required)?;
+                self.property_fields(name, &properties, &required)?
+            }
+            AllOfStrategy::Flatten => {
+                let mut fields = Vec::new();
+                for sub in subschemas {
+                    match sub.ref_ {
+                        Some(ref ref_) => {
+                            let refname = ref_name(ref_)?;
+                            fields.push(Field {
+                                name: refname.to_snake_case(),
+                                type_: refname.into(),
+                                attrs: vec!["#[serde(flatten)]".into()],
+                                default: None,
+                            })
+                        }
+                        None => fields.extend(
+                            self.property_fields(name, &sub.properties, &sub.required)?,
+                        ),
+                    }
+                }
+                fields
+            }
+        };
+        let mut derives = self.derives_hash_eq();
+        for sub in subschemas {
+            if !self.hashable(sub, &mut BTreeSet::new()) {
+                derives = self.base_derives();
+            }
+        }
+        self.push_struct(StructDef { name: name.into(), derives, fields });
+        Ok(())
+    }
+
+    fn derives(&self, schema: &Schema) -> Vec<String> {
+        if self.hashable(schema, &mut BTreeSet::new()) {
+            self.derives_hash_eq()
+        } else {
+            self.base_derives()
+        }
+    }
+
+    fn derives_hash_eq(&self) -> Vec<String> {
+        let mut derives = self.base_derives();
+        if self.config.derive_hash_eq {
+            for derive in &["PartialEq", "Eq", "Hash"] {
+                add_derive(&mut derives, derive);
+            }
+        }
+        derives
+    }
+
+    // The derives of every struct and union, including any the config asks for
+    fn base_derives(&self) -> Vec<String> {
+        let mut derives = Vec::new();
+        for derive in &["Debug", "Clone", "Serialize", "Deserialize"] {
+            add_derive(&mut derives, derive);
+        }
+        for derive in &self.config.type_derives {
+            add_derive(&mut derives, derive);
+        }
+        derives
+    }
+
+    // Whether the schema can derive `Eq` and `Hash`, i.e. it contains no floats,
+    // either directly or through the schemas it references
+    fn hashable(&self, schema: &Schema, seen: &mut BTreeSet<String>) -> bool {
+        if let Some(ref ref_) = schema.ref_ {
+            let name = match ref_name(ref_) {
+                Ok(name) => name,
+                Err(_) => return false,
+            };
+            if !seen.insert(name.into()) {
+                // Already being checked further up the stack
+                return true;
+            }
+            return match self.schemas.get(name) {
+                Some(referenced) => self.hashable(referenced, seen),
+                None => false,
+            };
+        }
+        // serde_json::Value does not implement Hash
+        if schema.type_.contains(&SimpleTypes::Number) || is_free_form(schema) {
+            return false;
+        }
+        for prop in schema.properties.values() {
+            if !self.hashable(prop, seen) {
+                return false;
+            }
+        }
+        for item in &schema.items {
+            if !self.hashable(item, seen) {
+                return false;
+            }
+        }
+        match schema.additional_properties {
+            Some(JsonValue::Bool(true)) => return false,
+            // Of the map types, only `BTreeMap` implements Hash
+            Some(JsonValue::Object(_)) if self.config.map_type != process::MapType::BTreeMap => {
+                return false
+            }
+            Some(JsonValue::Object(_)) => match additional_properties(schema) {
+                Ok(Some(values)) => if !self.hashable(&values, seen) {
+                    return false;
+                },
+                Ok(None) => return false,
+                Err(_) => return false,
+            },
+            _ => {}
+        }
+        for subschemas in subschema_lists(schema) {
+            for sub in subschemas {
+                if !self.hashable(sub, seen) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn merge_into(
+        &self,
+        subschemas: &[Schema],
+        properties: &mut BTreeMap<String, Schema>,
+        required: &mut Vec<String>,
+    ) -> Result<()> {
+        for sub in subschemas {
+            let sub = match sub.ref_ {
+                Some(ref ref_) => {
+                    let refname = ref_name(ref_)?;
+                    match self.schemas.get(refname) {
+                        Some(schema) => schema,
+                        None => bail!("allOf reference {} not found in components", ref_),
+                    }
+                }
+                None => sub,
+            };
+            if let Some(ref nested) = sub.all_of {
+                self.merge_into(nested, properties, required)?;
+            }
+            for (prop_name, prop) in &sub.properties {
+                properties.insert(prop_name.clone(), prop.clone());
+            }
+            required.extend(sub.required.iter().cloned());
+        }
+        Ok(())
+    }
+
+    fn property_fields(
+        &mut self,
+        struct_name: &str,
+        properties: &BTreeMap<String, Schema>,
+        required: &[String],
+    ) -> Result<Vec<Field>> {
+        let mut fields = Vec::new();
+        for (prop_name, prop) in properties {
+            let nested_name = format!("{}{}", struct_name, prop_name.to_class_case());
+            let name = field_name(prop_name);
+            let mut attrs: Vec<String> = prop.description
+                .as_ref()
+                .map(|d| doc_comment(d).lines().map(|line| line.to_string()).collect())
+                .unwrap_or_default();
+            if name != *prop_name {
+                attrs.push(format!("#[serde(rename = {:?})]", prop_name));
+            }
+            if prop.deprecated == Some(true) {
+                attrs.push("#[deprecated(note = \"deprecated in the API spec\")]".into());
+            }
+            if prop.write_only == Some(true) {
+                // Such as a password, which is accepted but never sent back
+                attrs.push("#[serde(skip_serializing)]".into());
+            }
+            // A missing property with a default takes that value, so needs no `Option`
+            let mut is_required = required.contains(prop_name);
+            let mut default = None;
+            if !is_required {
+                if let Some(ref value) = prop.default {
+                    match default_expr(prop, &nested_name, value, self.options())? {
+                        Some(expr) => {
+                            let function = format!("default_{}_{}", struct_name.to_snake_case(), name);
+                            self.out.push(format!(
+                                "fn {}() -> {} {{\n    {}\n}}\n",
+                                function,
+                                required_type(prop, &nested_name, self.options())?,
+                                expr
+                            ));
+                            attrs.push(format!("#[serde(default = {:?})]", function));
+                            default = Some(function);
+                            is_required = true;
+                        }
+                        None => warn(format!(
+                            "The default of property '{}' of {} cannot be generated and has been ignored",
+                            prop_name, struct_name
+                        )),
+                    }
+                }
+            }
+            let mut double_option = false;
+            let type_ = if let Some(def) = EnumDef::from_schema(&nested_name, prop) {
+                self.out.push(def.render());
+                if is_required {
+                    nested_name
+                } else {
+                    format!("Option<{}>", nested_name)
+                }
+            } else {
+                let options = self.options();
+                let native = NativeType::from_json_schema_with(prop, is_required, options)?;
+                if let NativeType::Option(ref inner) = native {
+                    double_option = inner.is_option();
+                }
+                // The default is a literal of the unvalidated type, as is
+                // a parameter's
+                let plain = is_scalar(prop, options) || is_plain_array(prop, options);
+                let newtype = if default.is_none() && plain {
+                    validated_newtype(&nested_name, prop, self)?
+                } else {
+                    None
+                };
+                match newtype {
+                    Some(newtype) => {
+                        self.out.push(newtype.render());
+                        wrap_options(&native, nested_name.clone())
+                    }
+                    None => {
+                        let mut nested = Vec::new();
+                        let type_ = native.render_with(&mut |schema| {
+                            nested.push(schema.clone());
+                            nested_name.clone()
+                        });
+                        for schema in nested {
+                            self.nested(&nested_name, &schema)?;
+                        }
+                        type_
+                    }
+                }
+            };
+            if double_option {
+                // Absent deserializes to `None` and an explicit null to `Some(None)`
+                attrs.push(
+                    "#[serde(default, skip_serializing_if = \"Option::is_none\", \
+                     deserialize_with = \"deserialize_some\")]"
+                        .into(),
+                );
+            }
+            fields.push(Field {
+                name,
+                type_,
+                attrs,
+                default,
+            });
+        }
+        Ok(fields)
+    }
+}
+
+// The checks enforcing a scalar or array schema's validation keywords, as
+// statements which return early with an error message.
+//
+// Floats cannot in general be checked exactly against `multipleOf` (0.3 is not
+// a multiple of 0.1 in binary floating point), so float values are accepted if
+// they are within a small tolerance of a multiple. Use integers (e.g. cents
+// rather than dollars) where exactness matters.
+fn validation_checks(schema: &Schema) -> Result<Vec<String>> {
+    use openapi3::objects::SimpleTypes::{Array, Integer, String as StringType};
+    let is_integer = schema.type_ == [Integer];
+    let mut checks = Vec::new();
+    if schema.type_ == [Array] {
+        if let Some(min_items) = schema.min_items {
+            checks.push(format!(
+                "        if value.len() < {min} {{\n            \
+                 return Err(format!(\"{{}} items is fewer than the minimum of {min}\", value.len()));\n        }}\n",
+                min = min_items
+            ));
+        }
+        if let Some(max_items) = schema.max_items {
+            checks.push(format!(
+                "        if value.len() > {max} {{\n            \
+                 return Err(format!(\"{{}} items is more than the maximum of {max}\", value.len()));\n        }}\n",
+                max = max_items
+            ));
+        }
+        // Items are compared as JSON, as generated types need not be `PartialEq`
+        if schema.unique_items == Some(true) {
+            checks.push(
+                "        let mut seen = Vec::new();\n        \
+                 for item in &value {\n            \
+                 let item = ::serde_json::to_value(item).map_err(|e| e.to_string())?;\n            \
+                 if seen.contains(&item) {\n                \
+                 return Err(format!(\"{} appears more than once\", item));\n            }\n            \
+                 seen.push(item);\n        }\n"
+                    .into(),
+            );
+        }
+    }
+    if let (true, Some(pattern)) = (schema.type_ == [StringType], schema.pattern.as_ref()) {
+        // Catch bad patterns now rather than when the generated code first runs
+        Regex::new(pattern).chain_err(|| format!("Invalid pattern: {}", pattern))?;
+        checks.push(format!(
+            "        lazy_static! {{\n            \
+             static ref PATTERN: ::regex::Regex = ::regex::Regex::new({}).unwrap();\n        }}\n        \
+             if !PATTERN.is_match(&value) {{\n            \
+             return Err(format!(\"{{:?}} does not match the pattern {{:?}}\", value, PATTERN.as_str()));\n        \
+             }}\n",
+            raw_string_literal(pattern)
+        ));
+    }
+    if schema.type_ == [StringType] {
+        if let Some(min_length) = schema.min_length {
+            checks.push(format!(
+                "        if value.chars().count() < {min} {{\n            \
+                 return Err(format!(\"{{:?}} is shorter than the minimum length of {min}\", value));\n        }}\n",
+                min = min_length
+            ));
+        }
+        if let Some(max_length) = schema.max_length {
+            checks.push(format!(
+                "        if value.chars().count() > {max} {{\n            \
+                 return Err(format!(\"{{:?}} is longer than the maximum length of {max}\", value));\n        }}\n",
+                max = max_length
+            ));
+        }
+    }
+    if let Some(minimum) = schema.minimum {
+        checks.push(format!(
+            "        if (value as f64) < {min:?} {{\n            \
+             return Err(format!(\"{{}} is less than the minimum of {min}\", value));\n        }}\n",
+            min = minimum
+        ));
+    }
+    if let Some(maximum) = schema.maximum {
+        checks.push(format!(
+            "        if (value as f64) > {max:?} {{\n            \
+             return Err(format!(\"{{}} is greater than the maximum of {max}\", value));\n        }}\n",
+            max = maximum
+        ));
+    }
+    if let Some(multiple_of) = schema.multiple_of {
+        let error = format!(
+            "            return Err(format!(\"{{}} is not a multiple of {}\", value));\n",
+            multiple_of
+        );
+        if is_integer && multiple_of.fract() == 0.0 {
+            checks.push(format!(
+                "        if value % {} != 0 {{\n{}        }}\n",
+                multiple_of as i64,
+                error
+            ));
+        } else {
+            checks.push(format!(
+                "        let quotient = value as f64 / {:?};\n        \
+                 if (quotient - quotient.round()).abs() > 1e-9 {{\n{}        }}\n",
+                multiple_of,
+                error
+            ));
+        }
+    }
+    Ok(checks)
+}
+
+// Embed arbitrary text as a raw string literal, with enough hashes that no
+// quote inside it can terminate the literal early
+fn raw_string_literal(text: &str) -> String {
+    let mut hashes = String::new();
+    while text.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{hashes}\"{}\"{hashes}", text, hashes = hashes)
+}
+
+/// Deserializer for `Option<Option<T>>` fields, which tells an explicit null
+/// apart from a missing property. Emitted alongside any type that needs it
+pub const DESERIALIZE_SOME: &str = r#"
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: ::serde::Deserialize<'de>,
+    D: ::serde::Deserializer<'de>,
+{
+    ::serde::Deserialize::deserialize(deserializer).map(Some)
+}
+"#;
+
+/// Whether generated code relies on `DESERIALIZE_SOME`
+pub fn needs_deserialize_some(code: &str) -> bool {
+    code.contains("deserialize_with = \"deserialize_some\"")
+}
+
+/// Bytes sent as base64 text, the type of `format: byte` strings
+pub const BASE64: &str = r#"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Base64(pub Vec<u8>);
+
+impl ::serde::Serialize for Base64 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        let text = ::base64::Engine::encode(&::base64::engine::general_purpose::STANDARD, &self.0);
+        serializer.serialize_str(&text)
+    }
+}
+
+impl<'de> ::serde::Deserialize<'de> for Base64 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        let text = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+        text.parse().map_err(::serde::de::Error::custom)
+    }
+}
+
+impl ::std::str::FromStr for Base64 {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        ::base64::Engine::decode(&::base64::engine::general_purpose::STANDARD, s)
+            .map(Base64)
+            .map_err(|e| e.to_string())
+    }
+}
+"#;
+
+/// Whether the schema, or any schema within it, is a `format: byte` string
+pub fn uses_base64(schema: &Schema) -> bool {
+    let is_byte = schema.type_ == [SimpleTypes::String]
+        && schema.format.as_ref().map_or(false, |format| format == "byte");
+    is_byte || schema.properties.values().any(uses_base64)
+        || schema.items.iter().any(uses_base64)
+        || subschema_lists(schema)
+            .into_iter()
+            .any(|subschemas| subschemas.iter().any(uses_base64))
+        || match additional_properties(schema) {
+            Ok(Some(values)) => uses_base64(&values),
+            _ => false,
+        }
+}
+
+pub(crate) const KEYWORDS: &[&str] = &[
+    "abstract", "alignof", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "offsetof", "override", "priv",
+    "proc", "pub", "pure", "ref", "return", "self", "sizeof", "static", "struct", "super",
+    "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where",
+    "while", "yield",
+];
+
+// A valid Rust field name for a property, following the `type_` convention
+// for keywords
+fn field_name(prop_name: &str) -> String {
+    let mut name: String = prop_name
+        .to_snake_case()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_numeric()) {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+fn is_read_only(schema: &Schema) -> bool {
+    schema.read_only == Some(true)
+}
+
+fn add_derive(derives: &mut Vec<String>, derive: &str) {
+    if !derives.iter().any(|existing| existing == derive) {
+        derives.push(derive.into());
+    }
+}
+
+// Whether the derive is a plausible path to a derive macro, e.g. `Default`
+// or `schemars::JsonSchema`
+fn is_derive_path(derive: &str) -> bool {
+    derive.split("::").all(|segment| {
+        segment.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Generate the Rust definition of a component schema, along with the
+/// definitions of any anonymous objects nested within it
+pub fn generate_type(
+    name: &str,
+    schema: &Schema,
+    schemas: &BTreeMap<String, Schema>,
+    config: &Config,
+) -> Result<String> {
+    use openapi3::objects::CodeGen;
+    for derive in &config.type_derives {
+        if !is_derive_path(derive) {
+            bail!("'{}' is not a valid derive", derive);
+        }
+    }
+    let mut gen = TypeGen {
+        schemas,
+        config,
+        out: Vec::new(),
+    };
+    if let Some(ref subschemas) = schema.all_of {
+        gen.all_of(&name.to_class_case(), subschemas)?;
+    } else if let Some(subschemas) = type_variants(schema) {
+        gen.one_of(&name.to_class_case(), schema, &subschemas)?;
+    } else if let Some(subschemas) = one_of(schema) {
+        gen.one_of(&name.to_class_case(), schema, subschemas)?;
+    } else if let Some(def) = EnumDef::from_schema(&name.to_class_case(), schema) {
+        gen.out.push(def.render());
+    } else if let native @ NativeType::Map(..) =
+        NativeType::from_json_schema_with(schema, true, gen.options())?
+    {
+        gen.map(&name.to_class_case(), &native)?;
+    } else if let NativeType::Anonymous(_) =
+        NativeType::from_json_schema_with(schema, true, gen.options())?
+    {
+        gen.object(&name.to_class_case(), schema)?;
+    } else {
+        match validated_newtype(&name.to_class_case(), schema, &gen)? {
+            Some(newtype) => gen.out.push(newtype.render()),
+            None if config.scalar_newtypes && schema.ref_.is_none() => {
+                match scalar_newtype(&name.to_class_case(), schema, &gen)? {
+                    Some(newtype) => gen.out.push(newtype.render()),
+                    None => gen.out.push(schema.generate_code(name)?),
+                }
+            }
+            None => gen.out.push(schema.generate_code(name)?),
+        }
+    }
+    Ok(gen.out.join("\n"))
+}
+
+// A newtype enforcing the schema's validation keywords, if newtypes are
+// enabled and the schema has any
+fn validated_newtype(name: &str, schema: &Schema, gen: &TypeGen) -> Result<Option<Newtype>> {
+    let config = gen.config;
+    let options = gen.options();
+    if !config.validated_newtypes || schema.ref_.is_some() {
+        return Ok(None);
+    }
+    // Arrays of inline objects would need types generating for their items
+    if schema.type_ == [SimpleTypes::Array] && !is_plain_array(schema, options) {
+        return Ok(None);
+    }
+    let checks = validation_checks(schema)?;
+    if checks.is_empty() {
+        return Ok(None);
+    }
+    let native = NativeType::from_json_schema_with(schema, true, options)?;
+    let inner = native.render_with(&mut |_| unreachable!());
+    let scalar = is_scalar(schema, options);
+    let scalar_items = schema.items.first().map_or(false, |items| is_scalar(items, options));
+    Ok(Some(Newtype {
+        name: name.into(),
+        inner,
+        checks,
+        scalar,
+        hash: config.derive_hash_eq && gen.hashable(schema, &mut BTreeSet::new()),
+        rocket_impls: config.framework == Framework::Rocket,
+        async_rocket: config.async_handlers,
+        scalar_items,
+    }))
+}
+
+// A newtype for a schema of a single scalar value, with `Config::scalar_newtypes`
+fn scalar_newtype(name: &str, schema: &Schema, gen: &TypeGen) -> Result<Option<ScalarNewtype>> {
+    use self::NativeType::*;
+    let native = NativeType::from_json_schema_with(schema, true, gen.options())?;
+    let parses = match native {
+        I32 | I64 | F32 | F64 | Bool | String | DateTime | Date | Uuid => true,
+        Base64 => false,
+        _ => return Ok(None),
+    };
+    Ok(Some(ScalarNewtype {
+        name: name.into(),
+        inner: native.render_with(&mut |_| unreachable!()),
+        derives: gen.derives(schema),
+        parses,
+        rocket_impls: gen.config.framework == Framework::Rocket,
+        async_rocket: gen.config.async_handlers,
+    }))
+}
+
+/// Whether a scalar or array schema has validation keywords, and so is
+/// generated as a validating newtype when `Config::validated_newtypes` is set
+pub fn is_validated(schema: &Schema, options: TypeOptions) -> bool {
+    (is_scalar(schema, options) || is_plain_array(schema, options))
+        && validation_checks(schema).map(|checks| !checks.is_empty()).unwrap_or(false)
+}
+
+// Whether the schema is an inline array of scalars or of named types, and so
+// has a type without generating any of its own
+fn is_plain_array(schema: &Schema, options: TypeOptions) -> bool {
+    match NativeType::from_json_schema_with(schema, true, options) {
+        Ok(NativeType::Array(_)) if schema.ref_.is_none() => schema
+            .items
+            .first()
+            .map_or(false, |items| items.ref_.is_some() || is_scalar(items, options)),
+        _ => false,
+    }
+}
+
+// Whether the schema is an inline number or string
+fn is_scalar(schema: &Schema, options: TypeOptions) -> bool {
+    use self::NativeType::*;
+    match NativeType::from_json_schema_with(schema, true, options) {
+        Ok(I32) | Ok(I64) | Ok(F32) | Ok(F64) | Ok(String) => schema.ref_.is_none(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn dog_components() -> (Schema, BTreeMap<String, Schema>) {
+        let pet: Schema = serde_json::from_value(json!({
+            "required": ["id", "name"],
+            "properties": {
+                "id": {"type": "integer"},
+                "name": {"type": "string"}
+            }
+        })).unwrap();
+        let dog: Schema = serde_json::from_value(json!({
+            "allOf": [
+                {"$ref": "#/components/schemas/Pet"},
+                {"properties": {"breed": {"type": "string"}}}
+            ]
+        })).unwrap();
+        let mut schemas = BTreeMap::new();
+        schemas.insert("Pet".into(), pet);
+        (dog, schemas)
+    }
+
+    #[test]
+    fn test_all_of_flatten() {
+        let (dog, schemas) = dog_components();
+        let config = Config {
+            all_of: AllOfStrategy::Flatten,
+            ..Config::default()
+        };
+        let code = generate_type("Dog", &dog, &schemas, &config).unwrap();
+        assert!(code.contains("pub struct Dog {"));
+        assert!(code.contains("#[serde(flatten)]\n    pub pet: Pet,"));
+        assert!(code.contains("pub breed: Option<String>,"));
+        assert!(!code.contains("pub id: i64"));
+    }
+
+    #[test]
+    fn test_all_of_merge() {
+        let (dog, schemas) = dog_components();
+        let config = Config {
+            all_of: AllOfStrategy::Merge,
+            ..Config::default()
+        };
+        let code = generate_type("Dog", &dog, &schemas, &config).unwrap();
+        assert!(code.contains("pub id: i64,"));
+        assert!(code.contains("pub name: String,"));
+        assert!(code.contains("pub breed: Option<String>,"));
+        assert!(!code.contains("flatten"));
+    }
+
+    #[test]
+    fn test_all_of_properties() {
+        let (dog, mut schemas) = dog_components();
+        schemas.insert("Dog".into(), dog);
+        let kennel: Schema = serde_json::from_value(json!({
+            "required": ["owner"],
+            "properties": {
+                "resident": {
+                    "allOf": [
+                        {"$ref": "#/components/schemas/Pet"},
+                        {"properties": {"since": {"type": "string"}}}
+                    ]
+                },
+                "owner": {
+                    "description": "A reference annotated through allOf",
+                    "allOf": [{"$ref": "#/components/schemas/Dog"}]
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Kennel", &kennel, &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub owner: Dog,"));
+        assert!(code.contains("pub resident: Option<KennelResident>,"));
+        assert!(code.contains("pub struct KennelResident {"));
+        assert!(code.contains("pub since: Option<String>,"));
+        assert!(code.contains("pub name: String,"));
+    }
+
+    #[test]
+    fn test_one_of_discriminator() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Cat": {"properties": {"lives": {"type": "integer"}}},
+            "Dog": {"properties": {"breed": {"type": "string"}}},
+            "Pet": {
+                "oneOf": [
+                    {"$ref": "#/components/schemas/Cat"},
+                    {"$ref": "#/components/schemas/Dog"}
+                ],
+                "discriminator": {"propertyName": "petType"}
+            }
+        })).unwrap();
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("#[serde(tag = \"petType\")]\npub enum Pet {\n    Cat(Cat),\n    Dog(Dog),\n}"));
+    }
+
+    #[test]
+    fn test_any_of_untagged() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Id": {
+                "anyOf": [
+                    {"type": "integer"},
+                    {"properties": {"name": {"type": "string"}}}
+                ]
+            }
+        })).unwrap();
+        let code = generate_type("Id", &schemas["Id"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("#[serde(untagged)]\npub enum Id {\n    Variant0(i64),\n    Variant1(IdVariant1),\n}"));
+        assert!(code.contains("pub struct IdVariant1 {"));
+    }
+
+    #[test]
+    fn test_hash_eq_derives() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Price": {"properties": {"amount": {"type": "number"}}},
+            "Item": {"properties": {"price": {"$ref": "#/components/schemas/Price"}}},
+            "Tag": {"properties": {"id": {"type": "integer"}}}
+        })).unwrap();
+        let config = Config {
+            derive_hash_eq: true,
+            ..Config::default()
+        };
+        let tag = generate_type("Tag", &schemas["Tag"], &schemas, &config).unwrap();
+        assert!(tag.contains("#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]"));
+        let price = generate_type("Price", &schemas["Price"], &schemas, &config).unwrap();
+        assert!(!price.contains("Eq"));
+        // Floats are found through references too
+        let item = generate_type("Item", &schemas["Item"], &schemas, &config).unwrap();
+        assert!(!item.contains("Eq"));
+
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Labels": {
+                "properties": {"id": {"type": "integer"}},
+                "additionalProperties": {"type": "string"}
+            }
+        })).unwrap();
+        let labels = generate_type("Labels", &schemas["Labels"], &schemas, &config).unwrap();
+        assert!(labels.contains("PartialEq, Eq, Hash)]"));
+        // HashMap implements neither
+        let config = Config {
+            derive_hash_eq: true,
+            map_type: process::MapType::HashMap,
+            ..Config::default()
+        };
+        let labels = generate_type("Labels", &schemas["Labels"], &schemas, &config).unwrap();
+        assert!(!labels.contains("Hash)]"));
+
+        // Validated newtypes derive them too, unless they wrap floats
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Pet": {
+                "properties": {
+                    "name": {"type": "string", "maxLength": 20},
+                    "tags": {"type": "array", "maxItems": 5, "items": {"type": "string"}},
+                    "weight": {"type": "number", "minimum": 0}
+                }
+            }
+        })).unwrap();
+        let config = Config {
+            derive_hash_eq: true,
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let pet = generate_type("Pet", &schemas["Pet"], &schemas, &config).unwrap();
+        assert!(pet.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]\npub struct PetName(String);"));
+        assert!(pet.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]\npub struct PetTags(Vec<String>);"));
+        assert!(pet.contains("#[derive(Debug, Clone, PartialEq, Serialize)]\npub struct PetWeight(f64);"));
+    }
+
+    #[test]
+    fn test_read_only_request_variant() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Pet": {
+                "required": ["id", "name"],
+                "properties": {
+                    "id": {"type": "integer", "readOnly": true},
+                    "name": {"type": "string"}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &Config::default()).unwrap();
+        assert!(!code.contains("PetRequest"));
+
+        let config = Config {
+            split_read_write: true,
+            ..Config::default()
+        };
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &config).unwrap();
+        let (request, response) = code.split_at(code.find("pub struct Pet {").unwrap());
+        assert!(request.contains("pub struct PetRequest {"));
+        assert!(request.contains("pub id: Option<i64>,"));
+        assert!(request.contains("pub name: String,"));
+        assert!(response.contains("pub id: i64,"));
+    }
+
+    #[test]
+    fn test_type_derives() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Pet": {
+                "properties": {"name": {"type": "string"}}
+            }
+        })).unwrap();
+        let config = Config {
+            type_derives: vec!["Debug".into(), "Default".into(), "schemars::JsonSchema".into()],
+            ..Config::default()
+        };
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &config).unwrap();
+        assert!(code.contains(
+            "#[derive(Debug, Clone, Serialize, Deserialize, Default, schemars::JsonSchema)]\n\
+             pub struct Pet {"
+        ));
+
+        for bad in &["Partial Eq", "", "Eq,Hash", "std::"] {
+            let config = Config {
+                type_derives: vec![bad.to_string()],
+                ..Config::default()
+            };
+            assert!(generate_type("Pet", &schemas["Pet"], &schemas, &config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_write_only_properties() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "User": {
+                "required": ["id", "name", "password"],
+                "properties": {
+                    "id": {"type": "integer", "readOnly": true},
+                    "name": {"type": "string"},
+                    "password": {"type": "string", "writeOnly": true}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("User", &schemas["User"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("    #[serde(skip_serializing)]\n    pub password: String,"));
+        assert!(!code.contains("skip_deserializing"));
+
+        let config = Config {
+            split_read_write: true,
+            ..Config::default()
+        };
+        let code = generate_type("User", &schemas["User"], &schemas, &config).unwrap();
+        let (request, response) = code.split_at(code.find("pub struct User {").unwrap());
+        assert!(request.contains("    #[serde(skip_deserializing)]\n    pub id: Option<i64>,"));
+        assert!(request.contains("    pub name: String,"));
+        assert!(!response.contains("skip_deserializing"));
+        assert!(response.contains("    pub id: i64,"));
+        assert!(response.contains("    #[serde(skip_serializing)]\n    pub password: String,"));
+    }
+
+    #[test]
+    fn test_multiple_of_newtype() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Quantity": {"type": "integer", "multipleOf": 5},
+            "Price": {"type": "number", "multipleOf": 0.01}
+        })).unwrap();
+        let config = Config {
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let code = generate_type("Quantity", &schemas["Quantity"], &schemas, &config).unwrap();
+        assert!(code.contains("pub struct Quantity(i64);"));
+        assert!(code.contains("if value % 5 != 0 {"));
+        assert!(code.contains("Quantity::new(value).map_err(::serde::de::Error::custom)"));
+
+        let code = generate_type("Price", &schemas["Price"], &schemas, &config).unwrap();
+        assert!(code.contains("pub struct Price(f64);"));
+        assert!(code.contains("let quotient = value as f64 / 0.01;"));
+    }
+
+    #[test]
+    fn test_pattern_newtype() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Sku": {"type": "string", "pattern": "^[A-Z]{3}-\\d+$"},
+            "Quoted": {"type": "string", "pattern": "^\"#[a-z]+\"$"},
+            "Broken": {"type": "string", "pattern": "(unclosed"}
+        })).unwrap();
+        let config = Config {
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let code = generate_type("Sku", &schemas["Sku"], &schemas, &config).unwrap();
+        assert!(code.contains("pub struct Sku(String);"));
+        assert!(code.contains("::regex::Regex::new(r\"^[A-Z]{3}-\\d+$\").unwrap();"));
+        assert!(code.contains("if !PATTERN.is_match(&value) {"));
+        let pattern = Regex::new(&schemas["Sku"].pattern.clone().unwrap()).unwrap();
+        assert!(pattern.is_match("ABC-123"));
+        assert!(!pattern.is_match("abc-123"));
+
+        let code = generate_type("Quoted", &schemas["Quoted"], &schemas, &config).unwrap();
+        assert!(code.contains("::regex::Regex::new(r##\"^\"#[a-z]+\"$\"##)"));
+
+        assert!(generate_type("Broken", &schemas["Broken"], &schemas, &config).is_err());
+    }
+
+    #[test]
+    fn test_length_newtypes() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Pet": {
+                "required": ["name"],
+                "properties": {
+                    "name": {"type": "string", "minLength": 1, "maxLength": 20},
+                    "nickname": {"type": "string", "pattern": "^[a-z]+$"},
+                    "tag": {"type": "string"},
+                    "limit": {"type": "integer", "minimum": 1, "default": 20}
+                }
+            }
+        })).unwrap();
+        let config = Config {
+            validated_newtypes: true,
+            ..Config::default()
+        };
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &config).unwrap();
+        assert!(code.contains("pub struct PetName(String);"));
+        assert!(code.contains("if value.chars().count() < 1 {"));
+        assert!(code.contains("if value.chars().count() > 20 {"));
+        assert!(code.contains("pub name: PetName,"));
+        assert!(code.contains("pub struct PetNickname(String);"));
+        assert!(code.contains("pub nickname: Option<PetNickname>,"));
+        assert!(code.contains("pub tag: Option<String>,"));
+        assert!(code.contains("impl ::std::str::FromStr for PetName {"));
+        // A property with a default keeps the type of its default
+        assert!(code.contains("fn default_pet_limit() -> i64 {\n    20\n}"));
+        assert!(code.contains("pub limit: i64,"));
+        assert!(!code.contains("PetLimit"));
+
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub name: String,"));
+    }
+
+    #[test]
+    fn test_additional_properties() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Labels": {
+                "type": "object",
+                "additionalProperties": {"type": "string"}
+            },
+            "Pet": {
+                "required": ["id"],
+                "properties": {"id": {"type": "integer"}},
+                "additionalProperties": {
+                    "properties": {"value": {"type": "string"}}
+                }
+            }
+        })).unwrap();
+        let config = Config::default();
+        let code = generate_type("Labels", &schemas["Labels"], &schemas, &config).unwrap();
+        assert!(code.contains("pub type Labels = ::std::collections::BTreeMap<String, String>;"));
+
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &config).unwrap();
+        assert!(code.contains("pub struct PetExtra {"));
+        assert!(code.contains("#[serde(flatten)]"));
+        assert!(code.contains("pub extra: ::std::collections::BTreeMap<String, PetExtra>,"));
+
+        // A property named like the field keeps its name
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Note": {
+                "properties": {"extra": {"type": "string"}},
+                "additionalProperties": {"type": "string"}
+            }
+        })).unwrap();
+        let code = generate_type("Note", &schemas["Note"], &schemas, &config).unwrap();
+        assert!(code.contains("pub extra: Option<String>,"));
+        assert!(code.contains("pub extra_2: ::std::collections::BTreeMap<String, String>,"));
+    }
+
+    #[test]
+    fn test_map_type() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Labels": {
+                "type": "object",
+                "additionalProperties": {"type": "string"}
+            }
+        })).unwrap();
+        let labels = |map_type| {
+            let config = Config {
+                map_type,
+                ..Config::default()
+            };
+            generate_type("Labels", &schemas["Labels"], &schemas, &config).unwrap()
+        };
+        assert!(labels(process::MapType::BTreeMap)
+            .contains("pub type Labels = ::std::collections::BTreeMap<String, String>;"));
+        assert!(labels(process::MapType::HashMap)
+            .contains("pub type Labels = ::std::collections::HashMap<String, String>;"));
+        assert!(labels(process::MapType::IndexMap)
+            .contains("pub type Labels = ::indexmap::IndexMap<String, String>;"));
+    }
+
+    #[test]
+    fn test_field_documentation() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Pet": {
+                "properties": {
+                    "id": {"type": "integer", "description": "The pet's unique id"},
+                    "name": {"type": "string"}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("    /// The pet's unique id\n    pub id: Option<i64>,"));
+        assert!(code.contains("Option<i64>,\n    pub name: Option<String>,"));
+    }
+
+    #[test]
+    fn test_base64_properties() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Upload": {
+                "required": ["data"],
+                "properties": {
+                    "data": {"type": "string", "format": "byte"},
+                    "raw": {"type": "string", "format": "binary"}
+                }
+            },
+            "Pet": {
+                "properties": {"name": {"type": "string"}}
+            }
+        })).unwrap();
+        let code = generate_type("Upload", &schemas["Upload"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub data: Base64,"));
+        assert!(code.contains("pub raw: Option<Vec<u8>>,"));
+        assert!(uses_base64(&schemas["Upload"]));
+        assert!(!uses_base64(&schemas["Pet"]));
+    }
+
+    #[test]
+    fn test_field_renames() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Thing": {
+                "properties": {
+                    "some type": {"type": "integer"},
+                    "type": {"type": "string"},
+                    "match": {"type": "string"},
+                    "async": {"type": "boolean"},
+                    "2fa": {"type": "boolean"},
+                    "petId": {"type": "integer"},
+                    "name": {"type": "string"}
+                }
+            }
+        })).unwrap();
+        let code =
+            generate_type("Thing", &schemas["Thing"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("    #[serde(rename = \"some type\")]\n    pub some_type: Option<i64>,"));
+        assert!(code.contains("    #[serde(rename = \"type\")]\n    pub type_: Option<String>,"));
+        assert!(code.contains("    #[serde(rename = \"match\")]\n    pub match_: Option<String>,"));
+        assert!(code.contains("    #[serde(rename = \"async\")]\n    pub async_: Option<bool>,"));
+        assert!(code.contains("    #[serde(rename = \"2fa\")]\n    pub _2"));
+        assert!(code.contains("    #[serde(rename = \"petId\")]\n    pub pet_id: Option<i64>,"));
+        assert!(!code.contains("rename = \"name\""));
+    }
+
+    #[test]
+    fn test_nullable_fields() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Thing": {
+                "required": ["a", "b"],
+                "properties": {
+                    "a": {"type": "string"},
+                    "b": {"type": "string", "nullable": true},
+                    "c": {"type": "string"},
+                    "d": {"type": "string", "nullable": true}
+                }
+            }
+        })).unwrap();
+        let code =
+            generate_type("Thing", &schemas["Thing"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("    pub a: String,"));
+        assert!(code.contains("    pub b: Option<String>,"));
+        assert!(code.contains("    pub c: Option<String>,"));
+        assert!(code.contains("deserialize_with = \"deserialize_some\")]\n    pub d: Option<Option<String>>,"));
+        assert!(needs_deserialize_some(&code));
+    }
+
+    #[test]
+    fn test_property_defaults() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Page": {
+                "properties": {
+                    "limit": {"type": "integer", "default": 20},
+                    "ratio": {"type": "number", "default": 1},
+                    "sort": {"type": "string", "enum": ["asc", "desc"], "default": "desc"},
+                    "cursor": {"type": "string"}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Page", &schemas["Page"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("fn default_page_limit() -> i64 {\n    20\n}"));
+        assert!(code.contains("    #[serde(default = \"default_page_limit\")]\n    pub limit: i64,"));
+        assert!(code.contains("fn default_page_ratio() -> f64 {\n    1.0\n}"));
+        assert!(code.contains("fn default_page_sort() -> PageSort {\n    PageSort::Desc\n}"));
+        assert!(code.contains("pub sort: PageSort,"));
+        assert!(code.contains("pub cursor: Option<String>,"));
+    }
+
+    #[test]
+    fn test_multiple_types() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Id": {"type": ["string", "integer"]},
+            "Item": {
+                "required": ["id", "label"],
+                "properties": {
+                    "id": {"type": ["integer", "string", "null"]},
+                    "label": {"type": ["string", "null"]}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Id", &schemas["Id"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains(
+            "#[serde(untagged)]\npub enum Id {\n    String(String),\n    Integer(i64),\n}"
+        ));
+
+        let code = generate_type("Item", &schemas["Item"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub id: Option<ItemId>,"));
+        assert!(code.contains("pub enum ItemId {\n    Integer(i64),\n    String(String),\n}"));
+        assert!(code.contains("pub label: Option<String>,"));
+    }
+
+    #[test]
+    fn test_string_enum() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Kind": {"type": "string", "enum": ["cat", "dog", "guinea pig", "3-toed sloth", "-"]},
+            "Pet": {
+                "required": ["kind"],
+                "properties": {
+                    "kind": {"type": "string", "enum": ["cat", "dog"]},
+                    "size": {"type": "string", "enum": ["small", "LARGE"]}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Kind", &schemas["Kind"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub enum Kind {"));
+        assert!(!code.contains("pub type Kind"));
+        assert!(code.contains("    #[serde(rename = \"cat\")]\n    Cat,"));
+        assert!(code.contains("    #[serde(rename = \"guinea pig\")]\n    GuineaPig,"));
+        assert!(code.contains("    #[serde(rename = \"3-toed sloth\")]\n    V3"));
+        assert!(code.contains("    #[serde(rename = \"-\")]\n    Variant4,"));
+
+        // `Self` is a keyword
+        let schema: Schema =
+            serde_json::from_value(json!({"type": "string", "enum": ["self", "other"]})).unwrap();
+        let code = generate_type("Target", &schema, &schemas, &Config::default()).unwrap();
+        assert!(code.contains("    #[serde(rename = \"self\")]\n    Self_,"));
+        assert!(code.contains("    #[serde(rename = \"other\")]\n    Other,"));
+
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub enum PetKind {"));
+        assert!(code.contains("pub enum PetSize {"));
+        assert!(code.contains("pub kind: PetKind,"));
+        assert!(code.contains("pub size: Option<PetSize>,"));
+
+        // Values sharing a name are numbered, and repeated values left out
+        let schema: Schema =
+            serde_json::from_value(json!({"type": "string", "enum": ["a-b", "a_b", "a-b"]})).unwrap();
+        let code = generate_type("Sep", &schema, &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub enum Sep {\n    #[serde(rename = \"a-b\")]\n    AB,\n    \
+             #[serde(rename = \"a_b\")]\n    AB2,\n}"));
+    }
+
+    #[test]
+    fn test_integer_enum() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Status": {"type": "integer", "enum": [1, 2, -3]},
+            "Priority": {
+                "type": "integer",
+                "enum": [0, 10],
+                "x-enum-varnames": ["low", "high"]
+            },
+            "Task": {
+                "properties": {
+                    "priority": {"type": "integer", "enum": [0, 10], "default": 10}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("Status", &schemas["Status"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains(
+            "#[serde(into = \"i64\", try_from = \"i64\")]\n#[repr(i64)]\npub enum Status {\n    \
+             Value1 = 1,\n    Value2 = 2,\n    ValueMinus3 = -3,\n}"
+        ));
+        assert!(code.contains("impl From<Status> for i64 {"));
+        assert!(code.contains("            2 => Ok(Status::Value2),\n"));
+        assert!(code.contains("            _ => Err(format!(\"{} is not a valid Status\", value)),"));
+
+        let code = generate_type("Priority", &schemas["Priority"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub enum Priority {\n    Low = 0,\n    High = 10,\n}"));
+
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "integer",
+            "enum": [0, 1, 1, 2],
+            "x-enum-varnames": ["low", "high", "top", "high"]
+        })).unwrap();
+        let code = generate_type("Level", &schema, &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub enum Level {\n    Low = 0,\n    High = 1,\n    High2 = 2,\n}"));
+
+        let code = generate_type("Task", &schemas["Task"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub enum TaskPriority {\n    Value0 = 0,\n    Value10 = 10,\n}"));
+        assert!(code.contains("fn default_task_priority() -> TaskPriority {\n    TaskPriority::Value10\n}"));
+    }
+
+    #[test]
+    fn test_scalar_newtypes() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "Email": {"type": "string", "format": "email"},
+            "Ratio": {"type": "number"}
+        })).unwrap();
+        let code = generate_type("Email", &schemas["Email"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("Email = String"));
+
+        let config = Config {
+            scalar_newtypes: true,
+            ..Config::default()
+        };
+        let code = generate_type("Email", &schemas["Email"], &schemas, &config).unwrap();
+        assert!(!code.contains("pub type Email"));
+        assert!(code.contains(
+            "#[derive(Debug, Clone, Serialize, Deserialize)]\n\
+             #[serde(transparent)]\n\
+             pub struct Email(pub String);\n"
+        ));
+        assert!(code.contains("impl ::std::str::FromStr for Email {"));
+        assert!(code.contains("impl<'a> ::rocket::request::FromParam<'a> for Email {"));
+        let code = generate_type("Ratio", &schemas["Ratio"], &schemas, &config).unwrap();
+        assert!(code.contains("pub struct Ratio(pub f64);"));
+
+        // Rocket 0.5 has traits of its own for parameters
+        let config = Config {
+            scalar_newtypes: true,
+            async_handlers: true,
+            ..Config::default()
+        };
+        let code = generate_type("Email", &schemas["Email"], &schemas, &config).unwrap();
+        assert!(code.contains("fn from_param(param: &'a str) -> Result<Self, String> {"));
+        assert!(code.contains("impl<'v> ::rocket::form::FromFormField<'v> for Email {"));
+        assert!(!code.contains("RawStr"));
+    }
+
+    #[test]
+    fn test_builders() {
+        let schemas: BTreeMap<String, Schema> = serde_json::from_value(json!({
+            "NewPet": {
+                "required": ["name"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "tag": {"type": "string"},
+                    "legs": {"type": "integer", "default": 4}
+                }
+            }
+        })).unwrap();
+        let code = generate_type("NewPet", &schemas["NewPet"], &schemas, &Config::default()).unwrap();
+        assert!(!code.contains("NewPetBuilder"));
+
+        let config = Config {
+            builders: true,
+            ..Config::default()
+        };
+        let code = generate_type("NewPet", &schemas["NewPet"], &schemas, &config).unwrap();
+        assert!(code.contains(
+            "#[derive(Debug, Clone, Default)]\npub struct NewPetBuilder {\n    \
+             legs: Option<i64>,\n    name: Option<String>,\n    tag: Option<String>,\n}"
+        ));
+        assert!(code.contains("    pub fn builder() -> NewPetBuilder {\n"));
+        assert!(code.contains("    pub fn with_legs(mut self, legs: i64) -> Self {\n"));
+        assert!(code.contains("    pub fn with_name(mut self, name: String) -> Self {\n"));
+        assert!(code.contains(
+            "    pub fn with_tag(mut self, tag: String) -> Self {\n        \
+             self.tag = Some(tag);\n        self\n    }"
+        ));
+        assert!(code.contains(
+            "            legs: self.legs.unwrap_or_else(default_new_pet_legs),\n            \
+             name: self.name.ok_or_else(|| \"name is required\".to_string())?,\n            \
+             tag: self.tag,\n"
+        ));
+    }
+
+    #[test]
+    fn test_property_requiredness() {
+        let yaml = include_str!("../example_apis/petstore.yaml");
+        let spec = ::OpenApi::from_string(yaml).unwrap();
+        let schemas = spec.components.unwrap().schemas.unwrap();
+        let code = generate_type("Pet", &schemas["Pet"], &schemas, &Config::default()).unwrap();
+        assert!(code.contains("pub id: i64,"));
+        assert!(code.contains("pub name: String,"));
+        assert!(code.contains("pub tag: Option<String>,"));
+    }
+}